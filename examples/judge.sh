@@ -0,0 +1,8 @@
+#!/bin/sh
+# A trivial judge for `--interactive`: sends every item of the input file (its last argument) to
+# the solution on its own line, and expects each one echoed back. Exits with 1 on a wrong reply.
+for item in $(cat "$1"); do
+    echo "$item"
+    read -r reply || exit 1
+    [ "$reply" = "$item" ] || exit 1
+done
@@ -1,41 +1,332 @@
-use std::{fmt::Display, fs::canonicalize, path::PathBuf};
+use std::{ffi::OsString, fmt::Display, fs::{canonicalize, read_to_string}, path::{Path, PathBuf}, time::Duration};
 
-use clap::Parser;
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
 use is_executable::IsExecutable;
+use serde::Deserialize;
 
-use crate::error::{AppResult, AppError};
+use beanfuzz::{unescape, AppError, AppResult, Value, DEFAULT_MAX_INPUT_BYTES, DEFAULT_MAX_RETRIES, EXIT_USAGE_ERROR};
+pub(crate) use beanfuzz::{InputMode, OutputFormat};
+
+use crate::file_handling::STDIN_PATH;
+
+/// Format of the log file.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LogFormat {
+    /// Human-readable blocks of text
+    #[default]
+    Text,
+    /// One JSON object per failing test, for scripts
+    Jsonl
+}
+
+/// Exit codes, as listed after the help.
+const EXIT_CODES_HELP: &str = "Exit codes:\n  0  every test passed\n  1  a test failed or crashed\n  2  the run couldn't go on, or tests errored out with --error-is-failure\n  3  invalid arguments, config file or spec";
 
 /// Beanfuzz: test output against two executables, used to test competitive programming executables.
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = EXIT_CODES_HELP)]
 pub(crate) struct CLIArgs {
-    /// Path to the fuzzing information
+    /// Path to the fuzzing information, or `-` to read it from stdin
     #[arg()]
     pub(crate) fuzz_data_filepath: PathBuf,
 
     /// Path to the first executable
-    #[arg()]
-    pub(crate) executable_a: PathBuf,
+    #[arg(required_unless_present_any = ["dry_run", "list_vars", "config"])]
+    pub(crate) executable_a: Option<PathBuf>,
 
     /// Path to the second executable
-    #[arg()]
-    pub(crate) executable_b: PathBuf,
+    #[arg(required_unless_present_any = ["dry_run", "list_vars", "config", "expected", "self_check"], conflicts_with = "expected")]
+    pub(crate) executable_b: Option<PathBuf>,
+
+    /// Instead of running a second executable, compare the first executable's output against
+    /// stored expected outputs. Only works with --corpus-in: the expected output of a saved input
+    /// is the file of the same name with an `out` extension in DIR (e.g `<DIR>/00000001.out` for
+    /// `00000001.txt`). A test errors out when its file is missing
+    #[arg(long = "expected", value_name = "DIR", default_value = None)]
+    pub(crate) expected: Option<PathBuf>,
+
+    /// Instead of running a second executable, run the first one twice on every input and compare
+    /// its two outputs, to catch nondeterminism (e.g unseeded randomness or undefined behavior).
+    /// Crashes of the second run are reported as executable B's
+    #[arg(long = "self-check", conflicts_with_all = ["executable_b", "expected"])]
+    pub(crate) self_check: bool,
+
+    /// File of known-good input and output pairs, ran through the second executable before
+    /// fuzzing: the run stops when its output disagrees with any of them. Each case is its input,
+    /// a `----` line, then its expected output, and cases are separated by `~~~~` lines
+    #[arg(long = "sanity-input", value_name = "FILE", default_value = None, conflicts_with = "expected")]
+    pub(crate) sanity_input: Option<PathBuf>,
+
+    /// More spec files whose lines are parsed after the spec's own, as if they were a single file
+    /// (e.g shared constraints kept apart from a problem's input order). Mixed specs get them too
+    #[arg(long = "spec", value_name = "FILE")]
+    pub(crate) spec: Vec<PathBuf>,
+
+    /// Maximum of the expressions made of a lower bound and a single variable group (e.g
+    /// `1 <= A`), which are invalid without it
+    #[arg(long = "default-max", value_name = "MAX", default_value = None, allow_negative_numbers = true)]
+    pub(crate) default_max: Option<Value>,
+
+    /// Mix more spec files into the run, as FILE:WEIGHT pairs: every input is generated from a
+    /// single spec, picked at random according to the weights. The positional spec takes part
+    /// with a weight of 1, unless it is listed as well (e.g `--mix small.fuzz:7 large.fuzz:3`
+    /// with `small.fuzz` as the positional spec). Sweeps only cover the positional spec
+    #[arg(long = "mix", num_args = 1.., value_name = "FILE:WEIGHT")]
+    pub(crate) mix: Option<Vec<String>>,
 
-    /// Input separator
+    /// TOML file holding default values for the other arguments, keyed by their field names (e.g
+    /// `how_many_times = 500`). Arguments given on the command line take precedence
+    #[arg(long = "config", default_value = None)]
+    pub(crate) config: Option<PathBuf>,
+
+    /// Input separator. Escape sequences (`\n`, `\t`, `\r`, `\s` and `\\`) are interpreted
     #[arg(short = 's', default_value = " ")]
     pub(crate) input_sep: String,
 
-    /// Output separator
+    /// Output separator, between the tokens of an output. Escape sequences are interpreted like
+    /// the input separator's
     #[arg(short = 'o', default_value = " ")]
     pub(crate) output_sep: String,
 
+    /// Separator between the records (e.g lines) of an output. When given, outputs are compared
+    /// in two levels: they must have as many records, and each pair of records must have equal
+    /// tokens (split by the output separator). Escape sequences are interpreted like the output
+    /// separator's
+    #[arg(long = "output-record-sep", default_value = None)]
+    pub(crate) output_record_sep: Option<String>,
+
+    /// Separator between the records of an input: the rows of a matrix, the edges of a tree, the
+    /// lines of a wrapped array and the blocks of --blocks. A newline by default. Escape sequences are interpreted like
+    /// the input separator's
+    #[arg(long = "input-record-sep", default_value = None)]
+    pub(crate) input_record_sep: Option<String>,
+
+    /// Compare outputs as multisets of records, ignoring their order (e.g for problems accepting
+    /// the answers in any order). Records are lines unless --output-record-sep is given, and each
+    /// record's tokens are still compared exactly
+    #[arg(long = "unordered")]
+    pub(crate) unordered: bool,
+
+    /// Compare outputs case-insensitively (e.g for problems accepting `YES` as well as `yes`). The
+    /// outputs are still logged verbatim
+    #[arg(long = "ignore-case")]
+    pub(crate) ignore_case: bool,
+
+    /// How the executables get their input
+    #[arg(long = "input-mode", value_enum, default_value_t = InputMode::Stdin)]
+    pub(crate) input_mode: InputMode,
+
+    /// Run an interactive judge for each executable instead of feeding it the input. The judge
+    /// gets the input file's path as its last argument and converses with the executable over
+    /// their stdin and stdout. What the executables sent is compared, and a judge exiting with a
+    /// nonzero status (e.g on a wrong answer) counts as the executable crashing
+    #[arg(long = "interactive", value_name = "JUDGE")]
+    pub(crate) interactive: Option<PathBuf>,
+
+    /// Longest input allowed, in bytes. A test whose input would be longer errors out before the
+    /// input gets generated, instead of exhausting the memory
+    #[arg(long = "max-input-bytes", default_value_t = DEFAULT_MAX_INPUT_BYTES)]
+    pub(crate) max_input_bytes: u64,
+
+    /// How many times a test case is generated again when it breaks a constraint (e.g `A != B`) or
+    /// a variable shared between expressions doesn't fit one of them, before the test is abandoned.
+    /// Abandoned tests count as errors, and are reported on their own in the summary
+    #[arg(long = "max-retries", default_value_t = DEFAULT_MAX_RETRIES)]
+    pub(crate) max_retries: u64,
+
+    /// Generate a variable appearing in multiple expressions again in each of them, keeping the
+    /// last value. By default, it is generated once and the other expressions fit around it
+    #[arg(long = "independent-vars")]
+    pub(crate) independent_vars: bool,
+
+    /// Allow arrays of length 0 (e.g `A[N]#` with `0 <= N <= 10`), which generate no items while
+    /// their length is still printed. Negative lengths are still rejected
+    #[arg(long = "allow-empty-arrays")]
+    pub(crate) allow_empty_arrays: bool,
+
+    /// Allow values outside the 64-bit range (e.g `1 <= N <= 10^19`), up to the 128-bit range.
+    /// Without it, such bounds are rejected
+    #[arg(long = "bigint")]
+    pub(crate) bigint: bool,
+
+    /// Make an executable printing nothing an error for that test. By default, two empty outputs
+    /// are equal, and an empty output differs from any other
+    #[arg(long = "require-output")]
+    pub(crate) require_output: bool,
+
     /// Log file
     #[arg(short = 'f', default_value = None)]
     pub(crate) log_file: Option<PathBuf>,
 
+    /// Append to the log file instead of overwriting it
+    #[arg(long = "log-append")]
+    pub(crate) log_append: bool,
+
+    /// Format of the log file
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text)]
+    pub(crate) log_format: LogFormat,
+
+    /// Log both full outputs of failing tests in text logs, not just where they first differ.
+    /// JSONL logs always hold both outputs
+    #[arg(long = "log-full-outputs")]
+    pub(crate) log_full_outputs: bool,
+
+    /// Log only the first N failing tests, keeping the log small when most tests fail. Later
+    /// failures are still counted, and the summary tells how many were left out of the log
+    #[arg(long = "fail-sample", value_name = "N", default_value = None)]
+    pub(crate) fail_sample: Option<u64>,
+
     /// How many times to fuzz
     #[arg(short = 'n', default_value = "100" )]
-    pub(crate) how_many_times: u64
+    pub(crate) how_many_times: u64,
+
+    /// Fuzz until a time budget elapses instead of for a fixed amount of tests, e.g `30s`, `500ms`,
+    /// `2m` or `1h`. Cannot be used with -n. Dry runs still generate -n inputs
+    #[arg(long = "until", value_name = "DURATION")]
+    pub(crate) until: Option<String>,
+
+    /// How many of the first tests are left out of the timing statistics, as process startup
+    /// jitter skews them. Warmup tests are still checked, and count toward the totals of
+    /// successful, failed, crashed and errored tests
+    #[arg(long = "warmup", value_name = "N", default_value = "0")]
+    pub(crate) warmup: u64,
+
+    /// Flag tests where one executable ran more than R times longer than the other, as that one
+    /// would likely exceed the judge's time limit. Slow tests are reported and logged, but don't
+    /// fail. Runs shorter than 10ms are too noisy to compare, so they are never flagged
+    #[arg(long = "tle-ratio", value_name = "R", default_value = None)]
+    pub(crate) tle_ratio: Option<f64>,
+
+    /// Directory to dump the input and outputs of each failing test into
+    #[arg(long = "dump-failures", default_value = None)]
+    pub(crate) dump_failures: Option<PathBuf>,
+
+    /// Directory to save every test's input into, numbered after the inputs it already holds (e.g
+    /// `00000042.txt`), to replay them later with --corpus-in
+    #[arg(long = "corpus-out", value_name = "DIR")]
+    pub(crate) corpus_out: Option<PathBuf>,
+
+    /// Replay the inputs saved in a directory (its `.txt` files), in file name order, instead of
+    /// generating any. The test count is then the amount of saved inputs
+    #[arg(long = "corpus-in", value_name = "DIR")]
+    pub(crate) corpus_in: Option<PathBuf>,
+
+    /// Before the random tests, run deterministic boundary assignments: every variable group at
+    /// its minimum, at its maximum, and alternating between both (2 cases when every expression
+    /// has a single variable group, 4 otherwise)
+    #[arg(long = "sweep")]
+    pub(crate) sweep: bool,
+
+    /// Minimize each failing test's input and log the minimized version, along with the spec
+    /// expressions the minimized failure depends on. Without a log file, the minimized input is
+    /// printed instead
+    #[arg(long = "shrink")]
+    pub(crate) shrink: bool,
+
+    /// Generate multiple test cases per input: the input starts with a random test case count
+    /// between MIN and MAX (inclusive), followed by that many test cases. See --blocks for inputs
+    /// without the count
+    #[arg(long = "multi-test", num_args = 2, value_names = ["MIN", "MAX"])]
+    pub(crate) multi_test: Option<Vec<u64>>,
+
+    /// Generate between MIN and MAX (inclusive) independent blocks per input, each starting on a new
+    /// line with no count in front, e.g for programs answering queries until the end of their input.
+    /// Unlike --multi-test, the executables aren't told how many blocks there are
+    #[arg(long = "blocks", num_args = 2, value_names = ["MIN", "MAX"])]
+    pub(crate) blocks: Option<Vec<u64>>,
+
+    /// Run the first executable through an interpreter (e.g `python3`), split like a shell would.
+    /// The executable then only has to be a file, not an executable one
+    #[arg(long = "interpreter-a", value_name = "COMMAND", allow_hyphen_values = true, default_value = None)]
+    pub(crate) interpreter_a: Option<String>,
+
+    /// Run the second executable through an interpreter, see --interpreter-a
+    #[arg(long = "interpreter-b", value_name = "COMMAND", allow_hyphen_values = true, default_value = None)]
+    pub(crate) interpreter_b: Option<String>,
+
+    /// Command line arguments passed to the first executable, split like a shell would
+    #[arg(long = "args-a", allow_hyphen_values = true, default_value = None)]
+    pub(crate) args_a: Option<String>,
+
+    /// Command line arguments passed to the second executable, split like a shell would
+    #[arg(long = "args-b", allow_hyphen_values = true, default_value = None)]
+    pub(crate) args_b: Option<String>,
+
+    /// Set an environment variable for the first executable, can be repeated (e.g `--env-a
+    /// RUST_BACKTRACE=1`)
+    #[arg(long = "env-a", value_name = "KEY=VAL")]
+    pub(crate) env_a: Vec<String>,
+
+    /// Set an environment variable for the second executable, can be repeated
+    #[arg(long = "env-b", value_name = "KEY=VAL")]
+    pub(crate) env_b: Vec<String>,
+
+    /// The working directory both executables run in, e.g for programs that open data files by
+    /// relative paths. Defaults to the current directory
+    #[arg(long = "cwd", value_name = "DIR")]
+    pub(crate) cwd: Option<PathBuf>,
+
+    /// Limit both executables' address space to MB megabytes, so that an executable allocating
+    /// past it crashes. Only supported on Unix, ignored with a warning elsewhere
+    #[arg(long = "mem-limit", value_name = "MB")]
+    pub(crate) mem_limit: Option<u64>,
+
+    /// Exit with code 2 when a test errored out (e.g an executable couldn't be ran) and none
+    /// failed or crashed. By default, only failed and crashed tests (exit code 1) count, so a run
+    /// where every test errored out exits with 0
+    #[arg(long = "error-is-failure")]
+    pub(crate) error_is_failure: bool,
+
+    /// Show a single progress line with the running counts instead of a heartbeat line every
+    /// thousand tests. Only failures, crashes and errors still get their own lines
+    #[arg(long = "progress", conflicts_with = "quiet")]
+    pub(crate) progress: bool,
+
+    /// Print nothing but the final summary
+    #[arg(long = "quiet")]
+    pub(crate) quiet: bool,
+
+    /// Print a line for every passing test too, instead of a heartbeat line every thousand tests
+    #[arg(long = "verbose", conflicts_with_all = ["quiet", "progress"])]
+    pub(crate) verbose: bool,
+
+    /// Only print the generated inputs without running the executables, which become optional
+    #[arg(long = "dry-run")]
+    pub(crate) dry_run: bool,
+
+    /// Only print every declared name with its inferred range and length, without running the
+    /// executables, which become optional
+    #[arg(long = "list-vars")]
+    pub(crate) list_vars: bool,
+
+    /// Print the tokens and the parsed expression of every expression line of the spec to stderr,
+    /// to debug specs that don't parse as intended
+    #[arg(long = "debug-parse")]
+    pub(crate) debug_parse: bool,
+
+    /// Seed of the random generator, to reproduce a previous run. Random when not set
+    #[arg(long = "seed", default_value = None)]
+    pub(crate) seed: Option<u64>,
+
+    /// Format of the summary printed after fuzzing. With `tap`, every test gets a Test Anything
+    /// Protocol line instead of the usual output, and the summary becomes comments after the plan
+    #[arg(long = "format", value_enum, default_value_t = OutputFormat::Text)]
+    pub(crate) format: OutputFormat,
+
+    /// Probability (between 0 and 1) of picking an endpoint of a variable's allowed range instead
+    /// of a uniform draw, as boundary values catch the most bugs
+    #[arg(long = "edge-bias", default_value = "0")]
+    pub(crate) edge_bias: f64,
+
+    /// Characters string variables (e.g `S{N}`) are made of
+    #[arg(long = "alphabet", default_value = "abcdefghijklmnopqrstuvwxyz")]
+    pub(crate) alphabet: String,
+
+    /// The most distinct characters a single string variable may hold (e.g 2 for binary-looking
+    /// strings), picked from the alphabet at random for each string
+    #[arg(long = "string-distinct", value_name = "K", default_value = None)]
+    pub(crate) string_distinct: Option<usize>,
 
 }
 
@@ -45,43 +336,684 @@ impl Display for CLIArgs {
         string.push_str(&format!("Fuzzing input path : {:?}\n", self.fuzz_data_filepath));
         string.push_str(&format!("Executable A       : {:?}\n", self.executable_a));
         string.push_str(&format!("Executable B       : {:?}\n", self.executable_b));
+        string.push_str(&format!("Expected outputs   : {:?}\n", self.expected));
+        string.push_str(&format!("Self check         : {:?}\n", self.self_check));
+        string.push_str(&format!("Sanity input       : {:?}\n", self.sanity_input));
+        string.push_str(&format!("Extra spec files   : {:?}\n", self.spec));
+        string.push_str(&format!("Default max        : {:?}\n", self.default_max));
+        string.push_str(&format!("Mixed specs        : {:?}\n", self.mix));
+        string.push_str(&format!("Config file        : {:?}\n", self.config));
         string.push_str(&format!("Input separator    : {:?}\n", self.input_sep));
         string.push_str(&format!("Output separator   : {:?}\n", self.output_sep));
+        string.push_str(&format!("Output record sep. : {:?}\n", self.output_record_sep));
+        string.push_str(&format!("Input record sep.  : {:?}\n", self.input_record_sep));
+        string.push_str(&format!("Unordered records  : {:?}\n", self.unordered));
+        string.push_str(&format!("Ignore case        : {:?}\n", self.ignore_case));
+        string.push_str(&format!("Input mode         : {:?}\n", self.input_mode));
+        string.push_str(&format!("Interactive judge  : {:?}\n", self.interactive));
+        string.push_str(&format!("Max input bytes    : {:?}\n", self.max_input_bytes));
+        string.push_str(&format!("Max retries        : {:?}\n", self.max_retries));
+        string.push_str(&format!("Independent vars   : {:?}\n", self.independent_vars));
+        string.push_str(&format!("Allow empty arrays : {:?}\n", self.allow_empty_arrays));
+        string.push_str(&format!("Big integers       : {:?}\n", self.bigint));
+        string.push_str(&format!("Require output     : {:?}\n", self.require_output));
         string.push_str(&format!("Log file path      : {:?}\n", self.log_file));
+        string.push_str(&format!("Append to log      : {:?}\n", self.log_append));
+        string.push_str(&format!("Log format         : {:?}\n", self.log_format));
+        string.push_str(&format!("Log full outputs   : {:?}\n", self.log_full_outputs));
+        string.push_str(&format!("Logged failures    : {:?}\n", self.fail_sample));
+        string.push_str(&format!("Time budget        : {:?}\n", self.until));
+        string.push_str(&format!("Warmup tests       : {:?}\n", self.warmup));
+        string.push_str(&format!("TLE ratio          : {:?}\n", self.tle_ratio));
+        string.push_str(&format!("Failure dump dir   : {:?}\n", self.dump_failures));
+        string.push_str(&format!("Corpus output dir  : {:?}\n", self.corpus_out));
+        string.push_str(&format!("Corpus input dir   : {:?}\n", self.corpus_in));
+        string.push_str(&format!("Sweep boundaries   : {:?}\n", self.sweep));
+        string.push_str(&format!("Shrink failures    : {:?}\n", self.shrink));
+        string.push_str(&format!("Test cases / input : {:?}\n", self.multi_test));
+        string.push_str(&format!("Blocks / input     : {:?}\n", self.blocks));
+        string.push_str(&format!("Interpreter A      : {:?}\n", self.interpreter_a));
+        string.push_str(&format!("Interpreter B      : {:?}\n", self.interpreter_b));
+        string.push_str(&format!("Executable A args  : {:?}\n", self.args_a));
+        string.push_str(&format!("Executable B args  : {:?}\n", self.args_b));
+        string.push_str(&format!("Executable A env   : {:?}\n", self.env_a));
+        string.push_str(&format!("Executable B env   : {:?}\n", self.env_b));
+        string.push_str(&format!("Working directory  : {:?}\n", self.cwd));
+        string.push_str(&format!("Memory limit (MB)  : {:?}\n", self.mem_limit));
+        string.push_str(&format!("Errors fail run    : {:?}\n", self.error_is_failure));
+        string.push_str(&format!("Progress line      : {:?}\n", self.progress));
+        string.push_str(&format!("Quiet              : {:?}\n", self.quiet));
+        string.push_str(&format!("Verbose            : {:?}\n", self.verbose));
+        string.push_str(&format!("Dry run            : {:?}\n", self.dry_run));
+        string.push_str(&format!("List variables     : {:?}\n", self.list_vars));
+        string.push_str(&format!("Debug parsing      : {:?}\n", self.debug_parse));
+        string.push_str(&format!("Seed               : {:?}\n", self.seed));
+        string.push_str(&format!("Summary format     : {:?}\n", self.format));
+        string.push_str(&format!("String alphabet    : {:?}\n", self.alphabet));
+        string.push_str(&format!("String distinct    : {:?}\n", self.string_distinct));
+        string.push_str(&format!("Edge bias          : {:?}\n", self.edge_bias));
 
         write!(f, "{}", string)
     }
 }
 
+/// Split a string of command line arguments like a shell would, e.g `-m "fast mode"` becomes
+/// `["-m", "fast mode"]`.
+///
+/// # Arguments
+/// - `args`: the command line arguments, if any
+///
+/// # Returns
+/// An `AppResult` containing the split arguments, or an `AppError::InvalidArgument` when the
+/// quoting is invalid.
+fn split_args(args: &Option<String>) -> AppResult<Vec<String>> {
+    match args {
+        Some(args) => shlex::split(args).ok_or(AppError::InvalidArgument(format!("cannot split arguments: {}", args))),
+        None => Ok(Vec::new())
+    }
+}
+
+/// Interpret the escape sequences of a separator argument, see `unescape`.
+///
+/// # Arguments
+/// - `sep`: the separator as given
+///
+/// # Returns
+/// An `AppResult` containing the separator, or an `AppError::InvalidArgument` on an invalid escape
+/// sequence.
+fn unescape_separator(sep: &str) -> AppResult<String> {
+    unescape(sep).ok_or(AppError::InvalidArgument(format!("invalid escape sequence in separator: {}", sep)))
+}
+
+/// Print a clap error and exit. Usage errors exit with `EXIT_USAGE_ERROR` rather than clap's own
+/// code, while `--help` and `--version` exit successfully as usual.
+fn exit_with_clap_error(err: clap::Error) -> ! {
+    if err.use_stderr() {
+        let _ = err.print();
+        std::process::exit(EXIT_USAGE_ERROR.into())
+    }
+    err.exit()
+}
+
+/// Check that a path points to an executable file.
+///
+/// # Arguments
+/// - `path`: the executable's path
+/// - `interpreted`: whether the file is ran through an interpreter, so it only has to be a file
+///
+/// # Returns
+/// An `AppResult` containing nothing, an `AppError::FileNotFound` when the path isn't a file (e.g
+/// it doesn't exist or is a directory) or an `AppError::NotExecutable` when the file isn't
+/// executable.
+fn validate_executable(path: &Path, interpreted: bool) -> AppResult<()> {
+    if !path.is_file() {
+        return Err(AppError::FileNotFound(path.to_path_buf()))
+    }
+
+    if !interpreted && !path.is_executable() {
+        return Err(AppError::NotExecutable(path.to_path_buf()))
+    }
+    Ok(())
+}
+
+/// Parse a `--until` duration: a positive integer followed by a unit, one of `ms`, `s`, `m` and
+/// `h`.
+///
+/// # Arguments
+/// - `value`: the duration, e.g `30s`
+///
+/// # Returns
+/// An `AppResult` containing the `Duration`, or an `AppError::InvalidArgument` when it isn't
+/// formatted correctly.
+fn parse_duration(value: &str) -> AppResult<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let duration = match (amount.parse::<u64>(), unit) {
+        (Ok(amount), "ms") => Some(Duration::from_millis(amount)),
+        (Ok(amount), "s") => Some(Duration::from_secs(amount)),
+        (Ok(amount), "m") => amount.checked_mul(60).map(Duration::from_secs),
+        (Ok(amount), "h") => amount.checked_mul(60 * 60).map(Duration::from_secs),
+        _ => None
+    };
+    duration.filter(|duration| !duration.is_zero())
+        .ok_or(AppError::InvalidArgument(format!("--until {:?} must be a positive amount of ms, s, m or h (e.g 30s)", value)))
+}
+
+/// Parse a `--mix` entry.
+///
+/// # Arguments
+/// - `entry`: the entry, e.g `large.fuzz:3`
+///
+/// # Returns
+/// An `AppResult` containing the spec file's path and its weight, or an
+/// `AppError::InvalidArgument` when the entry isn't a path followed by a positive weight.
+fn parse_mix_entry(entry: &str) -> AppResult<(PathBuf, u32)> {
+    match entry.rsplit_once(':').map(|(path, weight)| (path, weight.parse::<u32>())) {
+        Some((path, Ok(weight))) if !path.is_empty() && weight > 0 => Ok((PathBuf::from(path), weight)),
+        _ => Err(AppError::InvalidArgument(format!("--mix entry {:?} must be FILE:WEIGHT, with a positive WEIGHT", entry)))
+    }
+}
+
+/// Environment variables set for an executable, as `(KEY, VAL)` pairs.
+type EnvVars = Vec<(String, String)>;
+
+/// Parse an `--env-a` or `--env-b` entry.
+///
+/// # Arguments
+/// - `flag`: the flag the entry was given to, for the error message
+/// - `entry`: the entry, formatted as `KEY=VAL`
+///
+/// # Returns
+/// An `AppResult` containing the variable's name and value, or an `AppError::InvalidArgument`
+/// when the entry isn't formatted correctly.
+fn parse_env_entry(flag: &str, entry: &str) -> AppResult<(String, String)> {
+    match entry.split_once('=') {
+        Some((key, val)) if !key.is_empty() => Ok((key.to_string(), val.to_string())),
+        _ => Err(AppError::InvalidArgument(format!("{} entry {:?} must be KEY=VAL, with a non-empty KEY", flag, entry)))
+    }
+}
+
+/// Values read from a `--config` file. Every field is optional and named after the `CLIArgs`
+/// field it provides a value for.
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    executable_a: Option<PathBuf>,
+    executable_b: Option<PathBuf>,
+    expected: Option<PathBuf>,
+    self_check: Option<bool>,
+    sanity_input: Option<PathBuf>,
+    spec: Option<Vec<PathBuf>>,
+    default_max: Option<Value>,
+    mix: Option<Vec<String>>,
+    input_sep: Option<String>,
+    output_sep: Option<String>,
+    output_record_sep: Option<String>,
+    input_record_sep: Option<String>,
+    unordered: Option<bool>,
+    ignore_case: Option<bool>,
+    input_mode: Option<InputMode>,
+    interactive: Option<PathBuf>,
+    max_input_bytes: Option<u64>,
+    max_retries: Option<u64>,
+    independent_vars: Option<bool>,
+    allow_empty_arrays: Option<bool>,
+    bigint: Option<bool>,
+    require_output: Option<bool>,
+    log_file: Option<PathBuf>,
+    log_append: Option<bool>,
+    log_format: Option<LogFormat>,
+    log_full_outputs: Option<bool>,
+    fail_sample: Option<u64>,
+    how_many_times: Option<u64>,
+    until: Option<String>,
+    warmup: Option<u64>,
+    tle_ratio: Option<f64>,
+    dump_failures: Option<PathBuf>,
+    corpus_out: Option<PathBuf>,
+    corpus_in: Option<PathBuf>,
+    sweep: Option<bool>,
+    shrink: Option<bool>,
+    multi_test: Option<Vec<u64>>,
+    blocks: Option<Vec<u64>>,
+    interpreter_a: Option<String>,
+    interpreter_b: Option<String>,
+    args_a: Option<String>,
+    args_b: Option<String>,
+    env_a: Option<Vec<String>>,
+    env_b: Option<Vec<String>>,
+    cwd: Option<PathBuf>,
+    mem_limit: Option<u64>,
+    error_is_failure: Option<bool>,
+    progress: Option<bool>,
+    quiet: Option<bool>,
+    verbose: Option<bool>,
+    dry_run: Option<bool>,
+    list_vars: Option<bool>,
+    debug_parse: Option<bool>,
+    seed: Option<u64>,
+    format: Option<OutputFormat>,
+    edge_bias: Option<f64>,
+    alphabet: Option<String>,
+    string_distinct: Option<usize>,
+}
+
+impl ConfigFile {
+    /// Read and deserialize a config file.
+    ///
+    /// # Arguments
+    /// - `path`: the config file's path
+    ///
+    /// # Returns
+    /// An `AppResult` containing the config, an `AppError::FileNotFound` when the file doesn't
+    /// exist or an `AppError::InvalidConfig` when it isn't valid.
+    fn read(path: &Path) -> AppResult<Self> {
+        if !path.is_file() {
+            return Err(AppError::FileNotFound(path.to_path_buf()))
+        }
+        toml::from_str(&read_to_string(path)?).map_err(|err| AppError::InvalidConfig(path.to_path_buf(), err.message().to_string()))
+    }
+}
+
+/// Overwrite `CLIArgs` fields with the config file's values, except for the ones given on the
+/// command line.
+macro_rules! merge_config {
+    ($args:expr, $matches:expr, $config:expr, { $($field:ident),* $(,)? } , optional { $($opt_field:ident),* $(,)? }) => {
+        $(
+            if let (false, Some(val)) = (from_command_line($matches, stringify!($field)), $config.$field) {
+                $args.$field = val;
+            }
+        )*
+        $(
+            if let (false, Some(val)) = (from_command_line($matches, stringify!($opt_field)), $config.$opt_field) {
+                $args.$opt_field = Some(val);
+            }
+        )*
+    };
+}
+
+/// Whether an argument was given on the command line, as opposed to being left to its default.
+fn from_command_line(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
 impl CLIArgs {
     /// A wrapper function around the `Self::Parse` method. This method returns an
     /// `AppResult<Self>` containing an app error when an argument parsing error occured.
     pub fn checked_parse() -> AppResult<Self> {
-        let result = Self::parse();
-        if !&result.fuzz_data_filepath.is_file() {
+        Self::checked_parse_from(std::env::args_os())
+    }
+
+    /// Like `checked_parse`, but parse the given command line instead of the process' own.
+    pub(crate) fn checked_parse_from(args: impl IntoIterator<Item = impl Into<OsString> + Clone>) -> AppResult<Self> {
+        let matches = Self::command().try_get_matches_from(args).unwrap_or_else(|err| exit_with_clap_error(err));
+        let mut result = Self::from_arg_matches(&matches).unwrap_or_else(|err| exit_with_clap_error(err));
+        let mut count_given = from_command_line(&matches, "how_many_times");
+        if let Some(path) = &result.config {
+            let config = ConfigFile::read(path)?;
+            count_given |= config.how_many_times.is_some();
+            merge_config!(result, &matches, config, {
+                self_check, spec, input_sep, output_sep, unordered, ignore_case, input_mode,
+                max_input_bytes, max_retries, independent_vars, allow_empty_arrays, bigint,
+                require_output, log_append, log_format, log_full_outputs, how_many_times, warmup, sweep,
+                shrink, error_is_failure, progress, quiet, verbose, dry_run, list_vars, debug_parse,
+                format, edge_bias, alphabet, env_a, env_b,
+            }, optional {
+                executable_a, executable_b, expected, sanity_input, interactive, mix, output_record_sep,
+                input_record_sep, log_file, dump_failures, corpus_out, corpus_in, fail_sample, multi_test,
+                interpreter_a, interpreter_b, args_a, args_b, cwd, mem_limit, seed, string_distinct,
+                default_max, until, tle_ratio, blocks,
+            });
+        }
+
+        result.input_sep = unescape_separator(&result.input_sep)?;
+        result.output_sep = unescape_separator(&result.output_sep)?;
+        for sep in [&mut result.input_record_sep, &mut result.output_record_sep].into_iter().flatten() {
+            *sep = unescape_separator(sep)?;
+        }
+
+        if result.fuzz_data_filepath != Path::new(STDIN_PATH) && !result.fuzz_data_filepath.is_file() {
             return Err(AppError::FileNotFound(result.fuzz_data_filepath))
         }
 
-        if !&result.executable_a.is_file() {
-            return Err(AppError::FileNotFound(result.executable_a))
+        if let Some(expected) = &result.expected {
+            if result.executable_b.is_some() {
+                return Err(AppError::InvalidArgument("--expected replaces the second executable, so only one executable may be given".into()))
+            }
+            if !expected.is_dir() {
+                return Err(AppError::FileNotFound(expected.clone()))
+            }
+            if result.sanity_input.is_some() {
+                return Err(AppError::InvalidArgument("--sanity-input checks the second executable, so it cannot be used with --expected".into()))
+            }
+        }
+
+        if result.self_check {
+            if result.executable_b.is_some() || result.expected.is_some() {
+                return Err(AppError::InvalidArgument("--self-check runs the first executable twice, so neither a second executable nor --expected may be given".into()))
+            }
+            if result.sanity_input.is_some() {
+                return Err(AppError::InvalidArgument("--sanity-input checks the second executable, so it cannot be used with --self-check".into()))
+            }
+        }
+
+        if let Some(path) = result.sanity_input.as_ref().filter(|path| !path.is_file()) {
+            return Err(AppError::FileNotFound(path.clone()))
+        }
+
+        // The executables are unused (and may be absent) in a dry run or when listing variables.
+        if !result.dry_run && !result.list_vars {
+            let Some(executable_a) = &result.executable_a else {
+                return Err(AppError::InvalidArgument("the first executable must be given, either on the command line or in the config file".into()))
+            };
+            if result.executable_b.is_none() && result.expected.is_none() && !result.self_check {
+                return Err(AppError::InvalidArgument("the second executable (or --expected or --self-check) must be given, either on the command line or in the config file".into()))
+            }
+
+            validate_executable(executable_a, result.interpreter_a.is_some())?;
+            if let Some(executable_b) = &result.executable_b {
+                validate_executable(executable_b, result.interpreter_b.is_some())?;
+
+                // Both exist at this point, so canonicalizing them cannot fail on a missing path.
+                if canonicalize(executable_b)? == canonicalize(executable_a)? {
+                    return Err(AppError::SameExecutable)
+                }
+            }
+        }
+
+        if let Some(judge) = &result.interactive {
+            validate_executable(judge, false)?;
+            if result.input_mode != InputMode::Stdin {
+                return Err(AppError::InvalidArgument("--interactive hands the input to the judge, so it cannot be used with --input-mode".into()))
+            }
         }
 
-        if !&result.executable_a.is_executable() {
-            return Err(AppError::NotExecutable(result.executable_a))
+        for (flag, range) in [("--multi-test", &result.multi_test), ("--blocks", &result.blocks)] {
+            let Some(range) = range else {
+                continue
+            };
+            if range.len() != 2 {
+                return Err(AppError::InvalidArgument(format!("{} takes a MIN and a MAX, got {:?}", flag, range)))
+            }
+            if range[0] < 1 || range[0] > range[1] {
+                return Err(AppError::InvalidArgument(format!("{} range {} {} must satisfy 1 <= MIN <= MAX", flag, range[0], range[1])))
+            }
+        }
+        if result.multi_test.is_some() && result.blocks.is_some() {
+            return Err(AppError::InvalidArgument("--multi-test and --blocks cannot be used together: the input either starts with a count or it doesn't".into()))
+        }
+
+        if result.alphabet.is_empty() {
+            return Err(AppError::InvalidArgument("--alphabet must contain at least one character".into()))
+        }
+
+        if result.string_distinct == Some(0) {
+            return Err(AppError::InvalidArgument("--string-distinct must be at least 1".into()))
+        }
+
+        if !(0.0..=1.0).contains(&result.edge_bias) {
+            return Err(AppError::InvalidArgument(format!("--edge-bias {} must be between 0 and 1", result.edge_bias)))
+        }
+
+        if let Some(ratio) = result.tle_ratio {
+            if !(ratio > 1.0 && ratio.is_finite()) {
+                return Err(AppError::InvalidArgument(format!("--tle-ratio {} must be a number above 1", ratio)))
+            }
+            if result.expected.is_some() {
+                return Err(AppError::InvalidArgument("--tle-ratio compares the running times of two executables, so it cannot be used with --expected".into()))
+            }
         }
 
-        if canonicalize(&result.executable_b)? == canonicalize(&result.executable_a)? {
-            return Err(AppError::SameExecutable)
+        for (flag, interpreter) in [("--interpreter-a", &result.interpreter_a), ("--interpreter-b", &result.interpreter_b)] {
+            if interpreter.is_some() && split_args(interpreter)?.is_empty() {
+                return Err(AppError::InvalidArgument(format!("{} must name an interpreter", flag)))
+            }
         }
+        split_args(&result.args_a)?;
+        split_args(&result.args_b)?;
+        result.executable_envs()?;
 
-        if !&result.executable_b.is_file() {
-            return Err(AppError::FileNotFound(result.executable_b))
+        if result.mem_limit == Some(0) {
+            return Err(AppError::InvalidArgument("--mem-limit must be at least 1".into()))
         }
 
-        if !&result.executable_b.is_executable() {
-            return Err(AppError::NotExecutable(result.executable_b))
+        if result.until.is_some() && count_given {
+            return Err(AppError::InvalidArgument("-n and --until cannot be used together: give either an amount of tests or a time budget".into()))
+        }
+        result.time_budget()?;
+
+        if result.expected.is_some() && result.corpus_in.is_none() {
+            return Err(AppError::InvalidArgument("--expected looks expected outputs up by the name of saved inputs, so it needs --corpus-in".into()))
+        }
+        if let Some(dir) = &result.corpus_in {
+            if !dir.is_dir() {
+                return Err(AppError::DirectoryNotFound(dir.clone()))
+            }
+            // Replayed inputs come without their variables, so there is nothing to sweep or shrink.
+            if result.sweep || result.shrink || result.dry_run {
+                return Err(AppError::InvalidArgument("--corpus-in replays saved inputs, so it cannot be used with --sweep, --shrink or --dry-run".into()))
+            }
+        }
+
+        if let Some(cwd) = result.cwd.as_ref().filter(|cwd| !cwd.is_dir()) {
+            return Err(AppError::DirectoryNotFound(cwd.clone()))
+        }
+
+        let mix = result.spec_mix()?;
+        // The --spec files are read again along with every mixed spec.
+        let is_stdin = |path: &PathBuf| path == Path::new(STDIN_PATH);
+        let stdin_reads = mix.iter().filter(|(path, _)| is_stdin(path)).count() + mix.len() * result.spec.iter().filter(|path| is_stdin(path)).count();
+        if stdin_reads > 1 {
+            return Err(AppError::InvalidArgument(format!("The standard input can only be read once, so `{}` can only be given for a single spec", STDIN_PATH)))
+        }
+        for path in mix.into_iter().map(|(path, _)| path).chain(result.spec.iter().cloned()) {
+            if path != Path::new(STDIN_PATH) && !path.is_file() {
+                return Err(AppError::FileNotFound(path))
+            }
         }
 
         Ok(result)
     }
+
+    /// The interpreters of both executables along with their arguments, split like a shell would.
+    /// An executable without one has an empty interpreter.
+    pub fn executable_interpreters(&self) -> AppResult<(Vec<String>, Vec<String>)> {
+        Ok((split_args(&self.interpreter_a)?, split_args(&self.interpreter_b)?))
+    }
+
+    /// The command line arguments for both executables, split like a shell would.
+    pub fn executable_args(&self) -> AppResult<(Vec<String>, Vec<String>)> {
+        Ok((split_args(&self.args_a)?, split_args(&self.args_b)?))
+    }
+
+    /// The environment variables set for both executables, as `(KEY, VAL)` pairs.
+    pub fn executable_envs(&self) -> AppResult<(EnvVars, EnvVars)> {
+        let parse = |flag: &str, entries: &[String]| entries.iter().map(|entry| parse_env_entry(flag, entry)).collect::<AppResult<Vec<_>>>();
+        Ok((parse("--env-a", &self.env_a)?, parse("--env-b", &self.env_b)?))
+    }
+
+    /// Every spec file of the run along with its weight, the positional one first. Without
+    /// `--mix`, that's only the positional spec. The weights must add up to at most `u32::MAX`.
+    pub fn spec_mix(&self) -> AppResult<Vec<(PathBuf, u32)>> {
+        let mut specs = vec![(self.fuzz_data_filepath.clone(), 1)];
+        for entry in self.mix.iter().flatten() {
+            let (path, weight) = parse_mix_entry(entry)?;
+            if path == self.fuzz_data_filepath {
+                specs[0].1 = weight;
+            } else {
+                specs.push((path, weight));
+            }
+        }
+        if specs.iter().try_fold(0_u32, |total, (_, weight)| total.checked_add(*weight)).is_none() {
+            return Err(AppError::InvalidArgument(format!("--mix weights must add up to at most {}", u32::MAX)))
+        }
+        Ok(specs)
+    }
+
+    /// The time budget of the run, if it runs until one elapses.
+    pub fn time_budget(&self) -> AppResult<Option<Duration>> {
+        self.until.as_deref().map(parse_duration).transpose()
+    }
+
+    /// The inclusive range of test cases per input, if multiple test cases are enabled.
+    pub fn multi_test_range(&self) -> Option<(u64, u64)> {
+        self.multi_test.as_ref().map(|range| (range[0], range[1]))
+    }
+
+    /// The inclusive range of blocks per input without a count, if `--blocks` is given.
+    pub fn blocks_range(&self) -> Option<(u64, u64)> {
+        self.blocks.as_ref().map(|range| (range[0], range[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::write};
+
+    use super::*;
+
+    #[test]
+    fn test_config_merge() {
+        let path = temp_dir().join(format!("beanfuzz-config-{}.toml", std::process::id()));
+        write(&path, "executable_a = \"examples/example\"\nexecutable_b = \"/bin/cat\"\nhow_many_times = 500\nseed = 7\nformat = \"json\"\n").unwrap();
+
+        let args = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--config", path.to_str().unwrap(), "-n", "20"]).unwrap();
+        assert_eq!(args.executable_a, Some(PathBuf::from("examples/example")));
+        assert_eq!(args.executable_b, Some(PathBuf::from("/bin/cat")));
+        assert_eq!(args.how_many_times, 20);
+        assert_eq!(args.seed, Some(7));
+        assert_eq!(args.format, OutputFormat::Json);
+        assert_eq!(args.input_sep, " ");
+
+        write(&path, "how_many_tests = 500\n").unwrap();
+        let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--config", path.to_str().unwrap()]);
+        assert!(matches!(result, Err(AppError::InvalidConfig(..))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_separator_escapes() {
+        let args = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "-o", "\\n", "-s", "\\t"]).unwrap();
+        assert_eq!(args.output_sep, "\n");
+        assert_eq!(args.input_sep, "\t");
+        assert_eq!("1 2\n3\n".trim_end().split(args.output_sep.as_str()).collect::<Vec<_>>(), ["1 2", "3"]);
+
+        let args = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--output-record-sep", "\\n", "--input-record-sep", "\\t"]).unwrap();
+        assert_eq!(args.output_record_sep.as_deref(), Some("\n"));
+        assert_eq!(args.input_record_sep.as_deref(), Some("\t"));
+
+        let args = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "-o", "a\\\\b"]).unwrap();
+        assert_eq!(args.output_sep, "a\\b");
+
+        let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "-s", "\\x"]);
+        assert!(matches!(result, Err(AppError::InvalidArgument(..))));
+        let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--output-record-sep", "\\x"]);
+        assert!(matches!(result, Err(AppError::InvalidArgument(..))));
+    }
+
+    #[test]
+    fn test_validate_executable() {
+        assert_eq!(validate_executable(Path::new("examples/example"), false), Ok(()));
+        assert_eq!(validate_executable(Path::new("examples/nonexistent"), false), Err(AppError::FileNotFound("examples/nonexistent".into())));
+        assert_eq!(validate_executable(Path::new("examples"), false), Err(AppError::FileNotFound("examples".into())));
+        assert_eq!(validate_executable(Path::new("examples/example.c"), false), Err(AppError::NotExecutable("examples/example.c".into())));
+        assert_eq!(validate_executable(Path::new("examples/example.c"), true), Ok(()));
+        assert_eq!(validate_executable(Path::new("examples"), true), Err(AppError::FileNotFound("examples".into())));
+    }
+
+    #[test]
+    fn test_interpreter_checks() {
+        let parse = |extra: &[&str]| CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "examples/example.c", "examples/example"].iter().chain(extra));
+        assert_eq!(parse(&[]).unwrap_err(), AppError::NotExecutable("examples/example.c".into()));
+        assert_eq!(parse(&["--interpreter-b", "python3"]).unwrap_err(), AppError::NotExecutable("examples/example.c".into()));
+
+        let args = parse(&["--interpreter-a", "python3 -u"]).unwrap();
+        assert_eq!(args.executable_interpreters().unwrap(), (vec!["python3".to_string(), "-u".to_string()], Vec::new()));
+
+        let result = parse(&["--interpreter-a", " "]);
+        assert!(matches!(result, Err(AppError::InvalidArgument(_))));
+        let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "examples/nonexistent", "examples/example", "--interpreter-a", "sh"]);
+        assert_eq!(result.unwrap_err(), AppError::FileNotFound("examples/nonexistent".into()));
+    }
+
+    #[test]
+    fn test_executable_checks() {
+        let parse = |a: &str, b: &str| CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", a, b]);
+        assert_eq!(parse("examples/nonexistent", "examples/example").unwrap_err(), AppError::FileNotFound("examples/nonexistent".into()));
+        assert_eq!(parse("examples", "examples/example").unwrap_err(), AppError::FileNotFound("examples".into()));
+        assert_eq!(parse("examples/example.c", "examples/example").unwrap_err(), AppError::NotExecutable("examples/example.c".into()));
+        assert_eq!(parse("examples/example", "examples/nonexistent").unwrap_err(), AppError::FileNotFound("examples/nonexistent".into()));
+        assert_eq!(parse("examples/example", "examples").unwrap_err(), AppError::FileNotFound("examples".into()));
+        assert_eq!(parse("examples/example", "examples/example.c").unwrap_err(), AppError::NotExecutable("examples/example.c".into()));
+    }
+
+    #[test]
+    fn test_executable_b_checked_before_same_path() {
+        let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "examples/example", "examples/nonexistent"]);
+        assert!(matches!(result, Err(AppError::FileNotFound(path)) if path == Path::new("examples/nonexistent")));
+
+        let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "examples/example", "examples/../examples/example"]);
+        assert!(matches!(result, Err(AppError::SameExecutable)));
+    }
+
+    #[test]
+    fn test_executable_envs() {
+        let args = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--env-a", "A=1", "--env-a", "B=x=y", "--env-b", "C="]).unwrap();
+        let (env_a, env_b) = args.executable_envs().unwrap();
+        assert_eq!(env_a, [("A".to_string(), "1".to_string()), ("B".to_string(), "x=y".to_string())]);
+        assert_eq!(env_b, [("C".to_string(), String::new())]);
+
+        for entry in ["A", "=1"] {
+            let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--env-b", entry]);
+            assert!(matches!(result, Err(AppError::InvalidArgument(_))), "{}", entry);
+        }
+    }
+
+    #[test]
+    fn test_cwd_checked() {
+        let args = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--cwd", "examples"]).unwrap();
+        assert_eq!(args.cwd, Some(PathBuf::from("examples")));
+
+        for cwd in ["examples/nonexistent", "examples/sample.fuzz"] {
+            let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--cwd", cwd]);
+            assert!(matches!(result, Err(AppError::DirectoryNotFound(path)) if path == Path::new(cwd)));
+        }
+    }
+
+    #[test]
+    fn test_corpus_in_checked() {
+        let parse = |extra: &[&str]| CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "examples/example", "/bin/cat"].iter().chain(extra));
+        assert!(parse(&["--corpus-in", "examples"]).is_ok());
+        assert_eq!(parse(&["--corpus-in", "examples/nonexistent"]).unwrap_err(), AppError::DirectoryNotFound("examples/nonexistent".into()));
+        assert!(matches!(parse(&["--corpus-in", "examples", "--shrink"]), Err(AppError::InvalidArgument(_))));
+
+        let parse = |extra: &[&str]| CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "examples/example", "--expected", "examples"].iter().chain(extra));
+        assert!(matches!(parse(&[]), Err(AppError::InvalidArgument(_))));
+        assert!(parse(&["--corpus-in", "examples"]).is_ok());
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let parse = |extra: &[&str]| CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run"].iter().chain(extra));
+        assert_eq!(parse(&[]).unwrap().time_budget().unwrap(), None);
+        for (value, duration) in [("500ms", Duration::from_millis(500)), ("30s", Duration::from_secs(30)), ("2m", Duration::from_secs(120)), ("1h", Duration::from_secs(3600))] {
+            assert_eq!(parse(&["--until", value]).unwrap().time_budget().unwrap(), Some(duration));
+        }
+        for value in ["30", "0s", "s", "1.5s", "3d"] {
+            assert!(matches!(parse(&["--until", value]), Err(AppError::InvalidArgument(_))), "{}", value);
+        }
+        assert!(matches!(parse(&["--until", "30s", "-n", "10"]), Err(AppError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn test_stdin_spec_once() {
+        let parse = |extra: &[&str]| CLIArgs::checked_parse_from(["beanfuzz", "--dry-run"].iter().chain(extra));
+        assert!(parse(&["-"]).is_ok());
+        assert!(parse(&["examples/sample.fuzz", "--spec", "-"]).is_ok());
+        for extra in [&["-", "--spec", "-"][..], &["examples/sample.fuzz", "--spec", "-", "--spec", "-"], &["examples/sample.fuzz", "--spec", "-", "--mix", "examples/example.c:1"]] {
+            let result = parse(extra);
+            assert!(matches!(&result, Err(err) if matches!(err, AppError::InvalidArgument(_)) && err.exit_code() == EXIT_USAGE_ERROR), "{:?}", extra);
+        }
+    }
+
+    #[test]
+    fn test_spec_mix() {
+        let args = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run"]).unwrap();
+        assert_eq!(args.spec_mix().unwrap(), vec![(PathBuf::from("examples/sample.fuzz"), 1)]);
+
+        let args = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--mix", "examples/sample.fuzz:7", "examples/example.c:3"]).unwrap();
+        assert_eq!(args.spec_mix().unwrap(), vec![(PathBuf::from("examples/sample.fuzz"), 7), (PathBuf::from("examples/example.c"), 3)]);
+
+        let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--mix", "examples/nonexistent:2"]);
+        assert!(matches!(result, Err(AppError::FileNotFound(path)) if path == Path::new("examples/nonexistent")));
+
+        let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--mix", "examples/sample.fuzz:4000000000", "examples/example.c:4000000000"]);
+        assert!(matches!(result, Err(AppError::InvalidArgument(_))));
+
+        for entry in ["examples/sample.fuzz", "examples/sample.fuzz:0", ":3"] {
+            let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--mix", entry]);
+            assert!(matches!(result, Err(AppError::InvalidArgument(_))), "{}", entry);
+        }
+    }
 }
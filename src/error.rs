@@ -1,9 +1,29 @@
-use std::fmt::{Debug, Display};
+use std::fmt::Display;
 use std::process::{ExitCode, Termination};
 use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(PartialEq)]
-pub(crate) enum AppError {
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{exec::ExecutableId, parser::tokenizer::Value};
+
+/// Exit code of a run where a test failed or crashed, i.e a bug was found.
+pub const EXIT_MISMATCH: u8 = 1;
+
+/// Exit code of a run that couldn't go on (e.g an I/O error, or an error while running the
+/// tests), or where tests errored out with `--error-is-failure`.
+pub const EXIT_HARNESS_ERROR: u8 = 2;
+
+/// Exit code of a run rejected before fuzzing: invalid command line arguments, config file or
+/// spec.
+pub const EXIT_USAGE_ERROR: u8 = 3;
+
+/// Everything that can stop beanfuzz (or a single test). `Display` gives the message shown to
+/// users, while `Debug` shows the variant and its fields, for developers.
+#[derive(PartialEq, Debug)]
+pub enum AppError {
     /// Wrapper for std::io::Error
     IOError(std::io::ErrorKind),
 
@@ -21,25 +41,75 @@ pub(crate) enum AppError {
     /// Checked during: CLI args parsing-time
     NotExecutable(PathBuf),
 
+    /// An invalid value for a command line argument. Contains a `String` describing the issue.
+    /// Checked during: CLI args parsing-time
+    InvalidArgument(String),
+
+    /// The `--config` file is not valid TOML or holds unknown or mistyped fields. Contains a
+    /// `PathBuf` indicating the file and a `String` describing the issue.
+    /// Checked during: CLI args parsing-time
+    InvalidConfig(PathBuf, String),
+
     /// File cannot be found. Contains a `PathBuf` to indicate the nonexistent file.
     /// Checked during: CLI args parsing-time
     FileNotFound(PathBuf),
 
+    /// Directory cannot be found. Contains a `PathBuf` to indicate the nonexistent directory.
+    /// Checked during: CLI args parsing-time
+    DirectoryNotFound(PathBuf),
+
     /// An invalid syntax (exclusive to fuzz information, i.e the input & output separator and the
     /// input order). Contains a `u64` to indicate the line number along with the said string to
     /// identify the line.
     /// Checked during: parse-time
     InvalidSyntax(u64, String),
 
-    /// When variable is declared twice. Contains `String` indicating the variable name.
-    /// Checked during: run-time
+    /// When variable or `define` name is declared twice. Contains `String` indicating the name.
+    /// Checked during: parse-time
     DoubleDeclaration(String),
 
-    /// When variable is not declared but is written in the input order. Contains `String`
-    /// indicating the variable name.
-    /// Checked during: run-time
+    /// When variable is not declared but is written in the input order or used as a length.
+    /// Contains `String` indicating the variable name.
+    /// Checked during: parse-time
     UndeclaredVariable(String),
 
+    /// When a name in the input order is marked to have its length written first (e.g `N@len`),
+    /// but it isn't an array or a string. Contains a `String` indicating the name.
+    /// Checked during: parse-time
+    NoLength(String),
+
+    /// When array lengths depend on each other in a cycle, e.g `0 <= A[N]# <= N <= 10`, where `N`
+    /// can only be generated after `A[N]#`. Contains a `String` indicating an expression in the
+    /// cycle.
+    /// Checked during: parse-time
+    CyclicDependency(String),
+
+    /// When a range is too small for the `<` comparisons of its expression, e.g `0 < A < B < 2`,
+    /// where `0`, `A`, `B` and `2` need 4 distinct values but the range only holds 3. Contains the
+    /// line and the expression, the range's minimum and maximum, and the amount of distinct values
+    /// needed.
+    /// Checked during: parse-time
+    RangeTooSmall { line: u64, expr: String, min: Value, max: Value, needed: u64 },
+
+    /// When an expression's bounds don't fit in an `i64` while big integers aren't enabled (see
+    /// `ParseOptions::bigint`). Contains a `u64` indicating the line and a `String` indicating the
+    /// expression.
+    /// Checked during: parse-time
+    ValueTooLarge(u64, String),
+
+    /// When an expression ends with a variable group (e.g `1 <= A <= B`) whose largest value no
+    /// other expression determines, as it is only declared by expressions ending with it (or
+    /// relying on each other). Contains a `String` indicating the variable and a `String`
+    /// indicating the expression.
+    /// Checked during: parse-time
+    UnknownBound(String, String),
+
+    /// When a parse error occurs in one of multiple spec files parsed together (see `--spec`).
+    /// Contains a `PathBuf` indicating the file and the error, whose line number is counted from
+    /// the start of that file.
+    /// Checked during: parse-time
+    InSpecFile(PathBuf, Box<AppError>),
+
     /// When there's more than one input order.
     /// Checked during: parse-time
     MultipleInputOrder,
@@ -48,38 +118,382 @@ pub(crate) enum AppError {
     /// Checked during: parse-time
     NoInputOrder,
 
-    /// When array size is 0 or negative. Contains a `i64` indicating the invalid length and a
-    /// `String` indicating the invalid expression
+    /// When the spec declares nothing to generate (no expression, string variable or tree), so
+    /// every input would be empty.
+    /// Checked during: parse-time
+    NoExpressions,
+
+    /// When an array, matrix, string or tree length can't be resolved, e.g because its length
+    /// variable wasn't generated before it. Contains a `u64` indicating the line of the
+    /// declaration and a `String` indicating the declaration.
     /// Checked during: execution-time
-    InvalidArraySize(i64, String),
+    UnresolvedArrayLength(u64, String),
 
-    /// When program gives no output or another output reading error occured. Contains a `String`
-    /// indicating the executable ran.
+    /// When array size is 0 or negative. Contains a `Value` indicating the invalid length, a `u64`
+    /// indicating the line of the declaration and a `String` indicating the invalid expression
+    /// Checked during: execution-time
+    InvalidArraySize(Value, u64, String),
+
+    /// When an array, matrix, string or tree length can be below 1, e.g `A[N]#` with
+    /// `0 <= N <= 10` (below 0 for arrays with `ParseOptions::allow_empty_arrays`). Contains an
+    /// `Value` indicating the smallest possible length and a `String` indicating the declaration.
+    /// Checked during: parse-time
+    InvalidLengthRange(Value, String),
+
+    /// When a range holds less values than the amount of distinct values needed from it. Contains
+    /// a `Value` indicating the amount of distinct values needed, a `u64` indicating the line of
+    /// the expression and a `String` indicating the expression.
+    /// Checked during: execution-time
+    RangeTooSmallForDistinct(Value, u64, String),
+
+    /// When an array's sum cap is below the smallest sum its items can have. Contains a `Value`
+    /// indicating that smallest sum, a `u64` indicating the line of the expression and a `String`
+    /// indicating the expression.
+    /// Checked during: execution-time
+    SumCapTooSmall(Value, u64, String),
+
+    /// When the second executable disagrees with a known-good output of the `--sanity-input` file,
+    /// or crashes on its input. Contains a `u64` indicating the (1-indexed) case and a `String`
+    /// describing the disagreement.
+    /// Checked during: execution-time, before fuzzing
+    SanityCheckFailed(u64, String),
+
+    /// When there is no expected output stored for an input. Contains a `PathBuf` indicating the
+    /// file the expected output should be in.
+    /// Checked during: execution-time
+    NoExpectedOutput(PathBuf),
+
+    /// When a variable shared between expressions got a value in one expression that another one
+    /// can't fit around, e.g `1 <= A,B <= 10` and `1 <= A < B <= 10` with `A` = `B`. Constant
+    /// bounds never conflict, as shared variables are picked within all of them. Contains a `u64`
+    /// indicating the line of the expression that can't be satisfied and a `String` indicating
+    /// the expression. Only returned once the test ran out of retries, see
+    /// `RunnerOptions::max_retries`.
+    /// Checked during: execution-time
+    SharedVariableConflict(u64, String),
+
+    /// When the variables kept breaking an exclusion constraint (e.g `A != B`) after being
+    /// generated again many times. Contains a `u64` indicating the line of the constraint and a
+    /// `String` indicating the constraint.
+    /// Checked during: execution-time
+    ConstraintUnsatisfiable(u64, String),
+
+    /// When a generated input would be longer than the `--max-input-bytes` limit. Contains a
+    /// `u64` indicating the limit.
+    /// Checked during: execution-time
+    InputTooLarge(u64),
+
+    /// When the program printed nothing (or only whitespace) while `--require-output` is set.
+    /// Contains a `PathBuf` indicating the executable ran.
     /// Checked during: execution-time
     NoOutput(PathBuf),
+
+    /// When the program's output could not be read, e.g because it isn't valid UTF-8. Contains a
+    /// `PathBuf` indicating the executable ran and the kind of the reading error.
+    /// Checked during: execution-time
+    OutputNotCaptured(PathBuf, std::io::ErrorKind),
 }
 
-pub(crate) struct AppResultData {
+/// Wall-clock running time statistics of a single executable, over every test it finished.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct ExecTimings {
+    /// Amount of timed runs
+    pub runs: u64,
+
+    /// Time of every timed run combined
+    pub total: Duration,
+
+    /// Time of the fastest run, if any
+    pub min: Option<Duration>,
+
+    /// Time of the slowest run
+    pub max: Duration,
+}
+
+impl ExecTimings {
+    /// Count a run that took `duration`.
+    pub fn record(&mut self, duration: Duration) {
+        self.runs += 1;
+        self.total += duration;
+        self.min = Some(self.min.map_or(duration, |min| min.min(duration)));
+        self.max = self.max.max(duration);
+    }
+
+    /// Average time of a run, if there were any.
+    pub fn mean(&self) -> Option<Duration> {
+        (self.runs > 0).then(|| self.total.div_f64(self.runs as f64))
+    }
+
+    fn to_json(self) -> serde_json::Value {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        json!({
+            "runs": self.runs,
+            "total_ms": millis(self.total),
+            "min_ms": self.min.map(millis),
+            "mean_ms": self.mean().map(millis),
+            "max_ms": millis(self.max),
+        })
+    }
+}
+
+impl Display for ExecTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.min, self.mean()) {
+            (Some(min), Some(mean)) => write!(f, "min {:.2?}, mean {:.2?}, max {:.2?} (total {:.2?})", min, mean, self.max, self.total),
+            _ => write!(f, "-")
+        }
+    }
+}
+
+/// Size statistics of one kind of text (e.g the generated inputs), in bytes, over every test.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct SizeStats {
+    /// Amount of recorded texts
+    pub count: u64,
+
+    /// Size of every recorded text combined
+    pub total: u64,
+
+    /// Size of the smallest text, if any
+    pub min: Option<u64>,
+
+    /// Size of the largest text
+    pub max: u64,
+}
+
+impl SizeStats {
+    /// Count a text of `bytes` bytes.
+    pub fn record(&mut self, bytes: u64) {
+        self.count += 1;
+        self.total += bytes;
+        self.min = Some(self.min.map_or(bytes, |min| min.min(bytes)));
+        self.max = self.max.max(bytes);
+    }
+
+    /// Average size of a text, if there were any.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total as f64 / self.count as f64)
+    }
+
+    fn to_json(self) -> serde_json::Value {
+        json!({
+            "count": self.count,
+            "total_bytes": self.total,
+            "min_bytes": self.min,
+            "mean_bytes": self.mean(),
+            "max_bytes": self.max,
+        })
+    }
+}
+
+impl Display for SizeStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.min, self.mean()) {
+            (Some(min), Some(mean)) => write!(f, "min {} B, mean {:.1} B, max {} B (total {} B)", min, mean, self.max, self.total),
+            _ => write!(f, "-")
+        }
+    }
+}
+
+/// Shortest running time a test is flagged as slow at, see `AppResultData::tle_ratio`. Below it,
+/// process startup jitter outweighs the executables' own running time.
+const TLE_MIN_DURATION: Duration = Duration::from_millis(10);
+
+pub struct AppResultData {
     /// Amount of tests ran
-    pub(crate) successful_tests: u64,
+    pub successful_tests: u64,
 
     /// Amount of tests that fails
-    pub(crate) failed_tests: u64,
+    pub failed_tests: u64,
+
+    /// Amount of tests where an executable exited with a nonzero status or was killed
+    pub crashed_tests: u64,
 
     /// Amount of tests skipped due to error(s)
-    pub(crate) error_tests: u64,
+    pub error_tests: u64,
+
+    /// Amount of the tests skipped due to error(s) that were abandoned after running out of
+    /// retries, see `RunnerOptions::max_retries`
+    pub abandoned_tests: u64,
 
     /// Write test result to log file
-    pub(crate) log_file: Option<PathBuf>
+    pub log_file: Option<PathBuf>,
+
+    /// Whether this was a dry run, in which case no summary is reported
+    pub dry_run: bool,
+
+    /// Whether tests skipped due to error(s) make the exit code nonzero
+    pub error_is_failure: bool,
+
+    /// Seed of the RNG used to generate the inputs
+    pub seed: u64,
+
+    /// Test numbers (1-indexed) of the tests that fail
+    pub failed_test_indices: Vec<u64>,
+
+    /// Amount of the failing tests left out of the log file, past the `--fail-sample` first ones
+    pub unlogged_failures: u64,
+
+    /// How many of the first tests are left out of `exec_time_a` and `exec_time_b`. They still
+    /// count toward the other totals
+    pub warmup: u64,
+
+    /// Amount of tests where one executable ran more than `tle_ratio` times longer than the other.
+    /// They still count toward the other totals
+    pub slow_tests: u64,
+
+    /// How many times longer than the other executable one has to run for a test to be slow, if
+    /// slow tests are flagged at all
+    pub tle_ratio: Option<f64>,
+
+    /// Running time of the first executable
+    pub exec_time_a: ExecTimings,
+
+    /// Running time of the second executable
+    pub exec_time_b: ExecTimings,
+
+    /// Size of the generated inputs
+    pub input_size: SizeStats,
+
+    /// Size of the first executable's outputs
+    pub output_size_a: SizeStats,
+
+    /// Size of the second executable's outputs
+    pub output_size_b: SizeStats,
+
+    /// Format of the reported summary
+    pub format: OutputFormat
+}
+
+/// Format of the summary printed after fuzzing.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable summary
+    #[default]
+    Text,
+    /// A JSON object, for machines (e.g CI)
+    Json,
+    /// A Test Anything Protocol stream: a line for every test while fuzzing, then the plan, with
+    /// the summary as comments
+    Tap
 }
 
 impl AppResultData {
-    pub fn new(log_file: Option<PathBuf>) -> Self {
+    pub fn new(log_file: Option<PathBuf>, seed: u64, format: OutputFormat) -> Self {
         Self {
             successful_tests: 0,
             failed_tests: 0,
+            crashed_tests: 0,
             error_tests: 0,
-            log_file
+            abandoned_tests: 0,
+            log_file,
+            dry_run: false,
+            error_is_failure: false,
+            seed,
+            failed_test_indices: Vec::new(),
+            unlogged_failures: 0,
+            warmup: 0,
+            slow_tests: 0,
+            tle_ratio: None,
+            exec_time_a: ExecTimings::default(),
+            exec_time_b: ExecTimings::default(),
+            input_size: SizeStats::default(),
+            output_size_a: SizeStats::default(),
+            output_size_b: SizeStats::default(),
+            format
+        }
+    }
+
+    /// Serialize the result into a JSON object, e.g for CI consumption.
+    pub fn to_json(&self) -> String {
+        json!({
+            "successful_tests": self.successful_tests,
+            "failed_tests": self.failed_tests,
+            "crashed_tests": self.crashed_tests,
+            "error_tests": self.error_tests,
+            "abandoned_tests": self.abandoned_tests,
+            "seed": self.seed,
+            "failed_test_indices": self.failed_test_indices,
+            "unlogged_failures": self.unlogged_failures,
+            "slow_tests": self.slow_tests,
+            "exec_time_a": self.exec_time_a.to_json(),
+            "exec_time_b": self.exec_time_b.to_json(),
+            "input_size": self.input_size.to_json(),
+            "output_size_a": self.output_size_a.to_json(),
+            "output_size_b": self.output_size_b.to_json(),
+        }).to_string()
+    }
+}
+
+impl AppResultData {
+    /// Record the running time of both executables on a test, unless it is a warmup test.
+    ///
+    /// # Arguments
+    /// - `test_no`: the (1-indexed) test number
+    /// - `duration_a`: the first executable's running time
+    /// - `duration_b`: the second executable's running time
+    pub fn record_durations(&mut self, test_no: u64, duration_a: Duration, duration_b: Duration) {
+        if test_no > self.warmup {
+            self.exec_time_a.record(duration_a);
+            self.exec_time_b.record(duration_b);
+        }
+    }
+
+    /// Count a test as slow when one executable ran more than `tle_ratio` times longer than the
+    /// other. Warmup tests and runs shorter than `TLE_MIN_DURATION` are never slow.
+    ///
+    /// # Arguments
+    /// - `test_no`: the (1-indexed) test number
+    /// - `duration_a`: the first executable's running time
+    /// - `duration_b`: the second executable's running time
+    ///
+    /// # Returns
+    /// An `Option` containing the slow executable and how many times longer it ran, when the test
+    /// is slow.
+    pub fn record_slow(&mut self, test_no: u64, duration_a: Duration, duration_b: Duration) -> Option<(ExecutableId, f64)> {
+        let ratio = self.tle_ratio.filter(|_| test_no > self.warmup)?;
+        let (which, slow, fast) = if duration_a >= duration_b {
+            (ExecutableId::A, duration_a, duration_b)
+        } else {
+            (ExecutableId::B, duration_b, duration_a)
+        };
+        if slow < TLE_MIN_DURATION || slow.as_secs_f64() <= fast.as_secs_f64() * ratio {
+            return None
+        }
+        self.slow_tests += 1;
+        Some((which, slow.as_secs_f64() / fast.as_secs_f64()))
+    }
+
+    /// Record the size of a test's input and of both executables' outputs. Unlike running times,
+    /// warmup tests are recorded too.
+    ///
+    /// # Arguments
+    /// - `sizes`: the input's, first output's and second output's size, in bytes
+    pub fn record_sizes(&mut self, sizes: (usize, usize, usize)) {
+        self.input_size.record(sizes.0 as u64);
+        self.output_size_a.record(sizes.1 as u64);
+        self.output_size_b.record(sizes.2 as u64);
+    }
+
+    /// Count a test skipped due to an error.
+    pub fn record_error(&mut self, err: &AppError) {
+        self.error_tests += 1;
+        if let AppError::ConstraintUnsatisfiable(..) | AppError::SharedVariableConflict(..) = err {
+            self.abandoned_tests += 1;
+        }
+    }
+
+    /// The process' exit code: `EXIT_MISMATCH` when a test failed or crashed, otherwise
+    /// `EXIT_HARNESS_ERROR` when a test errored out and `error_is_failure` is set, otherwise 0.
+    /// Without `error_is_failure`, a run where every test errored out exits with 0.
+    fn exit_code(&self) -> u8 {
+        if self.failed_tests > 0 || self.crashed_tests > 0 {
+            EXIT_MISMATCH
+        } else if self.error_is_failure && self.error_tests > 0 {
+            EXIT_HARNESS_ERROR
+        } else {
+            0
         }
     }
 }
@@ -87,7 +501,7 @@ impl AppResultData {
 impl Display for AppResultData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut string = String::new();
-        if self.failed_tests > 0 {
+        if self.failed_tests > 0 || self.crashed_tests > 0 {
             string.push_str("\n--TESTS FINISHED WITH WARNING--\n");
         } else {
             string.push_str("\n--TESTS FINISHED--\n");
@@ -95,9 +509,23 @@ impl Display for AppResultData {
         }
 
         string.push_str(&format!("Ok      : {}\n", self.successful_tests));
-        string.push_str(&format!("Failed  : {}\n", self.failed_tests));
-        string.push_str(&format!("Error   : {}\n", self.error_tests));
+        if self.unlogged_failures > 0 {
+            string.push_str(&format!("Failed  : {} ({} not logged)\n", self.failed_tests, self.unlogged_failures));
+        } else {
+            string.push_str(&format!("Failed  : {}\n", self.failed_tests));
+        }
+        string.push_str(&format!("Crashed : {}\n", self.crashed_tests));
+        string.push_str(&format!("Error   : {} ({} out of retries)\n", self.error_tests, self.abandoned_tests));
+        if self.tle_ratio.is_some() {
+            string.push_str(&format!("Slow    : {}\n", self.slow_tests));
+        }
         string.push_str(&format!("Log file: {:?}\n", self.log_file));
+        string.push_str(&format!("Seed    : {}\n", self.seed));
+        string.push_str(&format!("Time A  : {}\n", self.exec_time_a));
+        string.push_str(&format!("Time B  : {}\n", self.exec_time_b));
+        string.push_str(&format!("Input   : {}\n", self.input_size));
+        string.push_str(&format!("Output A: {}\n", self.output_size_a));
+        string.push_str(&format!("Output B: {}\n", self.output_size_b));
 
         write!(f, "{}", string)
         
@@ -106,34 +534,82 @@ impl Display for AppResultData {
 }
 impl Termination for AppResultData {
     fn report(self) -> std::process::ExitCode {
-        let mut exit_code = 0;
-        if self.failed_tests > 0 {
-            exit_code = 1;
+        if self.dry_run {
+            return ExitCode::SUCCESS
         }
 
-        println!("{}", self.to_string());
+        match self.format {
+            OutputFormat::Text => println!("{}", self),
+            OutputFormat::Json => println!("{}", self.to_json()),
+            OutputFormat::Tap => println!("{}", self.to_string().trim().lines().map(|line| format!("# {}", line)).collect::<Vec<_>>().join("\n")),
+        }
 
-        ExitCode::from(exit_code)
+        ExitCode::from(self.exit_code())
     }
 }
 
-pub(crate) type AppResult<T> = Result<T, AppError>;
+pub type AppResult<T> = Result<T, AppError>;
 
-impl Debug for AppError {
+impl AppError {
+    /// The process' exit code when the run stops with this error: `EXIT_USAGE_ERROR` for errors
+    /// checked while parsing the CLI arguments or the spec, `EXIT_HARNESS_ERROR` for the others.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::InvalidExpression(..) | Self::SameExecutable | Self::NotExecutable(_) | Self::InvalidArgument(_)
+            | Self::InvalidConfig(..) | Self::FileNotFound(_) | Self::DirectoryNotFound(_) | Self::InvalidSyntax(..)
+            | Self::DoubleDeclaration(_) | Self::UndeclaredVariable(_) | Self::NoLength(_) | Self::CyclicDependency(_)
+            | Self::RangeTooSmall { .. } | Self::ValueTooLarge(..) | Self::UnknownBound(..) | Self::InSpecFile(..)
+            | Self::MultipleInputOrder | Self::NoInputOrder | Self::NoExpressions | Self::InvalidLengthRange(..) => EXIT_USAGE_ERROR,
+            Self::IOError(_) | Self::UnresolvedArrayLength(..) | Self::InvalidArraySize(..)
+            | Self::RangeTooSmallForDistinct(..) | Self::SumCapTooSmall(..) | Self::SanityCheckFailed(..)
+            | Self::NoExpectedOutput(_) | Self::SharedVariableConflict(..) | Self::ConstraintUnsatisfiable(..)
+            | Self::InputTooLarge(_) | Self::NoOutput(_) | Self::OutputNotCaptured(..) => EXIT_HARNESS_ERROR,
+        }
+    }
+}
+
+impl Termination for AppError {
+    fn report(self) -> ExitCode {
+        eprintln!("Error: {}", self);
+        ExitCode::from(self.exit_code())
+    }
+}
+
+impl Display for AppError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::InvalidExpression(line, expr) => write!(f, "Invalid expression at line {}: {}", line, expr),
             Self::FileNotFound(file) => write!(f, "File not found: {}", file.display()),
+            Self::DirectoryNotFound(dir) => write!(f, "Directory not found: {}", dir.display()),
             Self::InvalidSyntax(line, str) => write!(f, "Invalid syntax at line {}: {}", line, str),
             Self::DoubleDeclaration(var) => write!(f, "Variable declared twice: {}", var),
-            Self::UndeclaredVariable(var) => write!(f, "Undeclared variable written in input order: {}", var),
+            Self::UndeclaredVariable(var) => write!(f, "Undeclared variable used: {}", var),
+            Self::NoLength(var) => write!(f, "Only arrays and strings have a length to write before them in the input order: {}", var),
+            Self::CyclicDependency(expr) => write!(f, "Array lengths depend on each other in a cycle at expression '{}'", expr),
+            Self::UnknownBound(var, expr) => write!(f, "No expression gives variable {} a maximum, needed by expression '{}'", var, expr),
+            Self::RangeTooSmall { line, expr, min, max, needed } => write!(f, "Range from {} to {} is too small at line {}: `{}` needs {} distinct values, but only {} are available", min, max, line, expr, needed, max.abs_diff(*min).saturating_add(1)),
+            Self::ValueTooLarge(line, expr) => write!(f, "Value out of the 64-bit range at line {}: `{}` (pass --bigint to allow it)", line, expr),
+            Self::InSpecFile(file, err) => write!(f, "{}: {}", file.display(), err),
             Self::MultipleInputOrder => write!(f, "Input order is declared multiple times"),
             Self::NoInputOrder => write!(f, "No input order given"),
+            Self::NoExpressions => write!(f, "No expressions declared, so every input would be empty"),
             Self::IOError(kind) => write!(f, "I/O error: {}", kind),
             Self::SameExecutable => write!(f, "Two executables point to the same path"),
-            Self::InvalidArraySize(size, expr) => write!(f, "Invalid array size: {} at expression '{}'", size, expr),
-            Self::NoOutput(exe) => write!(f, "No output from executable {:?}!", exe),
-            Self::NotExecutable(exe) => write!(f, "{:?}: not an executable or is not executable", exe)
+            Self::UnresolvedArrayLength(line, decl) => write!(f, "Length has no value yet at line {}: `{}`", line, decl),
+            Self::InvalidArraySize(size, line, expr) => write!(f, "Invalid array size: {} at line {}: `{}`", size, line, expr),
+            Self::InvalidLengthRange(len, decl) => write!(f, "Length can be as small as {} (below 1) at '{}'", len, decl),
+            Self::RangeTooSmallForDistinct(count, line, expr) => write!(f, "Range too small for {} distinct values at line {}: `{}`", count, line, expr),
+            Self::SumCapTooSmall(min_sum, line, expr) => write!(f, "Sum cap is below the smallest possible sum {} at line {}: `{}`", min_sum, line, expr),
+            Self::SanityCheckFailed(case, msg) => write!(f, "Executable B failed sanity case #{}: {}", case, msg),
+            Self::NoExpectedOutput(file) => write!(f, "No expected output stored at {}", file.display()),
+            Self::SharedVariableConflict(line, expr) => write!(f, "Shared variables don't fit at line {}: `{}`", line, expr),
+            Self::ConstraintUnsatisfiable(line, constraint) => write!(f, "Cannot satisfy constraint at line {}: `{}`", line, constraint),
+            Self::InputTooLarge(limit) => write!(f, "Generated input would be longer than the limit of {} bytes", limit),
+            Self::NoOutput(exe) => write!(f, "No output from executable {} (--require-output is set)", exe.display()),
+            Self::OutputNotCaptured(exe, kind) => write!(f, "Cannot read the output of executable {}: {}", exe.display(), kind),
+            Self::InvalidConfig(file, msg) => write!(f, "Invalid config file {}: {}", file.display(), msg),
+            Self::InvalidArgument(msg) => write!(f, "Invalid argument: {}", msg),
+            Self::NotExecutable(exe) => write!(f, "{}: not an executable or is not executable. Make it executable (e.g `chmod +x`), or run it through an interpreter with --interpreter-a or --interpreter-b (e.g `python3`)", exe.display())
         }
     }
 }
@@ -143,3 +619,70 @@ impl From<std::io::Error> for AppError {
         Self::IOError(value.kind())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code_errors() {
+        let mut result = AppResultData::new(None, 0, OutputFormat::Text);
+        result.error_tests = 3;
+        assert_eq!(result.exit_code(), 0);
+
+        result.error_is_failure = true;
+        assert_eq!(result.exit_code(), 2);
+
+        result.failed_tests = 1;
+        assert_eq!(result.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_display() {
+        let err = AppError::InvalidSyntax(3, "1 <= N <=".into());
+        assert_eq!(err.to_string(), "Invalid syntax at line 3: 1 <= N <=");
+        assert_eq!(format!("{:?}", err), "InvalidSyntax(3, \"1 <= N <=\")");
+
+        let err = AppError::InSpecFile(PathBuf::from("extra.fuzz"), Box::new(AppError::UndeclaredVariable("M".into())));
+        assert_eq!(err.to_string(), "extra.fuzz: Undeclared variable used: M");
+        assert_eq!(AppError::NoOutput(PathBuf::from("./a.out")).to_string(), "No output from executable ./a.out (--require-output is set)");
+    }
+
+    #[test]
+    fn test_exit_code_categories() {
+        let mut result = AppResultData::new(None, 0, OutputFormat::Text);
+        assert_eq!(result.exit_code(), 0);
+        result.crashed_tests = 1;
+        assert_eq!(result.exit_code(), EXIT_MISMATCH);
+
+        assert_eq!(AppError::IOError(std::io::ErrorKind::NotFound).exit_code(), EXIT_HARNESS_ERROR);
+        assert_eq!(AppError::SanityCheckFailed(1, String::new()).exit_code(), EXIT_HARNESS_ERROR);
+        assert_eq!(AppError::InvalidArgument(String::new()).exit_code(), EXIT_USAGE_ERROR);
+        assert_eq!(AppError::NoInputOrder.exit_code(), EXIT_USAGE_ERROR);
+        assert_eq!(AppError::InSpecFile(PathBuf::new(), Box::new(AppError::NoExpressions)).exit_code(), EXIT_USAGE_ERROR);
+    }
+
+    #[test]
+    fn test_warmup_timings() {
+        let mut result = AppResultData::new(None, 0, OutputFormat::Text);
+        result.warmup = 2;
+        result.record_durations(1, Duration::from_secs(5), Duration::from_secs(6));
+        result.record_durations(2, Duration::from_millis(1), Duration::from_millis(1));
+        assert_eq!(result.exec_time_a, ExecTimings::default());
+        assert_eq!(result.exec_time_b, ExecTimings::default());
+
+        result.record_durations(3, Duration::from_millis(20), Duration::from_millis(30));
+        result.record_durations(4, Duration::from_millis(40), Duration::from_millis(10));
+        assert_eq!(result.exec_time_a, ExecTimings { runs: 2, total: Duration::from_millis(60), min: Some(Duration::from_millis(20)), max: Duration::from_millis(40) });
+        assert_eq!(result.exec_time_b, ExecTimings { runs: 2, total: Duration::from_millis(40), min: Some(Duration::from_millis(10)), max: Duration::from_millis(30) });
+        assert_eq!(result.exec_time_a.mean(), Some(Duration::from_millis(30)));
+    }
+
+    #[test]
+    fn test_timings_mean() {
+        assert_eq!(ExecTimings::default().mean(), None);
+        // More runs than a `u32` holds.
+        let timings = ExecTimings { runs: 1 << 32, total: Duration::from_secs(1 << 33), min: Some(Duration::from_secs(1)), max: Duration::from_secs(3) };
+        assert_eq!(timings.mean(), Some(Duration::from_secs(2)));
+    }
+}
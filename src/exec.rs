@@ -1,125 +1,652 @@
-use std::{collections::HashMap, io::{Read, Write}, path::{Path, PathBuf}, process::{Command, Stdio}};
+use std::{borrow::Cow, collections::{BTreeMap, HashMap}, env::temp_dir, fmt::{Debug, Display}, fs::{read_to_string, remove_file, write}, io::{Read, Write}, ops::RangeInclusive, path::{Path, PathBuf}, process::{self, Command, ExitStatus, Stdio}, sync::atomic::{AtomicU64, Ordering}, thread, time::{Duration, Instant}};
 
-use os_pipe::pipe;
-use rand::{distributions::Uniform, prelude::Distribution, rngs::ThreadRng, thread_rng};
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::json;
+use rand::{distributions::{Uniform, WeightedIndex}, prelude::Distribution, rngs::StdRng, seq::{index, SliceRandom}, Rng, SeedableRng};
 
-use crate::{error::{AppError, AppResult}, parser::{parser::{FuzzData, FuzzExpr}, tokenizer::{ComparisonType, ExprVariable, LenExpr}}};
+mod shrink;
 
-/// Variables that have been assigned values go here.
-#[derive(Debug)]
+use crate::{error::{AppError, AppResult}, parser::{parser::{FuzzData, FuzzExclusion, FuzzExpr, FuzzString, FuzzTree, LENGTH_MARKER}, tokenizer::{ComparisonType, ExprVariable, LenExpr, Value, VarFlags}}};
+
+/// Default of `RunnerOptions::max_input_bytes`: 64 MiB, far more than any judge's input.
+pub const DEFAULT_MAX_INPUT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// How the generated input is handed to the executables.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InputMode {
+    /// Piped into the executable's stdin
+    #[default]
+    Stdin,
+    /// Written to a temporary file, whose path is passed as the executable's last argument
+    File
+}
+
+/// Default of `RunnerOptions::max_retries`.
+pub const DEFAULT_MAX_RETRIES: u64 = 100;
+
+/// Default of `RunnerOptions::input_record_separator`.
+pub const DEFAULT_RECORD_SEPARATOR: &str = "\n";
+
+/// Variables that have been assigned values go here. Every map is ordered by name, so the same
+/// state is always listed (and logged) the same way.
+#[derive(Debug, Clone)]
 pub struct VarsData {
-    /// Hashmap containing variables as its key and value as its, well, values.
-    variables: HashMap<String, i64>,
-    /// Hashmap containing variables as its key and value (in the form of arrays) as its, well, values.
-    arrays: HashMap<String, Vec<i64>>
+    /// Map containing variables as its key and value as its, well, values.
+    variables: BTreeMap<String, Value>,
+    /// Map containing variables as its key and value (in the form of arrays) as its, well, values.
+    arrays: BTreeMap<String, Vec<Value>>,
+    /// Map containing matrix variables as its key and their rows as its values.
+    matrices: BTreeMap<String, Vec<Vec<Value>>>,
+    /// Map containing string variables as its key and the generated strings as its values.
+    strings: BTreeMap<String, String>,
+    /// Map containing tree variables as its key and the tree's edges as its values.
+    trees: BTreeMap<String, Vec<(Value, Value)>>
 }
 
 impl VarsData {
-    fn set_var(&mut self, key: &str, val: i64) {
+    fn set_var(&mut self, key: &str, val: Value) {
         self.variables.insert(key.to_string(), val);
     }
 
-    fn get_var(&self, key: &str) -> Option<&i64> {
+    pub fn get_var(&self, key: &str) -> Option<&Value> {
         self.variables.get(key)
     }
 
-    fn set_arr(&mut self, key: &str, val: Vec<i64>) {
+    fn set_arr(&mut self, key: &str, val: Vec<Value>) {
         self.arrays.insert(key.to_string(), val);
     }
 
-    fn get_arr(&self, key: &str) -> Option<&Vec<i64>> {
+    pub fn get_arr(&self, key: &str) -> Option<&Vec<Value>> {
         self.arrays.get(key)
     }
 
+    fn set_matrix(&mut self, key: &str, val: Vec<Vec<Value>>) {
+        self.matrices.insert(key.to_string(), val);
+    }
+
+    pub fn get_matrix(&self, key: &str) -> Option<&Vec<Vec<Value>>> {
+        self.matrices.get(key)
+    }
+
+    fn set_str(&mut self, key: &str, val: String) {
+        self.strings.insert(key.to_string(), val);
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&String> {
+        self.strings.get(key)
+    }
+
+    fn set_tree(&mut self, key: &str, val: Vec<(Value, Value)>) {
+        self.trees.insert(key.to_string(), val);
+    }
+
+    pub fn get_tree(&self, key: &str) -> Option<&Vec<(Value, Value)>> {
+        self.trees.get(key)
+    }
+
+    /// The variable state as a JSON object, with scalars, arrays, matrices, strings and trees in
+    /// separate objects.
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "variables": self.variables,
+            "arrays": self.arrays,
+            "matrices": self.matrices,
+            "strings": self.strings,
+            "trees": self.trees,
+        })
+    }
+
     fn new() -> Self {
         Self {
-            variables: HashMap::new(),
-            arrays: HashMap::new(),
+            variables: BTreeMap::new(),
+            arrays: BTreeMap::new(),
+            matrices: BTreeMap::new(),
+            strings: BTreeMap::new(),
+            trees: BTreeMap::new(),
+        }
+    }
+
+}
+
+/// Format a map with its keys sorted, e.g `{A: 1, B: 2}`.
+fn fmt_sorted<V: Debug>(f: &mut std::fmt::Formatter<'_>, map: &BTreeMap<String, V>) -> std::fmt::Result {
+    write!(f, "{{")?;
+    for (i, (key, val)) in map.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: {:?}", key, val)?;
+    }
+    write!(f, "}}")
+}
+
+impl Display for VarsData {
+    /// Scalars and arrays are always listed, matrices, strings and trees only when there are any.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "variables: ")?;
+        fmt_sorted(f, &self.variables)?;
+        write!(f, ", arrays: ")?;
+        fmt_sorted(f, &self.arrays)?;
+        if !self.matrices.is_empty() {
+            write!(f, ", matrices: ")?;
+            fmt_sorted(f, &self.matrices)?;
+        }
+        if !self.strings.is_empty() {
+            write!(f, ", strings: ")?;
+            fmt_sorted(f, &self.strings)?;
+        }
+        if !self.trees.is_empty() {
+            write!(f, ", trees: ")?;
+            fmt_sorted(f, &self.trees)?;
+        }
+        Ok(())
+    }
+}
+
+/// Pick `count` pairwise distinct values from an inclusive range, in random order.
+///
+/// # Arguments
+/// - `rng`: RNG mutable reference
+/// - `expr`: the current expression, used for error reporting
+/// - `count`: how many values to pick
+/// - `range`: the inclusive range to pick from
+///
+/// # Returns
+/// An `AppResult` containing the picked values, or `AppError::RangeTooSmallForDistinct` when the
+/// range holds less than `count` values.
+fn sample_distinct(rng: &mut impl Rng, expr: &FuzzExpr, count: usize, range: RangeInclusive<Value>) -> AppResult<Vec<Value>> {
+    let (min, max) = range.into_inner();
+    let available = if max < min { 0 } else { max.abs_diff(min).saturating_add(1) };
+    if count as u128 > available {
+        return Err(AppError::RangeTooSmallForDistinct(count as Value, expr.line, expr.to_string()))
+    }
+
+    let length = usize::try_from(available).unwrap_or(usize::MAX);
+    Ok(index::sample(rng, length, count).into_iter().map(|i| min + i as Value).collect())
+}
+
+/// A deterministic boundary assignment, ran before the random tests with `--sweep`. Every
+/// variable group is set to either the minimum or the maximum its ordering constraints allow.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SweepCase {
+    /// Every group at its minimum.
+    AllMin,
+    /// Every group at its maximum.
+    AllMax,
+    /// The first group at its minimum, the second at its maximum, and so on.
+    AlternateMinFirst,
+    /// The first group at its maximum, the second at its minimum, and so on.
+    AlternateMaxFirst,
+}
+
+impl SweepCase {
+    /// Whether the variable group at the given depth is set to its maximum.
+    fn picks_max(self, depth: usize) -> bool {
+        match self {
+            Self::AllMin => false,
+            Self::AllMax => true,
+            Self::AlternateMinFirst => !depth.is_multiple_of(2),
+            Self::AlternateMaxFirst => depth.is_multiple_of(2),
+        }
+    }
+}
+
+/// How values are picked while generating.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PickMode {
+    /// Uniform draws, except that an endpoint of the range is picked with the given probability
+    /// (the edge bias).
+    Random(f64),
+    /// Always an endpoint of the range, see `SweepCase`.
+    Sweep(SweepCase),
+}
+
+/// Settings shared by every step of filling an expression's variables.
+#[derive(Clone, Copy, Debug)]
+struct FillContext<'a> {
+    /// How values are picked.
+    mode: PickMode,
+    /// Most items an array, matrix, string or tree may hold. Every item takes at least one byte
+    /// of input, so this is the input size limit (see `RunnerOptions::max_input_bytes`), checked
+    /// before anything gets allocated.
+    max_items: u64,
+    /// Whether a variable already generated by an earlier expression keeps its value, see
+    /// `RunnerOptions::independent_vars`.
+    shared: bool,
+    /// Whether arrays may be empty, see `RunnerOptions::allow_empty_arrays`.
+    empty_arrays: bool,
+    /// The bounds of the variables shared between expressions, when they keep their value, see
+    /// `shared_bounds`.
+    bounds: Option<&'a HashMap<String, (Value, Value)>>,
+}
+
+impl From<PickMode> for FillContext<'_> {
+    fn from(mode: PickMode) -> Self {
+        Self { mode, max_items: u64::MAX, shared: false, empty_arrays: false, bounds: None }
+    }
+}
+
+/// Check the amount of items a variable is about to be filled with against the limit.
+///
+/// # Arguments
+/// - `items`: the amount of items, `None` when computing it overflowed
+/// - `max_items`: the most items allowed
+///
+/// # Returns
+/// An `AppResult` containing nothing, or `AppError::InputTooLarge` when there are too many items.
+fn check_items(items: Option<Value>, max_items: u64) -> AppResult<()> {
+    match items {
+        Some(items) if items <= 0 || u64::try_from(items).is_ok_and(|items| items <= max_items) => Ok(()),
+        _ => Err(AppError::InputTooLarge(max_items))
+    }
+}
+
+/// Picks values from an inclusive range: uniformly, (with probability `edge_bias`) one of the
+/// range's endpoints, since boundary values tend to catch the most bugs, or always the same
+/// endpoint when sweeping.
+struct Picker {
+    min: Value,
+    max: Value,
+    uniform: Uniform<Value>,
+    edge_bias: f64,
+    /// When set, the endpoint that is always picked: `true` for the maximum.
+    fixed_max: Option<bool>,
+}
+
+impl Picker {
+    fn new(range: RangeInclusive<Value>, mode: PickMode, depth: usize) -> Self {
+        let (edge_bias, fixed_max) = match mode {
+            PickMode::Random(edge_bias) => (edge_bias, None),
+            PickMode::Sweep(case) => (0.0, Some(case.picks_max(depth))),
+        };
+
+        Self {
+            min: *range.start(),
+            max: *range.end(),
+            uniform: Uniform::from(range),
+            edge_bias,
+            fixed_max,
+        }
+    }
+
+    /// Pick a value. With `log`, the distance from the minimum is picked log-uniformly: an
+    /// exponent is picked uniformly, then the value, so every order of magnitude is about as likely.
+    fn sample(&self, rng: &mut impl Rng, log: bool) -> Value {
+        match self.fixed_max {
+            Some(true) => self.max,
+            Some(false) => self.min,
+            None if self.edge_bias > 0.0 && rng.gen_bool(self.edge_bias) => {
+                if rng.gen_bool(0.5) { self.min } else { self.max }
+            }
+            None if log => {
+                let span = self.max.abs_diff(self.min);
+                let offset = ((span as f64 + 1.0).powf(rng.gen::<f64>()) as u128).saturating_sub(1);
+                self.min.saturating_add_unsigned(offset.min(span))
+            }
+            None => self.uniform.sample(rng)
+        }
+    }
+
+    /// Pick `count` pairwise distinct values, see `sample_distinct`. When sweeping, these are the
+    /// `count` values closest to the fixed endpoint.
+    fn sample_distinct(&self, rng: &mut impl Rng, expr: &FuzzExpr, count: usize) -> AppResult<Vec<Value>> {
+        let Some(fixed_max) = self.fixed_max else {
+            return sample_distinct(rng, expr, count, self.min..=self.max)
+        };
+
+        if count as u128 > self.max.abs_diff(self.min).saturating_add(1) {
+            return Err(AppError::RangeTooSmallForDistinct(count as Value, expr.line, expr.to_string()))
+        }
+        if fixed_max {
+            Ok((0..count as Value).map(|i| self.max - i).collect())
+        } else {
+            Ok((0..count as Value).map(|i| self.min + i).collect())
+        }
+    }
+}
+
+/// Lower the items of an array until their sum is at most `cap`. The excess is first taken off
+/// random amounts of the items in random order, then off whatever items still exceed `min`.
+///
+/// # Arguments
+/// - `rng`: RNG mutable reference
+/// - `expr`: the current expression, used for error reporting
+/// - `values`: the items, all at least `min`
+/// - `min`: the smallest value an item may take
+/// - `cap`: the largest sum allowed
+///
+/// # Returns
+/// An `AppResult` containing nothing, or `AppError::SumCapTooSmall` when even `values.len()`
+/// items at `min` exceed `cap`.
+fn cap_sum(rng: &mut impl Rng, expr: &FuzzExpr, values: &mut [Value], min: Value, cap: Value) -> AppResult<()> {
+    let min_sum = (values.len() as Value).saturating_mul(min);
+    if min_sum > cap {
+        return Err(AppError::SumCapTooSmall(min_sum, expr.line, expr.to_string()))
+    }
+
+    let mut excess = values.iter().fold(0, |sum: Value, val| sum.saturating_add(*val)).saturating_sub(cap);
+    let order = index::sample(rng, values.len(), values.len());
+    for randomized in [true, false] {
+        for i in order.iter() {
+            if excess <= 0 {
+                return Ok(())
+            }
+            let reducible = excess.min(values[i].saturating_sub(min));
+            let cut = if randomized { rng.gen_range(0..=reducible) } else { reducible };
+            values[i] -= cut;
+            excess -= cut;
         }
     }
+    Ok(())
+}
+
+/// Pick the items of an array (or the flattened items of a matrix), respecting its generation
+/// constraints.
+///
+/// # Arguments
+/// - `rng`: RNG mutable reference
+/// - `expr`: the current expression, used for error reporting
+/// - `count`: how many items to pick
+/// - `flags`: the array's generation constraints
+/// - `picker`: picks from the inclusive range of the array's items. Distinct items are never
+///   biased toward the endpoints.
+///
+/// # Returns
+/// An `AppResult` containing the picked items, an `AppError` otherwise.
+fn sample_values(rng: &mut impl Rng, expr: &FuzzExpr, count: usize, flags: VarFlags, picker: &Picker) -> AppResult<Vec<Value>> {
+    let mut new_vec = if flags.distinct {
+        picker.sample_distinct(rng, expr, count)?
+    } else {
+        (0..count).map(|_| picker.sample(rng, flags.log)).collect()
+    };
+
+    if let Some(cap) = flags.sum_cap {
+        cap_sum(rng, expr, &mut new_vec, picker.min, cap)?;
+    }
+    if flags.sorted {
+        new_vec.sort_unstable();
+    }
+    Ok(new_vec)
+}
 
+/// Resolve the length of an array, matrix, string or tree from the variables generated so far.
+///
+/// # Arguments
+/// - `len`: the length
+/// - `data`: the data struct that holds variable values
+/// - `line`: the line of the declaration, for the error
+/// - `repr`: the declaration, for the error
+///
+/// # Returns
+/// An `AppResult` containing the length, or `AppError::UnresolvedArrayLength` when its variable
+/// has no value yet (or the offset overflows).
+fn resolve_len(len: &LenExpr, data: &VarsData, line: u64, repr: &str) -> AppResult<Value> {
+    len.resolve(|key| data.get_var(key).copied()).ok_or_else(|| AppError::UnresolvedArrayLength(line, repr.to_string()))
 }
 
 /// Fill an array to a `VarsData` based on given parameters. Accesses to variable values is
 /// possible when the array has length of a specific set variable.
 ///
 /// # Arguments
-/// - `rng`: thread-local RNG mutable reference
+/// - `rng`: RNG mutable reference
 /// - `data`: the data struct that holds variable values
 /// - `size`: length of the array
-/// - `min`: minimum value of the array's items
-/// - `max`: maximum value of tfailed_testshfailed_testse array's items
-fn fill_array(rng: &mut ThreadRng, expr: &FuzzExpr, data: &mut VarsData, key: &str, size: &LenExpr, min: i64, max: i64) -> AppResult<i64> {
-    let mut new_vec = Vec::new();
-    let range = Uniform::from(min..=max);
-
-    let count = match size {
-        LenExpr::Variable(key) => *data.get_var(&key).expect("Failed to retrieve value from variable"),
-        LenExpr::Constant(val) => *val,
-    };
+/// - `flags`: the array's generation constraints
+/// - `picker`: picks from the inclusive range of the array's items
+/// - `allow_empty`: whether the length may be 0
+#[allow(clippy::too_many_arguments)]
+fn fill_array(rng: &mut impl Rng, expr: &FuzzExpr, data: &mut VarsData, key: &str, size: &LenExpr, flags: VarFlags, picker: &Picker, allow_empty: bool) -> AppResult<Value> {
+    let count = resolve_len(size, data, expr.line, &expr.repr)?;
 
-    if count < 1 {
-        return Err(AppError::InvalidArraySize(count, expr.to_string()));
+    if count < 1 - allow_empty as Value {
+        Err(AppError::InvalidArraySize(count, expr.line, expr.to_string()))
     } else {
-        let mut max: i64 = 0;
-        for _ in 0..=count {
-            let new = range.sample(rng);
-            max = max.max(new);
-            new_vec.push(new);
-        }
-
+        let new_vec = sample_values(rng, expr, count as usize, flags, picker)?;
+        let max = new_vec.iter().fold(Value::MIN, |max, new| max.max(*new));
         data.set_arr(key, new_vec);
-        return Ok(max)
+        Ok(max)
     }
 }
 
-fn recurse_set_variables(rng: &mut ThreadRng, expr: &FuzzExpr, data: &mut VarsData) -> AppResult<()> {
-    let min = if expr.comparisons[0] == ComparisonType::LessThan {
-        expr.const_min + 1
+/// Fill a matrix to a `VarsData` based on given parameters. Works like `fill_array`, with the
+/// generation constraints applying to the items in row-major order.
+///
+/// # Arguments
+/// - `rng`: RNG mutable reference
+/// - `data`: the data struct that holds variable values
+/// - `dims`: amount of rows and columns of the matrix
+/// - `flags`: the matrix's generation constraints
+/// - `picker`: picks from the inclusive range of the matrix's items
+fn fill_matrix(rng: &mut impl Rng, expr: &FuzzExpr, data: &mut VarsData, key: &str, dims: (&LenExpr, &LenExpr), flags: VarFlags, picker: &Picker) -> AppResult<Value> {
+    let rows = resolve_len(dims.0, data, expr.line, &expr.repr)?;
+    let cols = resolve_len(dims.1, data, expr.line, &expr.repr)?;
+
+    if rows < 1 {
+        Err(AppError::InvalidArraySize(rows, expr.line, expr.to_string()))
+    } else if cols < 1 {
+        Err(AppError::InvalidArraySize(cols, expr.line, expr.to_string()))
     } else {
-        expr.const_min
+        let items = sample_values(rng, expr, (rows * cols) as usize, flags, picker)?;
+        let max = items.iter().fold(Value::MIN, |max, new| max.max(*new));
+        data.set_matrix(key, items.chunks(cols as usize).map(<[Value]>::to_vec).collect());
+        Ok(max)
+    }
+}
+
+/// Build a palindrome of a given length by mirroring its first half, e.g `abc` into `abcba` for a
+/// length of 5. Only the first `(len + 1) / 2` characters of `half` are used.
+fn mirror_half(half: &[char], len: usize) -> String {
+    let half = &half[..half.len().min(len.div_ceil(2))];
+    half.iter().chain(half[..len / 2].iter().rev()).collect()
+}
+
+/// Fill a string to a `VarsData`, picking each character from the alphabet. Like arrays, the
+/// length may be a constant or depend on a set variable. A palindrome only has its first half
+/// picked, which gets mirrored.
+///
+/// # Arguments
+/// - `rng`: RNG mutable reference
+/// - `string`: the string variable's declaration
+/// - `data`: the data struct that holds variable values
+/// - `alphabet`: the characters to pick from
+/// - `distinct`: the most distinct characters the string may hold, if limited. They are picked
+///   from the alphabet at random for each string.
+fn fill_string(rng: &mut impl Rng, string: &FuzzString, data: &mut VarsData, alphabet: &[char], distinct: Option<usize>) -> AppResult<()> {
+    let len = resolve_len(&string.len, data, string.line, &string.repr)?;
+
+    if len < 1 {
+        return Err(AppError::InvalidArraySize(len, string.line, string.to_string()))
+    }
+
+    let symbols: Vec<char> = match distinct {
+        Some(distinct) if distinct < alphabet.len() => index::sample(rng, alphabet.len(), distinct).iter().map(|i| alphabet[i]).collect(),
+        _ => alphabet.to_vec()
     };
-    _recurse_set_variables(rng, expr, data, 0, min)?;
+    let range = Uniform::from(0..symbols.len());
+    let picked = if string.palindrome { (len as usize).div_ceil(2) } else { len as usize };
+    let chars: Vec<char> = (0..picked).map(|_| symbols[range.sample(rng)]).collect();
+    let new_string = if string.palindrome { mirror_half(&chars, len as usize) } else { chars.into_iter().collect() };
+    data.set_str(&string.name, new_string);
+    Ok(())
+}
+
+/// Fill a random labeled tree to a `VarsData`, as its edges. Every node after the first (in a
+/// random labeling) gets attached to a random earlier node, then the edges are shuffled and each
+/// edge's endpoints are swapped at random, so neither the labels nor the edge order give away the
+/// tree's shape.
+///
+/// # Arguments
+/// - `rng`: RNG mutable reference
+/// - `tree`: the tree's declaration
+/// - `data`: the data struct that holds variable values
+fn fill_tree(rng: &mut impl Rng, tree: &FuzzTree, data: &mut VarsData) -> AppResult<()> {
+    let nodes = resolve_len(&tree.nodes, data, tree.line, &tree.repr)?;
+
+    if nodes < 1 {
+        return Err(AppError::InvalidArraySize(nodes, tree.line, tree.to_string()))
+    }
+
+    let labels = index::sample(rng, nodes as usize, nodes as usize);
+    let mut edges: Vec<(Value, Value)> = (1..nodes as usize)
+        .map(|i| {
+            let (parent, child) = (labels.index(rng.gen_range(0..i)) as Value + 1, labels.index(i) as Value + 1);
+            if rng.gen_bool(0.5) { (parent, child) } else { (child, parent) }
+        })
+        .collect();
+    edges.shuffle(rng);
+    data.set_tree(&tree.name, edges);
     Ok(())
 }
 
+/// Build the picker of an expression's first variable group, which can be reused for every
+/// generation as its range only depends on the expression itself.
+fn first_picker(expr: &FuzzExpr, mode: PickMode) -> Picker {
+    Picker::new(expr.group_mins[0]..=expr.group_maxes[0], mode, 0)
+}
+
+/// The largest value each variable group of an expression may take, given the variables of the
+/// expression that already hold a value (shared with an earlier expression): every group before
+/// such a variable has to stay below it.
+///
+/// # Arguments
+/// - `expr`: the expression
+/// - `data`: the variable store holding the already generated variables
+///
+/// # Returns
+/// The maxima, one for each variable group.
+fn shared_group_maxes(expr: &FuzzExpr, data: &VarsData) -> Vec<Value> {
+    let mut maxes = expr.group_maxes.clone();
+    let mut bound = Value::MAX;
+    for depth in (0..expr.vars.len()).rev() {
+        maxes[depth] = maxes[depth].min(bound);
+        let fixed_min = expr.vars[depth].iter()
+            .filter_map(|var| match var {
+                ExprVariable::Variable(key, _) => data.get_var(key).copied(),
+                _ => None
+            })
+            .min();
+        if let Some(fixed_min) = fixed_min {
+            bound = bound.min(fixed_min);
+        }
+        if expr.comparisons[depth] == ComparisonType::LessThan {
+            bound = bound.saturating_sub(1);
+        }
+    }
+    maxes
+}
+
+fn recurse_set_variables<'a>(rng: &mut impl Rng, expr: &FuzzExpr, data: &mut VarsData, ctx: impl Into<FillContext<'a>>, first_picker: Option<&Picker>) -> AppResult<()> {
+    let ctx = ctx.into();
+    let has_shared = ctx.shared && expr.vars.iter().flatten().any(|var| matches!(var, ExprVariable::Variable(key, _) if data.get_var(key).is_some()));
+    if !has_shared {
+        return _recurse_set_variables(rng, expr, data, 0, expr.group_mins[0], ctx, &expr.group_maxes, first_picker)
+    }
+
+    // The prebuilt picker's range doesn't account for the shared variables.
+    let maxes = shared_group_maxes(expr, data);
+    _recurse_set_variables(rng, expr, data, 0, expr.group_mins[0], ctx, &maxes, None)
+}
+
 /// Recursively set variable values from the expressions stack.
 /// 
 /// # Arguments
-/// - `rng`: thread-local RNG mutable reference
+/// - `rng`: RNG mutable reference
 /// - `expr`: the current expression we're working with
 /// - `data`: struct containing variable hashmaps
 /// - `depth`: the current depth
 /// - `min`: the minimum value from previous variable's value
+/// - `ctx`: how values are picked from the allowed range, and how many items are allowed
+/// - `maxes`: the largest value each variable group may take
+/// - `first_picker`: a prebuilt picker for the first variable group, whose range never changes
 ///
 /// # Returns
 /// An AppError when an error occurs. Nothing otherwise.
-fn _recurse_set_variables(rng: &mut ThreadRng, expr: &FuzzExpr, data: &mut VarsData, depth: usize, min: i64) -> AppResult<()> {
-    let vars_len = expr.vars.len();
-    let mut run_min = if expr.comparisons[0] == ComparisonType::LessThan {
-        expr.const_min + 1
-    } else {
-        expr.const_min
-    };
-    if depth < vars_len {
-        run_min = min;
-    }
-    if depth == vars_len {
+#[allow(clippy::too_many_arguments)]
+fn _recurse_set_variables(rng: &mut impl Rng, expr: &FuzzExpr, data: &mut VarsData, depth: usize, min: Value, ctx: FillContext, maxes: &[Value], first_picker: Option<&Picker>) -> AppResult<()> {
+    if depth == expr.vars.len() {
         return Ok(())
     }
-    let max = expr.const_max - (expr.comparisons[depth + 1..].iter().filter(|x| x == &&ComparisonType::LessThan).count() as i64);
-    let range = Uniform::from(run_min..=max);
+    let max = maxes[depth];
+    // A shared variable keeps its value, which has to fit in this group's range.
+    let shared_vals: Vec<Option<Value>> = expr.vars[depth].iter()
+        .map(|var| match var {
+            ExprVariable::Variable(key, _) if ctx.shared => data.get_var(key).copied(),
+            _ => None
+        })
+        .collect();
+    if max < min || shared_vals.iter().flatten().any(|val| *val < min || *val > expr.group_maxes[depth]) {
+        return Err(AppError::SharedVariableConflict(expr.line, expr.to_string()))
+    }
+
+    let built_picker;
+    let picker = match first_picker {
+        Some(picker) if depth == 0 => picker,
+        _ => {
+            built_picker = Picker::new(min..=max, ctx.mode, depth);
+            &built_picker
+        }
+    };
+
+    for var in &expr.vars[depth] {
+        match var {
+            ExprVariable::Array(_, len, _) => check_items(Some(resolve_len(len, data, expr.line, &expr.repr)?), ctx.max_items)?,
+            ExprVariable::Matrix(_, rows, cols, _) => {
+                let (rows, cols) = (resolve_len(rows, data, expr.line, &expr.repr)?, resolve_len(cols, data, expr.line, &expr.repr)?);
+                check_items(rows.checked_mul(cols), ctx.max_items)?
+            }
+            ExprVariable::Variable(..) | ExprVariable::Choice(..) => ()
+        }
+    }
 
-    let mut n_max = 0; // current max value for the entire VariableGroup
+    let mut n_max = shared_vals.iter().flatten().fold(min, |acc, val| acc.max(*val)); // current max value for the entire VariableGroup
 
-    for i in 0..expr.vars[depth].len() {
-        if let ExprVariable::Variable(key) = &expr.vars[depth][i] {
-            let randomly_picked = range.sample(rng);
+    // Distinct variables of the group are picked together so they don't collide with each other.
+    let distinct_count = expr.vars[depth].iter().zip(&shared_vals).filter(|(var, shared)| matches!(var, ExprVariable::Variable(_, flags) if flags.distinct && shared.is_none())).count();
+    let mut distinct_picks = if distinct_count > 0 {
+        picker.sample_distinct(rng, expr, distinct_count)?
+    } else {
+        Vec::new()
+    };
+
+    for (var, shared_val) in expr.vars[depth].iter().zip(&shared_vals) {
+        if let ExprVariable::Variable(key, flags) = var {
+            if shared_val.is_some() {
+                continue
+            }
+            let randomly_picked = if flags.distinct {
+                distinct_picks.pop().expect("Picked as many distinct values as there are distinct variables")
+            } else {
+                match ctx.bounds.and_then(|bounds| bounds.get(key)) {
+                    // Picked within every expression's range, so the later ones fit around it.
+                    Some(&(shared_min, shared_max)) if shared_min > min || shared_max < max => {
+                        let (shared_min, shared_max) = (shared_min.max(min), shared_max.min(max));
+                        if shared_max < shared_min {
+                            return Err(AppError::SharedVariableConflict(expr.line, expr.to_string()))
+                        }
+                        Picker::new(shared_min..=shared_max, ctx.mode, depth).sample(rng, flags.log)
+                    }
+                    _ => picker.sample(rng, flags.log)
+                }
+            };
             n_max = n_max.max(randomly_picked);
-            data.set_var(&key, randomly_picked);
-        } else if let ExprVariable::Array(key, len) = &expr.vars[depth][i] {
-            let arr_max = fill_array(rng, expr, data, key, len, run_min, max)?;
+            data.set_var(key, randomly_picked);
+        } else if let ExprVariable::Array(key, len, flags) = var {
+            let arr_max = fill_array(rng, expr, data, key, len, *flags, picker, ctx.empty_arrays)?;
             n_max = n_max.max(arr_max);
+        } else if let ExprVariable::Matrix(key, rows, cols, flags) = var {
+            let matrix_max = fill_matrix(rng, expr, data, key, (rows, cols), *flags, picker)?;
+            n_max = n_max.max(matrix_max);
+        } else if let ExprVariable::Choice(key, values) = var {
+            // The range spans the listed values, so sweeping picks the smallest or the largest.
+            let picked = match picker.fixed_max {
+                Some(true) => picker.max,
+                Some(false) => picker.min,
+                None => *values.choose(rng).expect("A choice lists at least one value")
+            };
+            n_max = n_max.max(picked);
+            data.set_var(key, picked);
         }
     }
 
@@ -129,7 +656,95 @@ fn _recurse_set_variables(rng: &mut ThreadRng, expr: &FuzzExpr, data: &mut VarsD
         n_max
     };
 
-    return _recurse_set_variables(rng, expr, data, depth + 1, next_min);
+    _recurse_set_variables(rng, expr, data, depth + 1, next_min, ctx, maxes, first_picker)
+}
+
+/// The bounds of every variable appearing in more than one expression, merged over all of them
+/// (see `FuzzData::list_vars`). The first expression generating such a variable picks it within
+/// these, so a constant bound of a later expression never rules its value out.
+fn shared_bounds(data: &FuzzData) -> HashMap<String, (Value, Value)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for var in data.exprs.iter().flat_map(|expr| expr.vars.iter().flatten()) {
+        if let ExprVariable::Variable(key, _) = var {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+    data.list_vars().into_iter()
+        .filter(|listing| counts.get(listing.name.as_str()).is_some_and(|count| *count > 1))
+        .filter_map(|listing| Some((listing.name, listing.bounds?)))
+        .collect()
+}
+
+/// Whether both variables of an exclusion constraint hold different values.
+fn exclusion_holds(exclusion: &FuzzExclusion, data: &VarsData) -> bool {
+    data.get_var(&exclusion.left) != data.get_var(&exclusion.right)
+}
+
+/// Length of a number once written out, in bytes.
+fn num_len(val: Value) -> u64 {
+    (val.unsigned_abs().checked_ilog10().unwrap_or(0) + 1) as u64 + (val < 0) as u64
+}
+
+/// The wrap width of every array declared with `wrap(K)`, keyed by the array's name.
+fn array_wraps(data: &FuzzData) -> HashMap<String, usize> {
+    data.exprs.iter().flat_map(|expr| expr.vars.iter().flatten()).filter_map(|var| match var {
+        ExprVariable::Array(key, _, flags) => Some((key.clone(), flags.wrap?)),
+        _ => None
+    }).collect()
+}
+
+/// Split a name of the input order from its `LENGTH_MARKER`, if it has one.
+///
+/// # Arguments
+/// - `name`: the name as written in the input order
+/// - `vars`: variable data used to retrieve the length
+///
+/// # Returns
+/// The name without the marker, and when it was marked, the length of the array or string it
+/// names (`None` when it has no value).
+fn split_length<'a>(name: &'a str, vars: &VarsData) -> (&'a str, Option<usize>) {
+    match name.strip_suffix(LENGTH_MARKER) {
+        Some(name) => (name, vars.get_arr(name).map(Vec::len).or_else(|| vars.get_str(name).map(|val| val.chars().count()))),
+        None => (name, None)
+    }
+}
+
+/// Compute the length of the input `build_exec_input` would build, without building it.
+///
+/// # Arguments
+/// - `template`: array of variable names
+/// - `vars`: variable data used to retrieve the variable values
+/// - `sep`: the separator for each variable values
+/// - `record_sep`: the separator between records, see `RunnerOptions::input_record_separator`
+/// - `wraps`: the wrap width of the wrapped arrays, see `array_wraps`
+///
+/// # Returns
+/// The length of the input in bytes. Undeclared variables count as empty.
+fn exec_input_len(template: &[String], vars: &VarsData, sep: &str, record_sep: &str, wraps: &HashMap<String, usize>) -> u64 {
+    let sep_len = sep.len() as u64;
+    let row_sep_len = record_sep.len() as u64;
+    let items_len = |items: &[Value]| items.iter().map(|val| num_len(*val)).sum::<u64>() + items.len().saturating_sub(1) as u64 * sep_len;
+
+    let values_len: u64 = template.iter().map(|name| {
+        let (name, length) = split_length(name, vars);
+        length.map_or(0, |len| num_len(len as Value) + sep_len) + if let Some(val) = vars.get_var(name) {
+            num_len(*val)
+        } else if let Some(val) = vars.get_arr(name) {
+            match wraps.get(name) {
+                Some(wrap) => val.chunks(*wrap).map(items_len).sum::<u64>() + val.len().div_ceil(*wrap).saturating_sub(1) as u64 * row_sep_len,
+                None => items_len(val)
+            }
+        } else if let Some(val) = vars.get_matrix(name) {
+            val.iter().map(|row| items_len(row)).sum::<u64>() + val.len().saturating_sub(1) as u64 * row_sep_len
+        } else if let Some(val) = vars.get_str(name) {
+            val.len() as u64
+        } else if let Some(val) = vars.get_tree(name) {
+            val.iter().map(|(u, v)| num_len(*u) + sep_len + num_len(*v)).sum::<u64>() + val.len().saturating_sub(1) as u64 * row_sep_len
+        } else {
+            0
+        }
+    }).sum();
+    values_len + template.len().saturating_sub(1) as u64 * sep_len
 }
 
 /// Build the input for an executable, based on given information.
@@ -138,120 +753,1092 @@ fn _recurse_set_variables(rng: &mut ThreadRng, expr: &FuzzExpr, data: &mut VarsD
 /// - `template`: array of variable names
 /// - `vars`: variable data used to retrieve the variable values
 /// - `sep`: the separator for each variable values
+/// - `record_sep`: the separator between records, see `RunnerOptions::input_record_separator`
+/// - `wraps`: the wrap width of the wrapped arrays, see `array_wraps`
+/// - `max_bytes`: the longest input allowed, checked before building it
 ///
 /// # Returns
-/// An `AppResult` containing the built input when string is built successfuly. An AppError
+/// An `AppResult` containing the built input when string is built successfuly. An
+/// `AppError::InputTooLarge` when the input would be longer than `max_bytes`, another `AppError`
 /// otherwise.
-fn build_exec_input(template: &[String], vars: &VarsData, sep: &str) -> AppResult<String> {
-    let mut str = String::new();
-    let last_idx = template.len() - 1;
-    for i in 0..=last_idx {
-        if let Some(val) = vars.get_var(&template[i]) {
+fn build_exec_input(template: &[String], vars: &VarsData, sep: &str, record_sep: &str, wraps: &HashMap<String, usize>, max_bytes: u64) -> AppResult<String> {
+    let len = exec_input_len(template, vars, sep, record_sep, wraps);
+    if len > max_bytes {
+        return Err(AppError::InputTooLarge(max_bytes))
+    }
+
+    let mut str = String::with_capacity(len as usize);
+    let Some(last_idx) = template.len().checked_sub(1) else {
+        return Ok(str)
+    };
+    for (i, name) in template.iter().enumerate() {
+        let (name, length) = split_length(name, vars);
+        if let Some(len) = length {
+            str.push_str(&len.to_string());
+            str.push_str(sep);
+        }
+
+        if let Some(val) = vars.get_var(name) {
             str.push_str(&val.to_string());
 
-        } else if let Some(val) = vars.get_arr(&template[i]) {
-            let nums: Vec<String> = val.iter().map(ToString::to_string).collect();
-            str.push_str(&nums.join(&sep));
+        } else if let Some(val) = vars.get_arr(name) {
+            let join = |items: &[Value]| items.iter().map(ToString::to_string).collect::<Vec<_>>().join(sep);
+            match wraps.get(name) {
+                Some(wrap) => str.push_str(&val.chunks(*wrap).map(join).collect::<Vec<_>>().join(record_sep)),
+                None => str.push_str(&join(val))
+            }
+
+        } else if let Some(val) = vars.get_matrix(name) {
+            let rows: Vec<String> = val.iter().map(|row| row.iter().map(ToString::to_string).collect::<Vec<_>>().join(sep)).collect();
+            str.push_str(&rows.join(record_sep));
+
+        } else if let Some(val) = vars.get_str(name) {
+            str.push_str(val);
+
+        } else if let Some(val) = vars.get_tree(name) {
+            let edges: Vec<String> = val.iter().map(|(u, v)| format!("{}{}{}", u, sep, v)).collect();
+            str.push_str(&edges.join(record_sep));
 
         } else {
-            return Err(AppError::UndeclaredVariable(template[i].to_string()));
+            return Err(AppError::UndeclaredVariable(name.to_string()));
         }
 
         if i < last_idx {
-            str.push_str(&sep);
+            str.push_str(sep);
         }
     }
     Ok(str)
 
 }
 
+/// How a program is ran, apart from its input.
+#[derive(Clone, Copy, Debug, Default)]
+struct ExecSettings<'a> {
+    /// The interpreter running the program (e.g `python3`) along with its own arguments, the
+    /// program running directly when empty.
+    interpreter: &'a [String],
+    /// The command line arguments passed to the program.
+    args: &'a [String],
+    /// Environment variables set for the program, on top of the inherited ones.
+    env: &'a [(String, String)],
+    /// The working directory of the program, the current one if unset.
+    cwd: Option<&'a Path>,
+    /// The program's address space limit in bytes, only applied on Unix.
+    mem_limit: Option<u64>,
+    /// The judge conversing with the program, see `execute_interactive`. The input mode is
+    /// ignored when set.
+    judge: Option<&'a Path>,
+    /// How the program gets the input.
+    mode: InputMode,
+}
+
+impl ExecSettings<'_> {
+    /// A command running the program at `path` with these settings. Its standard streams are left
+    /// to the caller.
+    fn command(&self, path: &Path) -> Command {
+        // A relative program path is resolved against the child's working directory, so it is
+        // made absolute beforehand to keep it relative to ours. A bare name is looked up in `PATH`
+        // instead, unless it is handed to an interpreter.
+        let absolute = || std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let mut command = match (self.interpreter.split_first(), self.cwd) {
+            (Some((interpreter, interpreter_args)), cwd) => {
+                let mut command = Command::new(interpreter);
+                command.args(interpreter_args);
+                command.arg(if cwd.is_some() && path.is_relative() { absolute() } else { path.to_path_buf() });
+                command
+            }
+            (None, Some(_)) if path.is_relative() && path.components().count() > 1 => Command::new(absolute()),
+            (None, _) => Command::new(path)
+        };
+        command.args(self.args).envs(self.env.iter().map(|(key, val)| (key, val)));
+        if let Some(cwd) = self.cwd {
+            command.current_dir(cwd);
+        }
+        #[cfg(unix)]
+        if let Some(limit) = self.mem_limit {
+            use std::os::unix::process::CommandExt;
+
+            let limit = libc::rlimit { rlim_cur: limit as libc::rlim_t, rlim_max: limit as libc::rlim_t };
+            // SAFETY: `setrlimit` is async-signal-safe and the closure touches no other state, as
+            // required between `fork` and `exec`.
+            unsafe {
+                command.pre_exec(move || match libc::setrlimit(libc::RLIMIT_AS, &limit) {
+                    0 => Ok(()),
+                    _ => Err(std::io::Error::last_os_error())
+                });
+            }
+        }
+        command
+    }
+}
+
 /// Execute the program and capture its output.
 ///
 /// # Arguments
 /// - `path`: the executable's path
-/// - `input`: the input to feed into the program's stdin
+/// - `settings`: how the program is ran
+/// - `input`: the input to feed into the program
 ///
 /// # Outputs
-/// An AppResult containing the program's output when execution is successful. An `AppError`
-/// otherwise
-fn execute(path: &Path, input: &str) -> AppResult<String> {
-    let (read, mut write) = pipe()?;
-    write.write_all(&input.as_bytes())?;
-    drop(write);
-    let mut cmd = Command::new(path).stdin(read).stdout(Stdio::piped()).spawn()?;
-    let mut output = cmd.stdout.take().ok_or(AppError::NoOutput(path.to_path_buf()))?;
-    let mut str = String::new();
-    output.read_to_string(&mut str)?;
-    Ok(str)
+/// An AppResult containing the program's output and exit status when execution is successful. An
+/// `AppError` otherwise
+fn execute(path: &Path, settings: &ExecSettings, input: &str) -> AppResult<(String, ExitStatus)> {
+    match (settings.judge, settings.mode) {
+        (Some(judge), _) => execute_interactive(path, judge, settings, input),
+        (None, InputMode::Stdin) => execute_piped(path, settings, input),
+        (None, InputMode::File) => execute_with_file(path, settings, input)
+    }
 }
 
-fn split_and_compare(sep: &str, string_1: &str, string_2: &str) -> bool {
-    string_1.trim_start().trim_end().split(sep).eq(string_2.trim_start().trim_end().split(sep))
+/// Write the input to a new temporary file, run something given the file's path, then remove the
+/// file whatever the outcome.
+///
+/// # Arguments
+/// - `input`: the input to write
+/// - `run`: what to run with the file's path
+///
+/// # Returns
+/// What `run` returned, or the `AppError` of writing the file.
+fn with_input_file<T>(input: &str, run: impl FnOnce(&Path) -> AppResult<T>) -> AppResult<T> {
+    // Unique within this process, and the process id keeps parallel runs apart.
+    static INPUT_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+    let input_path = temp_dir().join(format!("beanfuzz-input-{}-{}", process::id(), INPUT_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)));
+    write(&input_path, input)?;
+    let result = run(&input_path);
+    // A leftover file is harmless, so failing to remove it is not an error.
+    let _ = remove_file(&input_path);
+    result
+}
 
+/// Execute the program with the input written to a temporary file, whose path is passed as the
+/// last command line argument. The file is removed afterwards.
+fn execute_with_file(path: &Path, settings: &ExecSettings, input: &str) -> AppResult<(String, ExitStatus)> {
+    let output = with_input_file(input, |input_path| Ok(settings.command(path).arg(input_path).stdin(Stdio::null()).stderr(Stdio::inherit()).output()?))?;
+    let str = String::from_utf8(output.stdout).map_err(|_| AppError::OutputNotCaptured(path.to_path_buf(), std::io::ErrorKind::InvalidData))?;
+    Ok((str, output.status))
 }
 
-pub struct Runner {
-    data: FuzzData,
-    variables_store: VarsData,
-    executable_1: PathBuf,
-    executable_2: PathBuf,
+/// Execute the program with the input piped into its stdin.
+fn execute_piped(path: &Path, settings: &ExecSettings, input: &str) -> AppResult<(String, ExitStatus)> {
+    let mut cmd = settings.command(path).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+    let mut stdin = cmd.stdin.take().expect("The child's stdin is piped");
+    let mut output = cmd.stdout.take().expect("The child's stdout is piped");
+
+    // The input is written on its own thread while the output is read here. Writing everything
+    // first deadlocks once the input outgrows the pipe buffer and the child blocks on writing its
+    // output, which nobody reads yet. A child may also exit (or close its stdin) before reading
+    // the whole input, e.g after the first line, in which case the rest of it is dropped.
+    let mut str = String::new();
+    thread::scope(|scope| {
+        let writer = scope.spawn(move || match stdin.write_all(input.as_bytes()) {
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            result => result
+        });
+        let read_result = output.read_to_string(&mut str);
+        writer.join().expect("The stdin writer thread panicked")?;
+        read_result.map_err(|err| AppError::OutputNotCaptured(path.to_path_buf(), err.kind()))
+    })?;
+    let status = cmd.wait()?;
+    Ok((str, status))
 }
 
-pub enum RunnerResult {
-    Ok,
-    Fail(String, String)
+/// Execute the program interactively. The judge gets the input written to a temporary file, whose
+/// path is passed as its last command line argument, and converses with the program: the judge's
+/// stdout is piped into the program's stdin and the other way around, turn by turn. The file is
+/// removed afterwards.
+///
+/// # Outputs
+/// An AppResult containing everything the program sent to the judge as its output, and the
+/// program's exit status. When the program exits successfully but the judge doesn't (e.g it
+/// rejected an answer), the judge's exit status is returned instead.
+fn execute_interactive(path: &Path, judge: &Path, settings: &ExecSettings, input: &str) -> AppResult<(String, ExitStatus)> {
+    with_input_file(input, |input_path| converse(path, judge, input_path, settings))
 }
 
-impl Runner {
-    pub fn new(data: FuzzData, executable_1: PathBuf, executable_2: PathBuf) -> Self {
-        Self {
-            data,
-            variables_store: VarsData::new(),
-            executable_1,
-            executable_2,
+/// Run the judge and the program with their standard streams connected, see `execute_interactive`.
+fn converse(path: &Path, judge: &Path, input_path: &Path, settings: &ExecSettings) -> AppResult<(String, ExitStatus)> {
+    let mut judge_cmd = Command::new(judge).arg(input_path).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+    let mut cmd = match settings.command(path).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn() {
+        Ok(cmd) => cmd,
+        Err(err) => {
+            let _ = judge_cmd.kill();
+            let _ = judge_cmd.wait();
+            return Err(err.into())
         }
-    }
+    };
+    let judge_stdin = judge_cmd.stdin.take().expect("The judge's stdin is piped");
+    let judge_stdout = judge_cmd.stdout.take().expect("The judge's stdout is piped");
+    let stdin = cmd.stdin.take().expect("The child's stdin is piped");
+    let output = cmd.stdout.take().expect("The child's stdout is piped");
 
-    pub fn run_once(&mut self) -> AppResult<RunnerResult>{
-        let exprs = &self.data.exprs;
-        let mut rng = thread_rng();
-        for expr in exprs {
-            recurse_set_variables(&mut rng, &expr, &mut self.variables_store)?;
-        }
-        let stdin = build_exec_input(&self.data.input_order, &self.variables_store, &self.data.input_separator)?;
-        let output_1 = execute(&self.executable_1, &stdin)?;
-        let output_2 = execute(&self.executable_2, &stdin)?;
+    // Each direction is relayed on its own thread, as either side may talk at any time.
+    let mut transcript = Vec::new();
+    thread::scope(|scope| {
+        let to_program = scope.spawn(move || relay(judge_stdout, stdin, None));
+        let from_program = relay(output, judge_stdin, Some(&mut transcript));
+        to_program.join().expect("The judge relay thread panicked")?;
+        from_program
+    }).map_err(|err| AppError::OutputNotCaptured(path.to_path_buf(), err.kind()))?;
 
-        if split_and_compare(&self.data.output_separator, &output_1, &output_2) {
-            return Ok(RunnerResult::Ok)
-        } else {
-            return Ok(RunnerResult::Fail(output_1, output_2))
+    let status = cmd.wait()?;
+    let judge_status = judge_cmd.wait()?;
+    let str = String::from_utf8(transcript).map_err(|_| AppError::OutputNotCaptured(path.to_path_buf(), std::io::ErrorKind::InvalidData))?;
+    Ok((str, if status.success() { judge_status } else { status }))
+}
+
+/// Copy everything read from `from` into `to` as soon as it arrives, until `from` is closed. `to`
+/// is closed afterwards. Once the other end of `to` is gone (e.g the process exited), the rest is
+/// still read but dropped.
+///
+/// # Arguments
+/// - `tee`: where to keep a copy of everything read, if anywhere
+fn relay(mut from: impl Read, mut to: impl Write, mut tee: Option<&mut Vec<u8>>) -> std::io::Result<()> {
+    let mut buf = [0; 8192];
+    let mut open = true;
+    loop {
+        let len = match from.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err)
+        };
+        if let Some(tee) = tee.as_deref_mut() {
+            tee.extend_from_slice(&buf[..len]);
+        }
+        if open {
+            match to.write_all(&buf[..len]) {
+                Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => open = false,
+                result => result?
+            }
         }
     }
+}
 
-    pub fn get_state(&self) -> &VarsData {
-        return &self.variables_store
-    }
+fn split_and_compare(sep: &str, string_1: &str, string_2: &str) -> bool {
+    string_1.trim_start().trim_end().split(sep).eq(string_2.trim_start().trim_end().split(sep))
 
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::parser::{parser::parse_expr_from_line, tokenizer::tokenize_expr_line};
+/// Compare two outputs. Without a record separator, the outputs are compared token by token (see
+/// `split_and_compare`). With one, the comparison is two-level: both outputs are split into
+/// records (e.g lines) first, which must be as many, then each pair of records is compared token
+/// by token. So `1 2\n3` and `1\n2 3` differ even when both separators are whitespace.
+///
+/// # Arguments
+/// - `token_sep`: the separator between tokens
+/// - `record_sep`: the separator between records, if any
+/// - `string_1`: the first output
+/// - `string_2`: the second output
+///
+/// # Returns
+/// A boolean indicating whether the outputs are equal.
+fn compare_outputs(token_sep: &str, record_sep: Option<&str>, string_1: &str, string_2: &str) -> bool {
+    let Some(record_sep) = record_sep else {
+        return split_and_compare(token_sep, string_1, string_2)
+    };
 
-    use super::*;
+    let records_1: Vec<&str> = string_1.trim().split(record_sep).collect();
+    let records_2: Vec<&str> = string_2.trim().split(record_sep).collect();
+    records_1.len() == records_2.len()
+        && records_1.iter().zip(&records_2).all(|(record_1, record_2)| split_and_compare(token_sep, record_1, record_2))
+}
 
-    #[test]
-    fn fill_variables_1() {
-        let expr_str = "1 < A < B <= 100";
-        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+/// Split an output into its records, each split into its tokens, and sort them.
+fn sorted_records<'a>(token_sep: &str, record_sep: &str, string: &'a str) -> Vec<Vec<&'a str>> {
+    let mut records: Vec<Vec<&str>> = string.trim().split(record_sep).map(|record| record.trim().split(token_sep).collect()).collect();
+    records.sort_unstable();
+    records
+}
+
+/// Compare two outputs as multisets of records: they must hold the same records as many times
+/// each, in any order. Records are compared token by token like in `compare_outputs`, so e.g
+/// `1 2\n3` and `3\n 1 2` are equal but `1 2\n3` and `1\n2 3` aren't.
+///
+/// # Arguments
+/// - `token_sep`: the separator between tokens
+/// - `record_sep`: the separator between records
+/// - `string_1`: the first output
+/// - `string_2`: the second output
+///
+/// # Returns
+/// A boolean indicating whether the outputs are equal.
+fn compare_unordered(token_sep: &str, record_sep: &str, string_1: &str, string_2: &str) -> bool {
+    sorted_records(token_sep, record_sep, string_1) == sorted_records(token_sep, record_sep, string_2)
+}
+
+/// Describe how two outputs differ as multisets of records, see `compare_unordered`, e.g
+/// "A has the line `1 2` more times than B".
+///
+/// # Arguments
+/// - `token_sep`: the separator between tokens
+/// - `record_sep`: the separator between records
+/// - `string_1`: the first executable's output
+/// - `string_2`: the second executable's (or expected) output
+///
+/// # Returns
+/// A `String` describing the first differing record, in sorted order.
+fn describe_unordered_mismatch(token_sep: &str, record_sep: &str, string_1: &str, string_2: &str) -> String {
+    let records_1 = sorted_records(token_sep, record_sep, string_1);
+    let records_2 = sorted_records(token_sep, record_sep, string_2);
+    let unit = if record_sep == "\n" { "line" } else { "record" };
+    if records_1.len() != records_2.len() {
+        return format!("A has {} {}s, B has {}", records_1.len(), unit, records_2.len())
+    }
+
+    // With as many records on both sides, the smaller of the first differing pair is held more
+    // times by its own side.
+    match records_1.iter().zip(&records_2).find(|(record_1, record_2)| record_1 != record_2) {
+        Some((record_1, record_2)) if record_1 < record_2 => format!("A has the {} `{}` more times than B", unit, token_excerpt(&record_1.join(token_sep))),
+        Some((_, record_2)) => format!("B has the {} `{}` more times than A", unit, token_excerpt(&record_2.join(token_sep))),
+        None => "no mismatch".to_string()
+    }
+}
+
+/// Longest token shown when describing a mismatch, in characters.
+const MISMATCH_TOKEN_MAX_CHARS: usize = 40;
+
+/// Show a token inside a one-line description: escaped (e.g newlines become `\n`) and cut short
+/// when it's too long.
+fn token_excerpt(token: &str) -> String {
+    let escaped: String = token.escape_debug().collect();
+    if escaped.chars().count() > MISMATCH_TOKEN_MAX_CHARS {
+        format!("{}...", escaped.chars().take(MISMATCH_TOKEN_MAX_CHARS).collect::<String>())
+    } else {
+        escaped
+    }
+}
+
+/// Describe where two outputs first differ, using the same structure as `compare_outputs`, e.g
+/// "first mismatch at line 5 token 2: A has `7`, B has `8`".
+///
+/// # Arguments
+/// - `token_sep`: the separator between tokens
+/// - `record_sep`: the separator between records, if any
+/// - `string_1`: the first executable's output
+/// - `string_2`: the second executable's (or expected) output
+///
+/// # Returns
+/// A `String` describing the first mismatch.
+fn describe_mismatch(token_sep: &str, record_sep: Option<&str>, string_1: &str, string_2: &str) -> String {
+    let records = |string: &str| -> Vec<String> {
+        match record_sep {
+            Some(record_sep) => string.trim().split(record_sep).map(str::to_string).collect(),
+            None => vec![string.to_string()],
+        }
+    };
+    let (records_1, records_2) = (records(string_1), records(string_2));
+    let unit = if record_sep == Some("\n") { "line" } else { "record" };
+
+    for i in 0..records_1.len().max(records_2.len()) {
+        let (Some(record_1), Some(record_2)) = (records_1.get(i), records_2.get(i)) else {
+            return format!("A has {} {}s, B has {}", records_1.len(), unit, records_2.len())
+        };
+        let location = if record_sep.is_some() { format!("{} {} ", unit, i + 1) } else { String::new() };
+        let tokens_1: Vec<&str> = record_1.trim().split(token_sep).collect();
+        let tokens_2: Vec<&str> = record_2.trim().split(token_sep).collect();
+        for j in 0..tokens_1.len().max(tokens_2.len()) {
+            match (tokens_1.get(j), tokens_2.get(j)) {
+                (Some(token_1), Some(token_2)) if token_1 == token_2 => continue,
+                (Some(token_1), Some(token_2)) => return format!("first mismatch at {}token {}: A has `{}`, B has `{}`", location, j + 1, token_excerpt(token_1), token_excerpt(token_2)),
+                (Some(token_1), None) => return format!("first mismatch at {}token {}: A has `{}`, B has nothing", location, j + 1, token_excerpt(token_1)),
+                (None, Some(token_2)) => return format!("first mismatch at {}token {}: A has nothing, B has `{}`", location, j + 1, token_excerpt(token_2)),
+                (None, None) => unreachable!("Both token lists are shorter than the longest one")
+            }
+        }
+    }
+    "no mismatch".to_string()
+}
+
+/// Optional behaviour of a `Runner`, mostly coming from the CLI arguments. More options keep
+/// being added, so outside this crate, start from `RunnerOptions::default()` and set the fields
+/// needed.
+#[non_exhaustive]
+pub struct RunnerOptions {
+    /// Inclusive range of the amount of test cases per input. When set, the input starts with the
+    /// amount of test cases `T`, followed by `T` independently generated blocks.
+    pub multi_test: Option<(u64, u64)>,
+
+    /// Inclusive range of the amount of independently generated blocks per input, like with
+    /// `multi_test` but without the count in front, e.g for executables keeping state across
+    /// queries read until the end of the input. The blocks are joined by the input record
+    /// separator, so each one starts on a record of its own. Ignored with `multi_test`.
+    pub blocks: Option<(u64, u64)>,
+
+    /// Seed of the RNG used to generate the inputs.
+    pub seed: u64,
+
+    /// Command line arguments passed to the first executable.
+    pub args_1: Vec<String>,
+
+    /// Command line arguments passed to the second executable.
+    pub args_2: Vec<String>,
+
+    /// Environment variables set for the first executable, as key and value pairs.
+    pub env_1: Vec<(String, String)>,
+
+    /// Environment variables set for the second executable, as key and value pairs.
+    pub env_2: Vec<(String, String)>,
+
+    /// The working directory both executables run in, the current one if unset.
+    pub cwd: Option<PathBuf>,
+
+    /// The address space limit of both executables in bytes, so that allocating past it fails
+    /// and the executable crashes. Only applied on Unix, ignored elsewhere.
+    pub mem_limit: Option<u64>,
+
+    /// A judge both executables converse with instead of reading the input, for interactive
+    /// problems. See `execute_interactive` for the protocol.
+    pub judge: Option<PathBuf>,
+
+    /// The interpreter running the first executable (e.g `python3`) along with its own
+    /// arguments, which then doesn't need to be executable. Runs it directly when empty.
+    pub interpreter_1: Vec<String>,
+
+    /// The interpreter running the second executable, see `interpreter_1`.
+    pub interpreter_2: Vec<String>,
+
+    /// Characters string variables are made of.
+    pub alphabet: Vec<char>,
+
+    /// The most distinct characters a single string variable may hold, picked from the alphabet
+    /// at random for each string. Unlimited when `None`.
+    pub string_distinct: Option<usize>,
+
+    /// Probability of picking an endpoint of a variable's allowed range instead of a uniform draw.
+    pub edge_bias: f64,
+
+    /// How the executables get their input.
+    pub input_mode: InputMode,
+
+    /// Separator between the records (e.g lines) of an output. When set, outputs are compared
+    /// record by record, see `compare_outputs`.
+    pub output_record_separator: Option<String>,
+
+    /// Separator between the records of an input: the rows of a matrix, the edges of a tree, the
+    /// lines of a wrapped array and the blocks of `blocks`. Items inside a record are separated by
+    /// the input separator.
+    /// `DEFAULT_RECORD_SEPARATOR` when unset.
+    pub input_record_separator: Option<String>,
+
+    /// Whether outputs are compared as multisets of records, ignoring the records' order, see
+    /// `compare_unordered`. Records are lines unless `output_record_separator` is set.
+    pub unordered: bool,
+
+    /// Whether outputs are compared case-insensitively, e.g for problems accepting `YES` as well
+    /// as `yes`. Only the comparison is affected, the outputs are still logged verbatim.
+    pub ignore_case: bool,
+
+    /// Longest input allowed, in bytes. Generating a longer one is an `AppError::InputTooLarge`,
+    /// detected before the oversized variables or input get allocated.
+    pub max_input_bytes: u64,
+
+    /// Whether every expression generates its variables on its own. By default, a variable
+    /// appearing in multiple expressions is generated by the first one and keeps its value in the
+    /// others, which have to fit around it. Otherwise, every expression generates it again and the
+    /// last value wins.
+    pub independent_vars: bool,
+
+    /// Whether an executable printing nothing (or only whitespace) is an `AppError::NoOutput`.
+    /// Otherwise, two empty outputs are equal and an empty output differs from any other.
+    pub require_output: bool,
+
+    /// How many times the variables of a test case are generated again when they break a
+    /// constraint (e.g `A != B`) or a shared variable doesn't fit a later expression, before the
+    /// test is abandoned with an `AppError::ConstraintUnsatisfiable` or an
+    /// `AppError::SharedVariableConflict`.
+    pub max_retries: u64,
+
+    /// Whether arrays may be empty: a length of 0 generates no items (the length itself is still
+    /// part of the input when it is in the input order). Specs declaring such arrays have to be
+    /// parsed with `ParseOptions::allow_empty_arrays`.
+    pub allow_empty_arrays: bool,
+}
+
+impl RunnerOptions {
+    /// How one of the executables is ran.
+    fn exec_settings(&self, which: ExecutableId) -> ExecSettings<'_> {
+        let (interpreter, args, env) = match which {
+            ExecutableId::A => (&self.interpreter_1, &self.args_1, &self.env_1),
+            ExecutableId::B => (&self.interpreter_2, &self.args_2, &self.env_2),
+        };
+        ExecSettings { interpreter, args, env, cwd: self.cwd.as_deref(), mem_limit: self.mem_limit, judge: self.judge.as_deref(), mode: self.input_mode }
+    }
+}
+
+impl Default for RunnerOptions {
+    fn default() -> Self {
+        Self {
+            multi_test: None,
+            blocks: None,
+            seed: 0,
+            args_1: Vec::new(),
+            args_2: Vec::new(),
+            env_1: Vec::new(),
+            env_2: Vec::new(),
+            cwd: None,
+            mem_limit: None,
+            judge: None,
+            interpreter_1: Vec::new(),
+            interpreter_2: Vec::new(),
+            alphabet: ('a'..='z').collect(),
+            string_distinct: None,
+            edge_bias: 0.0,
+            input_mode: InputMode::Stdin,
+            output_record_separator: None,
+            input_record_separator: None,
+            unordered: false,
+            ignore_case: false,
+            max_input_bytes: DEFAULT_MAX_INPUT_BYTES,
+            independent_vars: false,
+            require_output: false,
+            max_retries: DEFAULT_MAX_RETRIES,
+            allow_empty_arrays: false,
+        }
+    }
+}
+
+/// A spec to generate inputs from, see `Generator::with_mix`.
+struct MixedSpec {
+    data: FuzzData,
+    /// Pickers of each expression's first variable group for random generation, built once.
+    first_pickers: Vec<Picker>,
+    /// See `array_wraps`.
+    wraps: HashMap<String, usize>,
+    /// See `shared_bounds`.
+    shared_bounds: HashMap<String, (Value, Value)>,
+}
+
+impl MixedSpec {
+    fn new(data: FuzzData, edge_bias: f64) -> Self {
+        let mode = PickMode::Random(edge_bias);
+        Self {
+            first_pickers: data.exprs.iter().map(|expr| first_picker(expr, mode)).collect(),
+            wraps: array_wraps(&data),
+            shared_bounds: shared_bounds(&data),
+            data,
+        }
+    }
+}
+
+/// Generates inputs from the fuzzing data, without running anything.
+pub struct Generator {
+    /// The specs inputs are generated from. There is more than one when mixing.
+    specs: Vec<MixedSpec>,
+    /// Picks the spec of each random input according to the weights, when mixing.
+    spec_weights: Option<WeightedIndex<u32>>,
+    /// Index of the spec the last input was generated from.
+    current_spec: usize,
+    /// The RNG every value is picked with. Seeded, so a run can be reproduced.
+    rng: StdRng,
+    /// Inclusive range of the amount of test cases per input, see `RunnerOptions::multi_test`.
+    multi_test: Option<(u64, u64)>,
+    /// Inclusive range of the amount of blocks per input without a count, see
+    /// `RunnerOptions::blocks`.
+    block_range: Option<(u64, u64)>,
+    /// Characters string variables are made of.
+    alphabet: Vec<char>,
+    /// See `RunnerOptions::string_distinct`.
+    string_distinct: Option<usize>,
+    /// See `RunnerOptions::edge_bias`.
+    edge_bias: f64,
+    /// See `RunnerOptions::max_input_bytes`.
+    max_input_bytes: u64,
+    /// See `RunnerOptions::input_record_separator`.
+    record_separator: String,
+    /// See `RunnerOptions::independent_vars`.
+    independent_vars: bool,
+    /// See `RunnerOptions::max_retries`.
+    max_retries: u64,
+    /// See `RunnerOptions::allow_empty_arrays`.
+    allow_empty_arrays: bool,
+    /// The variable stores of the last generated input, one for each test case block.
+    blocks: Vec<VarsData>,
+    /// The last generated input.
+    last_input: String,
+}
+
+impl Generator {
+    /// Create a generator. Only the generation related options are used, i.e the test case count
+    /// range, the seed, the alphabet and the edge bias.
+    pub fn new(data: FuzzData, options: &RunnerOptions) -> Self {
+        Self::with_mix(vec![(data, 1)], options)
+    }
+
+    /// Create a generator mixing multiple specs: every random input is generated from a single
+    /// spec, picked at random according to the weights (e.g weights 7 and 3 pick the first spec
+    /// 70% of the time). Sweeps only cover the first spec.
+    ///
+    /// # Panics
+    /// When there are no specs, every weight is 0, or the weights add up past `u32::MAX`.
+    pub fn with_mix(specs: Vec<(FuzzData, u32)>, options: &RunnerOptions) -> Self {
+        assert!(!specs.is_empty(), "At least one spec is given");
+        let spec_weights = (specs.len() > 1).then(|| WeightedIndex::new(specs.iter().map(|(_, weight)| *weight)).expect("At least one spec has a nonzero weight, and the weights fit a u32"));
+        Self {
+            specs: specs.into_iter().map(|(data, _)| MixedSpec::new(data, options.edge_bias)).collect(),
+            spec_weights,
+            current_spec: 0,
+            rng: StdRng::seed_from_u64(options.seed),
+            multi_test: options.multi_test,
+            block_range: options.blocks,
+            alphabet: options.alphabet.clone(),
+            string_distinct: options.string_distinct,
+            edge_bias: options.edge_bias,
+            max_input_bytes: options.max_input_bytes,
+            record_separator: options.input_record_separator.clone().unwrap_or_else(|| DEFAULT_RECORD_SEPARATOR.to_string()),
+            independent_vars: options.independent_vars,
+            max_retries: options.max_retries,
+            allow_empty_arrays: options.allow_empty_arrays,
+            blocks: vec![VarsData::new()],
+            last_input: String::new(),
+        }
+    }
+
+    /// Assign fresh values to every variable and build the input from them. Every test case
+    /// block starts from an empty variable store, so nothing from a previous input can leak into
+    /// this one.
+    ///
+    /// # Returns
+    /// An `AppResult` containing the generated input, an `AppError` otherwise.
+    pub fn generate(&mut self) -> AppResult<&str> {
+        if let Some(weights) = &self.spec_weights {
+            self.current_spec = weights.sample(&mut self.rng);
+        }
+        let block_count = match self.multi_test.or(self.block_range) {
+            Some((min, max)) => Uniform::from(min..=max).sample(&mut self.rng),
+            None => 1
+        };
+        self.fill_blocks(block_count, PickMode::Random(self.edge_bias))
+    }
+
+    /// The sweep cases worth running for the fuzzing data. When every expression has a single
+    /// variable group, the alternating cases are the same as the all-min and all-max ones, so
+    /// only 2 cases are generated. Otherwise (any expression with `k >= 2` groups) all 4 are.
+    pub fn sweep_cases(&self) -> Vec<SweepCase> {
+        if self.specs[0].data.exprs.iter().any(|expr| expr.vars.len() >= 2) {
+            vec![SweepCase::AllMin, SweepCase::AllMax, SweepCase::AlternateMinFirst, SweepCase::AlternateMaxFirst]
+        } else {
+            vec![SweepCase::AllMin, SweepCase::AllMax]
+        }
+    }
+
+    /// Assign the deterministic boundary values of a sweep case to every variable and build the
+    /// input from them. Strings are still random. With multiple test cases (or blocks) per input,
+    /// their count is the minimum or maximum as well, following the first variable group.
+    ///
+    /// # Returns
+    /// An `AppResult` containing the generated input, an `AppError` otherwise.
+    pub fn generate_sweep(&mut self, case: SweepCase) -> AppResult<&str> {
+        self.current_spec = 0;
+        let block_count = match self.multi_test.or(self.block_range) {
+            Some((_, max)) if case.picks_max(0) => max,
+            Some((min, _)) => min,
+            None => 1
+        };
+        self.fill_blocks(block_count, PickMode::Sweep(case))
+    }
+
+    /// How the variables of a spec's expressions get filled, according to the generation options.
+    fn fill_context<'a>(&self, mode: PickMode, spec: &'a MixedSpec) -> FillContext<'a> {
+        let shared = !self.independent_vars;
+        FillContext { mode, max_items: self.max_input_bytes, shared, empty_arrays: self.allow_empty_arrays, bounds: shared.then_some(&spec.shared_bounds) }
+    }
+
+    fn fill_blocks(&mut self, block_count: u64, mode: PickMode) -> AppResult<&str> {
+        let spec = &self.specs[self.current_spec];
+        let ctx = self.fill_context(mode, spec);
+        let MixedSpec { data, first_pickers, .. } = spec;
+        self.blocks.clear();
+        for _ in 0..block_count {
+            let mut retries = 0;
+            let mut block = loop {
+                let mut block = VarsData::new();
+                let mut filled = Ok(());
+                for (expr, first_picker) in data.exprs.iter().zip(first_pickers) {
+                    let first_picker = matches!(mode, PickMode::Random(_)).then_some(first_picker);
+                    filled = recurse_set_variables(&mut self.rng, expr, &mut block, ctx, first_picker);
+                    if filled.is_err() {
+                        break
+                    }
+                }
+                // A shared variable that a later expression can't fit around is retried like a
+                // broken exclusion constraint.
+                let err = match filled {
+                    Err(err @ AppError::SharedVariableConflict(..)) => err,
+                    Err(err) => return Err(err),
+                    Ok(()) => match data.exclusions.iter().find(|exclusion| !exclusion_holds(exclusion, &block)) {
+                        None => break block,
+                        Some(exclusion) => AppError::ConstraintUnsatisfiable(exclusion.line, exclusion.repr.clone())
+                    }
+                };
+                if retries >= self.max_retries {
+                    return Err(err)
+                }
+                retries += 1;
+            };
+            for string in &data.strings {
+                check_items(Some(resolve_len(&string.len, &block, string.line, &string.repr)?), self.max_input_bytes)?;
+            }
+            for tree in &data.trees {
+                check_items(Some(resolve_len(&tree.nodes, &block, tree.line, &tree.repr)?), self.max_input_bytes)?;
+            }
+            for string in &data.strings {
+                fill_string(&mut self.rng, string, &mut block, &self.alphabet, self.string_distinct)?;
+            }
+            for tree in &data.trees {
+                fill_tree(&mut self.rng, tree, &mut block)?;
+            }
+            self.blocks.push(block);
+        }
+        self.render()
+    }
+
+    /// Build the input from the current variable stores. With multiple test cases per input, the
+    /// test case count comes first, followed by every block, all joined by the input separator.
+    /// Blocks without a count (see `RunnerOptions::blocks`) are joined by the record separator.
+    fn render(&mut self) -> AppResult<&str> {
+        let spec = &self.specs[self.current_spec];
+        let data = &spec.data;
+        let sep = &data.input_separator;
+        let block_sep = if self.multi_test.is_some() { sep } else { &self.record_separator };
+        let mut inputs = Vec::new();
+        if self.multi_test.is_some() {
+            inputs.push(self.blocks.len().to_string());
+        }
+        let mut budget = self.max_input_bytes;
+        for block in &self.blocks {
+            let input = build_exec_input(&data.input_order, block, sep, &self.record_separator, &spec.wraps, budget)
+                .map_err(|err| if let AppError::InputTooLarge(_) = err { AppError::InputTooLarge(self.max_input_bytes) } else { err })?;
+            budget = budget.saturating_sub(input.len() as u64 + block_sep.len() as u64);
+            inputs.push(input);
+        }
+
+        self.last_input = inputs.join(block_sep);
+        Ok(&self.last_input)
+    }
+
+    /// Take a given input (e.g one saved earlier) as the last input instead of generating one.
+    /// Nothing is known about its variables, so the variable store is left empty.
+    pub fn set_input(&mut self, input: String) -> &str {
+        self.current_spec = 0;
+        self.blocks = vec![VarsData::new()];
+        self.last_input = input;
+        &self.last_input
+    }
+
+    /// The spec the last input was generated from.
+    fn data(&self) -> &FuzzData {
+        &self.specs[self.current_spec].data
+    }
+
+    /// Index of the spec the last input was generated from, in the order given to `with_mix`.
+    pub fn current_spec(&self) -> usize {
+        self.current_spec
+    }
+
+    /// Get the variable store of the last generated input. With multiple test cases per input,
+    /// this is the last test case's variable store.
+    pub fn get_state(&self) -> &VarsData {
+        self.blocks.last().expect("A generator always holds at least one block")
+    }
+
+    /// Get the variable stores of every test case block of the last generated input.
+    pub fn get_blocks(&self) -> &[VarsData] {
+        &self.blocks
+    }
+
+    pub fn get_last_input(&self) -> &str {
+        &self.last_input
+    }
+}
+
+/// What the first executable's output is compared against.
+pub enum Reference {
+    /// The output of a second executable, ran on the same input.
+    Executable(PathBuf),
+    /// Expected outputs stored in a directory, see `expected_output_path`.
+    ExpectedOutputs(PathBuf),
+    /// The output of the first executable itself, ran a second time on the same input (as
+    /// executable B), to catch nondeterminism like unseeded randomness or undefined behavior.
+    SelfCheck,
+}
+
+impl From<PathBuf> for Reference {
+    fn from(value: PathBuf) -> Self {
+        Self::Executable(value)
+    }
+}
+
+/// The file holding the expected output of a saved input: named after the input's file with an
+/// `out` extension, e.g `00000001.out` for the corpus input `00000001.txt`.
+///
+/// # Arguments
+/// - `dir`: the directory holding the expected outputs
+/// - `input_path`: where the input was read from
+fn expected_output_path(dir: &Path, input_path: &Path) -> PathBuf {
+    dir.join(Path::new(input_path.file_name().unwrap_or_default()).with_extension("out"))
+}
+
+pub struct Runner {
+    generator: Generator,
+    executable_1: PathBuf,
+    reference: Reference,
+    options: RunnerOptions,
+    /// Wall-clock time each executable took on the last input, including spawning it.
+    last_durations: (Duration, Duration),
+    /// Size of the last input and of both executables' outputs on it, in bytes.
+    last_sizes: (usize, usize, usize),
+    /// Where the last input was read from, when it was given instead of generated.
+    last_input_path: Option<PathBuf>,
+}
+
+/// Identifies one of the two executables of a `Runner`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExecutableId {
+    A,
+    B
+}
+
+impl Display for ExecutableId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::A => write!(f, "A"),
+            Self::B => write!(f, "B"),
+        }
+    }
+}
+
+/// Outcome of running a single input through both executables.
+pub enum RunnerResult {
+    /// Both executables gave the same output.
+    Ok,
+    /// The outputs differ. Contains the output of the first and second executable, in that order.
+    Fail(String, String),
+    /// An executable exited with a nonzero status or was killed by a signal. When both did, the
+    /// first executable is reported.
+    Crashed { which: ExecutableId, status: ExitStatus }
+}
+
+impl Runner {
+    pub fn new(data: FuzzData, executable_1: PathBuf, reference: impl Into<Reference>, options: RunnerOptions) -> Self {
+        Self::with_mix(vec![(data, 1)], executable_1, reference, options)
+    }
+
+    /// Create a runner mixing multiple weighted specs, see `Generator::with_mix`.
+    pub fn with_mix(specs: Vec<(FuzzData, u32)>, executable_1: PathBuf, reference: impl Into<Reference>, options: RunnerOptions) -> Self {
+        Self {
+            generator: Generator::with_mix(specs, &options),
+            executable_1,
+            reference: reference.into(),
+            options,
+            last_durations: (Duration::ZERO, Duration::ZERO),
+            last_sizes: (0, 0, 0),
+            last_input_path: None,
+        }
+    }
+
+    pub fn run_once(&mut self) -> AppResult<RunnerResult>{
+        self.generator.generate()?;
+        self.run_current()
+    }
+
+    /// Replace the (first) spec inputs are generated from, keeping everything else.
+    #[cfg(test)]
+    fn replace_spec(&mut self, data: FuzzData) {
+        self.generator.specs[0] = MixedSpec::new(data, self.options.edge_bias);
+    }
+
+    /// Run a deterministic boundary assignment instead of a random one.
+    pub fn run_sweep(&mut self, case: SweepCase) -> AppResult<RunnerResult> {
+        self.generator.generate_sweep(case)?;
+        self.run_current()
+    }
+
+    pub fn sweep_cases(&self) -> Vec<SweepCase> {
+        self.generator.sweep_cases()
+    }
+
+    /// Run a given input (e.g one saved earlier) instead of a generated one. Its variable store
+    /// is empty, see `Generator::set_input`.
+    ///
+    /// # Arguments
+    /// - `path`: where the input was read from, which names its expected output, see
+    ///   `expected_output_path`
+    /// - `input`: the input
+    pub fn run_input(&mut self, path: &Path, input: String) -> AppResult<RunnerResult> {
+        self.generator.set_input(input);
+        self.last_input_path = Some(path.to_path_buf());
+        self.run_last_input()
+    }
+
+    /// Build the input from the current variable stores and run it through both executables.
+    fn run_current(&mut self) -> AppResult<RunnerResult> {
+        self.generator.render()?;
+        self.last_input_path = None;
+        self.run_last_input()
+    }
+
+    /// Run the generator's last input through both executables.
+    fn run_last_input(&mut self) -> AppResult<RunnerResult> {
+        let input = self.generator.get_last_input();
+        let started = Instant::now();
+        let (output_1, status_1) = execute(&self.executable_1, &self.options.exec_settings(ExecutableId::A), input)?;
+        let duration_1 = started.elapsed();
+        let started = Instant::now();
+        let (output_2, status_2) = match &self.reference {
+            Reference::Executable(executable_2) => execute(executable_2, &self.options.exec_settings(ExecutableId::B), input)?,
+            Reference::SelfCheck => execute(&self.executable_1, &self.options.exec_settings(ExecutableId::A), input)?,
+            Reference::ExpectedOutputs(dir) => {
+                let Some(input_path) = &self.last_input_path else {
+                    return Err(AppError::InvalidArgument("Expected outputs are named after saved inputs, so generated inputs have none".into()))
+                };
+                let path = expected_output_path(dir, input_path);
+                let expected = read_to_string(&path).map_err(|err| match err.kind() {
+                    std::io::ErrorKind::NotFound => AppError::NoExpectedOutput(path),
+                    kind => AppError::IOError(kind)
+                })?;
+                (expected, ExitStatus::default())
+            }
+        };
+        self.last_durations = (duration_1, started.elapsed());
+        self.last_sizes = (input.len(), output_1.len(), output_2.len());
+
+        if !status_1.success() {
+            return Ok(RunnerResult::Crashed { which: ExecutableId::A, status: status_1 })
+        }
+        if !status_2.success() {
+            return Ok(RunnerResult::Crashed { which: ExecutableId::B, status: status_2 })
+        }
+
+        if self.options.require_output {
+            if output_1.trim().is_empty() {
+                return Err(AppError::NoOutput(self.executable_1.clone()))
+            }
+            // Stored expected outputs aren't printed by anything, so they may well be empty. A self
+            // check's second run is already covered by the first one.
+            if let (Reference::Executable(executable_2), true) = (&self.reference, output_2.trim().is_empty()) {
+                return Err(AppError::NoOutput(executable_2.clone()))
+            }
+        }
+
+        if self.outputs_equal(&output_1, &output_2) {
+            Ok(RunnerResult::Ok)
+        } else {
+            Ok(RunnerResult::Fail(output_1, output_2))
+        }
+    }
+
+    /// Get the variable store of the last input, see `Generator::get_state`. With multiple test
+    /// cases per input, this is the last test case's variable store, see `get_blocks` for all of
+    /// them.
+    pub fn get_state(&self) -> &VarsData {
+        self.generator.get_state()
+    }
+
+    pub fn get_blocks(&self) -> &[VarsData] {
+        self.generator.get_blocks()
+    }
+
+    pub fn get_last_input(&self) -> &str {
+        self.generator.get_last_input()
+    }
+
+    /// Describe where two outputs of this runner's executables first differ, see
+    /// `describe_mismatch`.
+    pub fn describe_mismatch(&self, output_1: &str, output_2: &str) -> String {
+        let token_sep = &self.generator.data().output_separator;
+        let record_sep = self.options.output_record_separator.as_deref();
+        let (output_1, output_2) = (&self.fold_case(output_1), &self.fold_case(output_2));
+        if self.options.unordered {
+            describe_unordered_mismatch(token_sep, record_sep.unwrap_or("\n"), output_1, output_2)
+        } else {
+            describe_mismatch(token_sep, record_sep, output_1, output_2)
+        }
+    }
+
+    /// Compare two outputs of this runner's executables, see `compare_outputs` and
+    /// `compare_unordered`.
+    fn outputs_equal(&self, output_1: &str, output_2: &str) -> bool {
+        let token_sep = &self.generator.data().output_separator;
+        let record_sep = self.options.output_record_separator.as_deref();
+        let (output_1, output_2) = (&self.fold_case(output_1), &self.fold_case(output_2));
+        if self.options.unordered {
+            compare_unordered(token_sep, record_sep.unwrap_or("\n"), output_1, output_2)
+        } else {
+            compare_outputs(token_sep, record_sep, output_1, output_2)
+        }
+    }
+
+    /// Lowercase an output before comparing it when ignoring case, see `ignore_case`.
+    fn fold_case<'a>(&self, output: &'a str) -> Cow<'a, str> {
+        if self.options.ignore_case {
+            Cow::Owned(output.to_lowercase())
+        } else {
+            Cow::Borrowed(output)
+        }
+    }
+
+    /// Wall-clock time the first and second executable took on the last input.
+    pub fn get_last_durations(&self) -> (Duration, Duration) {
+        self.last_durations
+    }
+
+    /// Size of the last input and of the first and second executable's outputs on it, in bytes.
+    pub fn get_last_sizes(&self) -> (usize, usize, usize) {
+        self.last_sizes
+    }
+
+    /// Index of the spec the last input was generated from, see `Generator::current_spec`.
+    pub fn current_spec(&self) -> usize {
+        self.generator.current_spec()
+    }
+
+    /// Run a known input through the second executable and compare its output against the known
+    /// expected output, the same way outputs are compared while fuzzing. Stored expected outputs
+    /// aren't ran, so they always agree.
+    ///
+    /// # Arguments
+    /// - `input`: the input
+    /// - `expected`: the known expected output
+    ///
+    /// # Returns
+    /// An `AppResult` containing `None` when the outputs agree, or a description of how they
+    /// don't (or of the crash).
+    pub fn check_reference(&self, input: &str, expected: &str) -> AppResult<Option<String>> {
+        let Reference::Executable(executable_2) = &self.reference else {
+            return Ok(None)
+        };
+        let (output, status) = execute(executable_2, &self.options.exec_settings(ExecutableId::B), input)?;
+        if !status.success() {
+            return Ok(Some(format!("exited with {}", status)))
+        }
+        if self.outputs_equal(&output, expected) {
+            Ok(None)
+        } else {
+            Ok(Some(self.describe_mismatch(&output, expected)))
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::{AppResultData, ExecTimings, OutputFormat}, parser::{parser::{parse_expr_from_line, ParseOptions}, tokenizer::tokenize_expr_line}};
+
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn fill_variables_1() {
+        let expr_str = "1 < A < B <= 100";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
         let mut data = VarsData::new();
 
         // Amount of possible values for A and B is 99C2 = 4851. The amount of times we need to
         // draw the values to have at least each possibility once is the harmonic sum up to H4851
         // multiplied by 4851. That's 43971.
         for _ in 0..43971 {
-            recurse_set_variables(&mut thread_rng(), &expr, &mut data).unwrap();
+            recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap();
             assert!(*data.get_var("B").unwrap() <= 100);
             assert!(*data.get_var("B").unwrap() > 2);
             assert!(*data.get_var("A").unwrap() < 100);
@@ -269,37 +1856,1121 @@ mod tests {
         // draw the values to have at least each possibility once is the harmonic sum up to H98
         // multiplied by 98. That's 507.
         for _ in 0..507 {
-            recurse_set_variables(&mut thread_rng(), &expr, &mut data).unwrap();
+            recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap();
             data.get_arr("A").unwrap().iter().for_each(|item| assert!(*item <= 100));
         }
     }
 
     #[test]
-    fn test_build_vars_from_template() {
-        let template: Vec<String> = vec!["A".into(), "B".into()];
+    fn fill_variables_negative() {
+        let expr_str = "-10^9 <= A < B <= -1";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
         let mut data = VarsData::new();
-        data.set_var("A", 100);
-        data.set_var("B", 200);
 
-        let built = build_exec_input(&template, &data, " ").unwrap();
-        assert_eq!(built, "100 200".to_string())
+        for _ in 0..1000 {
+            recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap();
+            let a = *data.get_var("A").unwrap();
+            let b = *data.get_var("B").unwrap();
+            assert!(-1_000_000_000 <= a && a < b && b <= -1);
+        }
     }
 
     #[test]
-    fn test_build_var_arrays_from_template() {
-        let template: Vec<String> = vec!["A".into(), "B".into()];
+    fn fill_variables_edge_bias() {
+        let expr_str = "1 <= A < B <= 100";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let expr_str = "5 <= C[20]# <= 10";
+        let arr_expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
         let mut data = VarsData::new();
-        data.set_arr("A", vec![10, 20, 30]);
-        data.set_arr("B", vec![40, 50, 60]);
 
-        let built = build_exec_input(&template, &data, " ").unwrap();
-        assert_eq!(built, "10 20 30 40 50 60".to_string())
+        for _ in 0..1000 {
+            recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(1.0), None).unwrap();
+            recurse_set_variables(&mut thread_rng(), &arr_expr, &mut data, PickMode::Random(1.0), None).unwrap();
+            let a = *data.get_var("A").unwrap();
+            let b = *data.get_var("B").unwrap();
+            assert!(a == 1 || a == 99);
+            assert!(b == a + 1 || b == 100);
+            assert!(data.get_arr("C").unwrap().iter().all(|item| *item == 5 || *item == 10));
+        }
     }
 
     #[test]
-    fn test_execute() {
-        let filepath = Path::new("examples/example");
-        let result = execute(filepath, "1\n2\n");
-        assert!(split_and_compare("\n", &result.unwrap(), "3"))
+    fn test_generator_sweep() {
+        let lines: Vec<String> = vec!["1 <= N < M <= 10".into(), "0 <= A[N]#! <= 100".into(), "input order: N M A".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+
+        assert_eq!(generator.sweep_cases().len(), 4);
+        assert_eq!(generator.generate_sweep(SweepCase::AllMin).unwrap(), "1 2 0");
+        assert_eq!(generator.generate_sweep(SweepCase::AllMax).unwrap(), "9 10 100 99 98 97 96 95 94 93 92");
+        assert_eq!(generator.generate_sweep(SweepCase::AlternateMinFirst).unwrap(), "1 10 0");
+        assert_eq!(generator.generate_sweep(SweepCase::AlternateMaxFirst).unwrap(), "9 10 100 99 98 97 96 95 94 93 92");
+    }
+
+    #[test]
+    fn fill_variables_log() {
+        let expr_str = "1 <= N <= 10^9";
+        let uniform_expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let expr_str = "1 <= N@log <= 10^9";
+        let log_expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+
+        let mut small_uniform = 0;
+        let mut small_log = 0;
+        for _ in 0..1000 {
+            recurse_set_variables(&mut thread_rng(), &uniform_expr, &mut data, PickMode::Random(0.0), None).unwrap();
+            small_uniform += (*data.get_var("N").unwrap() <= 1000) as u32;
+            recurse_set_variables(&mut thread_rng(), &log_expr, &mut data, PickMode::Random(0.0), None).unwrap();
+            let val = *data.get_var("N").unwrap();
+            assert!((1..=1000000000).contains(&val));
+            small_log += (val <= 1000) as u32;
+        }
+
+        // About a third of the log-uniform values have at most 3 digits, against almost none of
+        // the uniform ones.
+        assert!(small_log > 200, "only {} small values", small_log);
+        assert!(small_uniform < 10, "{} small values", small_uniform);
+    }
+
+    #[test]
+    fn fill_array_exact_length() {
+        let expr_str = "1 <= A[10]# <= 100";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+
+        fill_array(&mut thread_rng(), &expr, &mut data, "A", &LenExpr::Constant(10), VarFlags::default(), &Picker::new(1..=100, PickMode::Random(0.0), 0), false).unwrap();
+        assert_eq!(data.get_arr("A").unwrap().len(), 10);
+
+        data.set_var("N", 7);
+        fill_array(&mut thread_rng(), &expr, &mut data, "B", &LenExpr::Variable("N".into()), VarFlags::default(), &Picker::new(1..=100, PickMode::Random(0.0), 0), false).unwrap();
+        assert_eq!(data.get_arr("B").unwrap().len(), 7);
+    }
+
+    #[test]
+    fn fill_offset_array() {
+        let expr_str = "1 <= A[N-1]#,B[N+2]# <= 100";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+        data.set_var("N", 5);
+
+        recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap();
+        assert_eq!(data.get_arr("A").unwrap().len(), 4);
+        assert_eq!(data.get_arr("B").unwrap().len(), 7);
+
+        data.set_var("N", 1);
+        let result = recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap_err();
+        assert_eq!(result, AppError::InvalidArraySize(0, 0, expr_str.into()));
+    }
+
+    #[test]
+    fn fill_array_unresolved_length() {
+        let expr_str = "1 <= A[N]# <= 100";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+
+        let result = recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap_err();
+        assert_eq!(result, AppError::UnresolvedArrayLength(0, expr_str.into()));
+    }
+
+    #[test]
+    fn fill_distinct_array() {
+        let expr_str = "1 <= A[10]#! <= 10";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+
+        // The range holds exactly as many values as the array length, so it must be a permutation.
+        for _ in 0..100 {
+            recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap();
+            let mut arr = data.get_arr("A").unwrap().clone();
+            arr.sort();
+            assert_eq!(arr, (1..=10).collect::<Vec<Value>>());
+        }
+    }
+
+    #[test]
+    fn fill_distinct_array_range_too_small() {
+        let expr_str = "1 <= A[11]#! <= 10";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+
+        let result = recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap_err();
+        assert_eq!(result, AppError::RangeTooSmallForDistinct(11, 0, expr_str.into()));
+    }
+
+    #[test]
+    fn test_error_reports_line() {
+        let lines: Vec<String> = vec!["5 <= N <= 5".into(), "# Too many distinct values".into(), "1 <= A[N]#! <= 3".into(), "input order: N A".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        let result = generator.generate().unwrap_err();
+        assert_eq!(result, AppError::RangeTooSmallForDistinct(5, 3, "1 <= A[N]#! <= 3".into()));
+        assert_eq!(result.to_string(), "Range too small for 5 distinct values at line 3: `1 <= A[N]#! <= 3`");
+    }
+
+    #[test]
+    fn fill_distinct_variables() {
+        let expr_str = "1 <= A!,B!,C! <= 3";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+
+        for _ in 0..100 {
+            recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap();
+            let mut vals = vec![*data.get_var("A").unwrap(), *data.get_var("B").unwrap(), *data.get_var("C").unwrap()];
+            vals.sort();
+            assert_eq!(vals, vec![1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn fill_sorted_array() {
+        let expr_str = "1 <= N <= 100";
+        let len_expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let expr_str = "1 <= A[N]#+ <= 10";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let expr_str = "1 <= B[N]#++ <= 1000";
+        let strict_expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+
+        for _ in 0..100 {
+            recurse_set_variables(&mut thread_rng(), &len_expr, &mut data, PickMode::Random(0.0), None).unwrap();
+            recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap();
+            recurse_set_variables(&mut thread_rng(), &strict_expr, &mut data, PickMode::Random(0.0), None).unwrap();
+            assert!(data.get_arr("A").unwrap().windows(2).all(|pair| pair[0] <= pair[1]));
+            assert!(data.get_arr("B").unwrap().windows(2).all(|pair| pair[0] < pair[1]));
+
+            let expected: Vec<String> = data.get_arr("A").unwrap().iter().map(ToString::to_string).collect();
+            let built = build_exec_input(&["A".into()], &data, " ", "\n", &HashMap::new(), u64::MAX).unwrap();
+            assert_eq!(built, expected.join(" "));
+        }
+    }
+
+    #[test]
+    fn fill_sum_capped_array() {
+        let expr_str = "1 <= N <= 100";
+        let len_expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let expr_str = "-5 <= A[N]#sum(300) <= 1000";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+
+        for _ in 0..1000 {
+            recurse_set_variables(&mut thread_rng(), &len_expr, &mut data, PickMode::Random(0.5), None).unwrap();
+            recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.5), None).unwrap();
+            let arr = data.get_arr("A").unwrap();
+            assert!(arr.iter().sum::<Value>() <= 300);
+            assert!(arr.iter().all(|val| (-5..=1000).contains(val)));
+        }
+    }
+
+    #[test]
+    fn fill_sum_capped_array_cap_too_small() {
+        let expr_str = "2 <= A[10]#sum(19) <= 10";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+
+        let result = recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap_err();
+        assert_eq!(result, AppError::SumCapTooSmall(20, 0, expr_str.into()));
+    }
+
+    #[test]
+    fn fill_matrix_dimensions() {
+        let expr_str = "1 <= A[3][4]#,B[N][M-1]# <= 100";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+        data.set_var("N", 2);
+        data.set_var("M", 6);
+
+        recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap();
+        let a = data.get_matrix("A").unwrap();
+        assert_eq!(a.len(), 3);
+        assert!(a.iter().all(|row| row.len() == 4));
+        assert!(a.iter().flatten().all(|item| (1..=100).contains(item)));
+        let b = data.get_matrix("B").unwrap();
+        assert_eq!(b.len(), 2);
+        assert!(b.iter().all(|row| row.len() == 5));
+
+        data.set_var("M", 1);
+        let result = recurse_set_variables(&mut thread_rng(), &expr, &mut data, PickMode::Random(0.0), None).unwrap_err();
+        assert_eq!(result, AppError::InvalidArraySize(0, 0, expr_str.into()));
+    }
+
+    #[test]
+    fn test_build_vars_from_template() {
+        let template: Vec<String> = vec!["A".into(), "B".into()];
+        let mut data = VarsData::new();
+        data.set_var("A", 100);
+        data.set_var("B", 200);
+
+        let built = build_exec_input(&template, &data, " ", "\n", &HashMap::new(), u64::MAX).unwrap();
+        assert_eq!(built, "100 200".to_string())
+    }
+
+    #[test]
+    fn test_build_empty_template() {
+        let built = build_exec_input(&[], &VarsData::new(), " ", "\n", &HashMap::new(), u64::MAX).unwrap();
+        assert_eq!(built, "");
+    }
+
+    #[test]
+    fn test_build_var_arrays_from_template() {
+        let template: Vec<String> = vec!["A".into(), "B".into()];
+        let mut data = VarsData::new();
+        data.set_arr("A", vec![10, 20, 30]);
+        data.set_arr("B", vec![40, 50, 60]);
+
+        let built = build_exec_input(&template, &data, " ", "\n", &HashMap::new(), u64::MAX).unwrap();
+        assert_eq!(built, "10 20 30 40 50 60".to_string())
+    }
+
+    #[test]
+    fn test_build_matrix_from_template() {
+        let template: Vec<String> = vec!["N".into(), "A".into()];
+        let mut data = VarsData::new();
+        data.set_var("N", 2);
+        data.set_matrix("A", vec![vec![1, 2, 3], vec![4, 5, 6]]);
+
+        let built = build_exec_input(&template, &data, " ", "\n", &HashMap::new(), u64::MAX).unwrap();
+        assert_eq!(built, "2 1 2 3\n4 5 6".to_string());
+
+        // Matrix rows and tree edges are both records.
+        data.set_tree("G", vec![(1, 2), (1, 3)]);
+        let template: Vec<String> = vec!["A".into(), "G".into()];
+        let built = build_exec_input(&template, &data, " ", ";\n", &HashMap::new(), u64::MAX).unwrap();
+        assert_eq!(built, "1 2 3;\n4 5 6 1 2;\n1 3".to_string())
+    }
+
+    #[test]
+    fn test_build_wrapped_array() {
+        let lines: Vec<String> = vec!["1 <= A[7]#wrap(3) <= 9".into(), "1 <= B[2]# <= 9".into(), "input order: A B".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let wraps = array_wraps(&data);
+        assert_eq!(wraps, HashMap::from([("A".to_string(), 3)]));
+
+        let mut vars = VarsData::new();
+        vars.set_arr("A", vec![1, 2, 3, 4, 5, 6, 7]);
+        vars.set_arr("B", vec![8, 9]);
+        let template: Vec<String> = vec!["A".into(), "B".into()];
+        assert_eq!(build_exec_input(&template, &vars, " ", "\n", &wraps, u64::MAX).unwrap(), "1 2 3\n4 5 6\n7 8 9");
+
+        vars.set_arr("A", vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(build_exec_input(&template, &vars, " ", "\n", &wraps, u64::MAX).unwrap(), "1 2 3\n4 5 6 8 9");
+
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        let input = generator.generate().unwrap().to_string();
+        let lines: Vec<&str> = input.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[..2].iter().all(|line| line.split(' ').count() == 3));
+    }
+
+    #[test]
+    fn test_build_length_first() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "1 <= A[N]# <= 100".into(), "S{N+2}".into(), "input order: A@len S@len".into()];
+        let data = FuzzData::parse("\n".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        for _ in 0..20 {
+            let input = generator.generate().unwrap().to_string();
+            let state = generator.get_state();
+            let n = *state.get_var("N").unwrap();
+            let items: Vec<String> = state.get_arr("A").unwrap().iter().map(Value::to_string).collect();
+            assert_eq!(input, format!("{}\n{}\n{}\n{}", n, items.join("\n"), n + 2, state.get_str("S").unwrap()));
+            assert_eq!(exec_input_len(&generator.data().input_order, state, "\n", "\n", &HashMap::new()), input.len() as u64);
+        }
+
+        for (order, err) in [("N@len", AppError::NoLength("N".into())), ("B@len", AppError::UndeclaredVariable("B".into()))] {
+            let lines: Vec<String> = vec!["1 <= N <= 10".into(), "1 <= A[N]# <= 100".into(), format!("input order: {} A", order)];
+            assert_eq!(FuzzData::parse(" ".into(), " ".into(), lines).unwrap_err(), err);
+        }
+    }
+
+    #[test]
+    fn test_exec_input_len() {
+        let lines: Vec<String> = vec!["-100 <= X <= 100".into(), "1 <= N,M <= 10".into(), "-5 <= A[3]# <= 10^9".into(), "1 <= B[N][M]# <= 10".into(), "1 <= C[N]#wrap(3) <= 100".into(), "S{2}".into(), "tree G[3]#".into(), "input order: X N A S G C".into()];
+        let data = FuzzData::parse(", ".into(), " ".into(), lines).unwrap();
+        let wraps = array_wraps(&data);
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        for _ in 0..100 {
+            generator.generate().unwrap();
+            let input = build_exec_input(&generator.data().input_order, generator.get_state(), ", ", ";\r\n", &wraps, u64::MAX).unwrap();
+            assert_eq!(exec_input_len(&generator.data().input_order, generator.get_state(), ", ", ";\r\n", &wraps), input.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_generate_choices() {
+        let lines: Vec<String> = vec!["K in {1, 2, 4, 8}".into(), "0 <= A[K]# <= 9".into(), "input order: K A".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions { edge_bias: 0.5, ..Default::default() });
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..200 {
+            generator.generate().unwrap();
+            let k = *generator.get_blocks()[0].get_var("K").unwrap();
+            assert!([1, 2, 4, 8].contains(&k), "{}", k);
+            assert_eq!(generator.get_blocks()[0].get_arr("A").unwrap().len() as Value, k);
+            seen.insert(k);
+        }
+        assert_eq!(seen.len(), 4);
+
+        // Sweeping picks the listed extremes.
+        let sweeps: Vec<Value> = generator.sweep_cases().into_iter().map(|case| {
+            generator.generate_sweep(case).unwrap();
+            *generator.get_blocks()[0].get_var("K").unwrap()
+        }).collect();
+        assert!(sweeps.contains(&1) && sweeps.contains(&8), "{:?}", sweeps);
+    }
+
+    #[test]
+    fn test_generate_bigint() {
+        let lines: Vec<String> = vec![
+            "10^18 <= N <= 10^19".into(),
+            "-10000000000000000004 <= A[5]#! <= -10^19".into(),
+            "0 <= L@log <= 10^30".into(),
+            "input order: N A L".into()
+        ];
+        assert!(matches!(FuzzData::parse(" ".into(), " ".into(), lines.clone()).unwrap_err(), AppError::ValueTooLarge(1, _)));
+
+        let options = ParseOptions { bigint: true, ..Default::default() };
+        let data = FuzzData::parse_with(" ".into(), " ".into(), lines, options).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        for _ in 0..100 {
+            let input = generator.generate().unwrap().to_string();
+            let state = &generator.get_blocks()[0];
+            let n = *state.get_var("N").unwrap();
+            assert!((10_i128.pow(18)..=10_i128.pow(19)).contains(&n));
+            assert!(input.starts_with(&n.to_string()));
+            assert!(state.get_arr("A").unwrap().iter().all(|item| (-10_i128.pow(19) - 4..=-10_i128.pow(19)).contains(item)));
+            assert!((0..=10_i128.pow(30)).contains(state.get_var("L").unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_allow_empty_arrays() {
+        let lines: Vec<String> = vec!["0 <= N <= 0".into(), "1 <= A[N]# <= 10".into(), "input order: N A".into()];
+        let options = ParseOptions { allow_empty_arrays: true, ..Default::default() };
+        let data = FuzzData::parse_with("\n".into(), " ".into(), lines, options).unwrap();
+
+        // Rejected unless the runner allows it too.
+        let mut generator = Generator::new(data.clone(), &RunnerOptions::default());
+        assert!(matches!(generator.generate().unwrap_err(), AppError::InvalidArraySize(0, ..)));
+        let mut generator = Generator::new(data, &RunnerOptions { allow_empty_arrays: true, ..Default::default() });
+        assert_eq!(generator.generate().unwrap(), "0\n");
+        assert_eq!(generator.get_blocks()[0].get_arr("A").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_max_input_bytes() {
+        let lines: Vec<String> = vec!["1 <= A[1000000000]# <= 10^9".into(), "input order: A".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        assert_eq!(generator.generate().unwrap_err(), AppError::InputTooLarge(DEFAULT_MAX_INPUT_BYTES));
+
+        // Every item fits, but the input as a whole doesn't.
+        let lines: Vec<String> = vec!["10 <= A[10]# <= 99".into(), "input order: A".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines.clone()).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions { max_input_bytes: 28, ..Default::default() });
+        assert_eq!(generator.generate().unwrap_err(), AppError::InputTooLarge(28));
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions { max_input_bytes: 29, ..Default::default() });
+        assert_eq!(generator.generate().unwrap().len(), 29);
+    }
+
+    #[test]
+    fn test_execute() {
+        let filepath = Path::new("examples/example");
+        let (result, status) = execute(filepath, &ExecSettings::default(), "1\n2\n").unwrap();
+        assert!(status.success());
+        assert!(split_and_compare("\n", &result, "3"))
+    }
+
+    #[test]
+    fn test_compare_outputs_records() {
+        assert!(compare_outputs(" ", Some("\n"), "1 2\n3\n", "1 2\n3"));
+        assert!(compare_outputs(" ", Some("\n"), " 1 2 \n 3", "1 2\n3"));
+        // Only the line structure differs.
+        assert!(!compare_outputs(" ", Some("\n"), "1 2\n3", "1\n2 3"));
+        assert!(!compare_outputs(" ", Some("\n"), "1 2\n3", "1 2 3"));
+        assert!(!compare_outputs(" ", Some("\n"), "1 2\n3", "1 2\n3\n4"));
+        assert!(compare_outputs("\n", None, "1\n2\n", "1\n2"));
+    }
+
+    #[test]
+    fn test_compare_unordered() {
+        let (output_1, output_2) = ("1 2\n3\n4 5\n", "4 5\n1 2\n3");
+        assert!(compare_unordered(" ", "\n", output_1, output_2));
+        assert!(!compare_outputs(" ", Some("\n"), output_1, output_2));
+        assert!(!compare_outputs(" ", None, output_1, output_2));
+
+        assert!(compare_unordered(" ", "\n", " 3 \n1 2", "1 2\n3"));
+        assert!(!compare_unordered(" ", "\n", "1 2\n3", "1\n2 3"));
+        assert!(!compare_unordered(" ", "\n", "1\n1\n2", "1\n2\n2"));
+
+        assert_eq!(describe_unordered_mismatch(" ", "\n", "1\n1\n2", "2\n1\n2"), "A has the line `1` more times than B");
+        assert_eq!(describe_unordered_mismatch(" ", "\n", "3\n1 2", "1 2\n4"), "A has the line `3` more times than B");
+        assert_eq!(describe_unordered_mismatch(" ", "\n", "5\n1 2", "1 2\n4"), "B has the line `4` more times than A");
+        assert_eq!(describe_unordered_mismatch(" ", "\n", "1\n2", "1"), "A has 2 lines, B has 1");
+        assert_eq!(describe_unordered_mismatch(" ", ";", "1;2", "2;1"), "no mismatch");
+    }
+
+    #[test]
+    fn test_describe_mismatch() {
+        assert_eq!(describe_mismatch(" ", Some("\n"), "1 2\n3 7\n", "1 2\n3 8"), "first mismatch at line 2 token 2: A has `7`, B has `8`");
+        assert_eq!(describe_mismatch(" ", Some("\n"), "1 2\n3", "1 2\n3 4"), "first mismatch at line 2 token 2: A has nothing, B has `4`");
+        assert_eq!(describe_mismatch(" ", Some("\n"), "1\n2", "1"), "A has 2 lines, B has 1");
+        assert_eq!(describe_mismatch(" ", None, "1 2 3", "1 5"), "first mismatch at token 2: A has `2`, B has `5`");
+        assert_eq!(describe_mismatch(" ", None, "1 2", "1 2"), "no mismatch");
+        assert_eq!(describe_mismatch(" ", None, "1\n2", "1"), "first mismatch at token 1: A has `1\\n2`, B has `1`");
+        assert_eq!(describe_mismatch(" ", None, &"9".repeat(50), "1"), format!("first mismatch at token 1: A has `{}...`, B has `1`", "9".repeat(40)));
+    }
+
+    #[test]
+    fn test_execute_large_input() {
+        // Far bigger than a pipe buffer (usually 64KB), so the child has to be read from while
+        // it's still being written to.
+        let input = "1234567\n".repeat(200_000);
+        let (result, _) = execute(Path::new("/bin/cat"), &ExecSettings::default(), &input).unwrap();
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_execute_partial_read() {
+        // head exits after the first line, long before the rest of the input is written.
+        let input = format!("first\n{}", "1234567\n".repeat(200_000));
+        let settings = ExecSettings { args: &["-n1".into()], ..Default::default() };
+        let (result, status) = execute(Path::new("/usr/bin/head"), &settings, &input).unwrap();
+        assert!(status.success());
+        assert_eq!(result, "first\n");
+    }
+
+    #[test]
+    fn test_execute_invalid_output() {
+        // `printf` interprets the octal escape, which isn't valid UTF-8 on its own.
+        let result = execute(Path::new("/usr/bin/printf"), &ExecSettings { args: &["\\377".into()], ..Default::default() }, "").unwrap_err();
+        assert_eq!(result, AppError::OutputNotCaptured(PathBuf::from("/usr/bin/printf"), std::io::ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn test_execute_input_file() {
+        // cat prints the file named by its last argument, which is the input file.
+        let (result, status) = execute(Path::new("/bin/cat"), &ExecSettings { mode: InputMode::File, ..Default::default() }, "1 2\n3\n").unwrap();
+        assert!(status.success());
+        assert_eq!(result, "1 2\n3\n");
+
+        // The file path comes after the other arguments, and the input is not piped into stdin.
+        let (result, _) = execute(Path::new("/bin/cat"), &ExecSettings { args: &["-".into()], mode: InputMode::File, ..Default::default() }, "hello").unwrap();
+        assert_eq!(result, "hello");
+    }
+
+    #[test]
+    fn test_execute_with_args() {
+        let (result, _) = execute(Path::new("/bin/echo"), &ExecSettings { args: &["hello".into(), "big world".into()], ..Default::default() }, "").unwrap();
+        assert_eq!(result, "hello big world\n");
+    }
+
+    #[test]
+    fn test_execute_cwd() {
+        let cwd = temp_dir().canonicalize().unwrap();
+        let args = ["-c".to_string(), "cat > /dev/null; pwd".to_string()];
+        let settings = ExecSettings { args: &args, cwd: Some(&cwd), ..Default::default() };
+        let (result, status) = execute(Path::new("/bin/sh"), &settings, "1\n").unwrap();
+        assert!(status.success());
+        assert_eq!(result, format!("{}\n", cwd.display()));
+
+        // Relative executable paths stay relative to our own working directory.
+        let settings = ExecSettings { cwd: Some(&cwd), ..Default::default() };
+        let (result, _) = execute(Path::new("examples/example"), &settings, "1\n2\n").unwrap();
+        assert!(split_and_compare("\n", &result, "3"))
+    }
+
+    #[test]
+    fn test_execute_interpreter() {
+        // Not executable, so it can only run through the interpreter.
+        let script = temp_dir().join(format!("beanfuzz-script-{}.sh", process::id()));
+        write(&script, "read n; echo $((n * 2)) \"$1\"").unwrap();
+        let (interpreter, args) = (["sh".to_string(), "-e".to_string()], ["arg".to_string()]);
+        let settings = ExecSettings { interpreter: &interpreter, args: &args, ..Default::default() };
+        let (result, status) = execute(&script, &settings, "21\n").unwrap();
+        remove_file(&script).unwrap();
+        assert!(status.success());
+        assert_eq!(result, "42 arg\n");
+
+        // A relative path handed to the interpreter stays relative to our own working directory.
+        let (interpreter, cwd) = (["cat".to_string()], temp_dir());
+        let settings = ExecSettings { interpreter: &interpreter, cwd: Some(&cwd), ..Default::default() };
+        let (result, _) = execute(Path::new("examples/sample.fuzz"), &settings, "").unwrap();
+        assert_eq!(result, read_to_string("examples/sample.fuzz").unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_runner_mem_limit() {
+        // Doubles a string until allocating fails.
+        let args = vec!["-c".to_string(), "cat > /dev/null; awk 'BEGIN { s = \"x\"; while (1) s = s s }'".to_string()];
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "input order: N".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let options = RunnerOptions { args_1: args, mem_limit: Some(64 * 1024 * 1024), ..Default::default() };
+        let mut runner = Runner::new(data, PathBuf::from("/bin/sh"), PathBuf::from("/bin/cat"), options);
+        assert!(matches!(runner.run_once(), Ok(RunnerResult::Crashed { which: ExecutableId::A, .. })));
+    }
+
+    #[test]
+    fn test_runner_interactive() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "1 <= A[N]# <= 100".into(), "input order: N A".into()];
+        let run = |executable_2: &str, args_2: &[&str]| {
+            let data = FuzzData::parse(" ".into(), " ".into(), lines.clone()).unwrap();
+            let options = RunnerOptions {
+                args_2: args_2.iter().map(|arg| arg.to_string()).collect(),
+                judge: Some(PathBuf::from("examples/judge.sh")),
+                ..Default::default()
+            };
+            let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from(executable_2), options);
+            let result = runner.run_once().unwrap();
+            (result, runner.get_last_input().to_string())
+        };
+
+        let echo = ["-c", "while read -r n; do echo \"$n\"; done"];
+        let (result, _) = run("/bin/sh", &echo);
+        assert!(matches!(result, RunnerResult::Ok));
+
+        // The judge rejects the first reply.
+        let (result, _) = run("/bin/sh", &["-c", "while read -r n; do echo $((n + 1)); done"]);
+        assert!(matches!(result, RunnerResult::Crashed { which: ExecutableId::B, .. }));
+
+        // Both are accepted, but the conversations differ.
+        let (result, input) = run("/bin/sh", &["-c", "while read -r n; do echo \"$n\"; done; echo bye"]);
+        let RunnerResult::Fail(output_1, output_2) = result else {
+            panic!("Expected the conversations to differ")
+        };
+        let expected: String = input.split(' ').map(|item| format!("{}\n", item)).collect();
+        assert_eq!(output_1, expected);
+        assert_eq!(output_2, format!("{}bye\n", expected));
+    }
+
+    #[test]
+    fn test_runner_env() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "input order: N".into()];
+        let data = FuzzData::parse("\n".into(), "\n".into(), lines).unwrap();
+        let options = RunnerOptions {
+            args_1: vec!["-c".into(), "cat > /dev/null; echo $BEANFUZZ_TEST".into()],
+            args_2: vec!["-c".into(), "cat > /dev/null; echo $BEANFUZZ_TEST".into()],
+            env_1: vec![("BEANFUZZ_TEST".into(), "a".into())],
+            env_2: vec![("BEANFUZZ_TEST".into(), "b".into())],
+            ..Default::default()
+        };
+        let mut runner = Runner::new(data, PathBuf::from("/bin/sh"), PathBuf::from("/bin/sh"), options);
+
+        let RunnerResult::Fail(output_1, output_2) = runner.run_once().unwrap() else {
+            panic!("Expected the outputs to differ")
+        };
+        assert_eq!((output_1.as_str(), output_2.as_str()), ("a\n", "b\n"));
+    }
+
+    #[test]
+    fn test_runner_ignore_case() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "input order: N".into()];
+        let runner = |ignore_case, unordered| {
+            let data = FuzzData::parse("\n".into(), " ".into(), lines.clone()).unwrap();
+            let options = RunnerOptions {
+                args_1: vec!["-c".into(), "cat > /dev/null; printf 'YES 1\\nNo'".into()],
+                args_2: vec!["-c".into(), "cat > /dev/null; printf 'no\\nyes 1'".into()],
+                ignore_case,
+                unordered,
+                ..Default::default()
+            };
+            Runner::new(data, PathBuf::from("/bin/sh"), PathBuf::from("/bin/sh"), options)
+        };
+
+        assert!(matches!(runner(true, true).run_once().unwrap(), RunnerResult::Ok));
+        // The outputs still differ by their order, and are kept verbatim.
+        let RunnerResult::Fail(output_1, output_2) = runner(true, false).run_once().unwrap() else {
+            panic!("Expected the outputs to differ")
+        };
+        assert_eq!((output_1.as_str(), output_2.as_str()), ("YES 1\nNo", "no\nyes 1"));
+        assert!(matches!(runner(false, true).run_once().unwrap(), RunnerResult::Fail(..)));
+    }
+
+    #[test]
+    fn test_generator_generate() {
+        let lines: Vec<String> = vec!["1 <= N <= 100".into(), "input order: N".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+
+        let input = generator.generate().unwrap().to_string();
+        assert_eq!(input, generator.get_state().get_var("N").unwrap().to_string());
+    }
+
+    #[test]
+    fn test_generator_seed() {
+        let lines: Vec<String> = vec!["1 <= N <= 1000000".into(), "0 <= A[10]# <= 1000000".into(), "input order: N A".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines.clone()).unwrap();
+        let mut generator_1 = Generator::new(data, &RunnerOptions { seed: 42, ..Default::default() });
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator_2 = Generator::new(data, &RunnerOptions { seed: 42, ..Default::default() });
+
+        for _ in 0..10 {
+            assert_eq!(generator_1.generate().unwrap(), generator_2.generate().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_generator_shared_variable() {
+        let lines: Vec<String> = vec!["1 <= A <= 10".into(), "0 <= B < A <= 20".into(), "input order: A B".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        for _ in 0..100 {
+            generator.generate().unwrap();
+            let a = *generator.get_state().get_var("A").unwrap();
+            let b = *generator.get_state().get_var("B").unwrap();
+            assert!((1..=10).contains(&a));
+            assert!((0..a).contains(&b));
+        }
+
+        // `A` is picked within both expressions' ranges, never below the second one's minimum.
+        let lines: Vec<String> = vec!["1 <= A <= 10".into(), "5 <= A <= 20".into(), "input order: A".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        for _ in 0..100 {
+            let a: Value = generator.generate().unwrap().parse().unwrap();
+            assert!((5..=10).contains(&a), "{}", a);
+        }
+        for case in [SweepCase::AllMin, SweepCase::AllMax] {
+            let a: Value = generator.generate_sweep(case).unwrap().parse().unwrap();
+            assert!((5..=10).contains(&a), "{}", a);
+        }
+    }
+
+    #[test]
+    fn test_generator_open_chain() {
+        let lines: Vec<String> = vec!["1 <= A < B".into(), "0 <= C <= A".into(), "2 <= B <= 50".into(), "input order: A B C".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions { edge_bias: 0.5, ..Default::default() });
+        for _ in 0..1000 {
+            generator.generate().unwrap();
+            let state = generator.get_state();
+            let (a, b, c) = (*state.get_var("A").unwrap(), *state.get_var("B").unwrap(), *state.get_var("C").unwrap());
+            assert!((2..=50).contains(&b));
+            assert!((1..b).contains(&a));
+            assert!((0..=a).contains(&c));
+        }
+    }
+
+    #[test]
+    fn test_generator_exclusions() {
+        let lines: Vec<String> = vec!["1 <= U,V <= 2".into(), "U != V".into(), "input order: U V".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        for _ in 0..1000 {
+            generator.generate().unwrap();
+            assert_ne!(generator.get_state().get_var("U"), generator.get_state().get_var("V"));
+        }
+
+        let lines: Vec<String> = vec!["1 <= U,V <= 1".into(), "U != V".into(), "input order: U V".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+        assert_eq!(generator.generate().unwrap_err(), AppError::ConstraintUnsatisfiable(2, "U != V".into()));
+    }
+
+    #[test]
+    fn test_generator_shared_variable_retries() {
+        // `A` and `B` are generated by the first expression, and only fit the second one when `A < B`.
+        let lines: Vec<String> = vec!["1 <= A,B <= 10".into(), "1 <= A < B <= 10".into(), "input order: A B".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data.clone(), &RunnerOptions::default());
+        for _ in 0..100 {
+            generator.generate().unwrap();
+            assert!(generator.get_state().get_var("A") < generator.get_state().get_var("B"));
+        }
+
+        let options = RunnerOptions { max_retries: 0, ..Default::default() };
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from("/bin/cat"), options);
+        let mut result = AppResultData::new(None, 0, OutputFormat::Text);
+        for _ in 0..100 {
+            match runner.run_once() {
+                Ok(RunnerResult::Ok) => result.successful_tests += 1,
+                Ok(_) => panic!("Both executables are the same"),
+                Err(err) => {
+                    assert!(matches!(err, AppError::SharedVariableConflict(2, _)), "{:?}", err);
+                    result.record_error(&err)
+                }
+            }
+        }
+        assert!(result.abandoned_tests > 0);
+        assert_eq!(result.abandoned_tests, result.error_tests);
+    }
+
+    #[test]
+    fn test_runner_max_retries() {
+        // Only 2 of the 64 assignments are valid, so most tests run out of retries.
+        let lines: Vec<String> = vec!["1 <= A,B,C,D,E,F <= 2".into(), "A != B".into(), "B != C".into(), "C != D".into(), "D != E".into(), "E != F".into(), "input order: A B C D E F".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let options = RunnerOptions { max_retries: 2, ..Default::default() };
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from("/bin/cat"), options);
+
+        let mut result = AppResultData::new(None, 0, OutputFormat::Text);
+        for _ in 0..100 {
+            match runner.run_once() {
+                Ok(RunnerResult::Ok) => result.successful_tests += 1,
+                Ok(_) => panic!("Both executables are the same"),
+                Err(err) => result.record_error(&err)
+            }
+        }
+        assert!(result.abandoned_tests > 50, "{} abandoned tests", result.abandoned_tests);
+        assert_eq!(result.abandoned_tests, result.error_tests);
+        assert_eq!(result.successful_tests + result.error_tests, 100);
+    }
+
+    #[test]
+    fn test_generator_independent_variables() {
+        let lines: Vec<String> = vec!["1 <= A <= 10".into(), "0 <= B < A <= 20".into(), "input order: A B".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions { independent_vars: true, ..Default::default() });
+        let mut above_first_max = false;
+        for _ in 0..100 {
+            generator.generate().unwrap();
+            let a = *generator.get_state().get_var("A").unwrap();
+            above_first_max |= a > 10;
+        }
+        assert!(above_first_max);
+    }
+
+    #[test]
+    fn test_generator_mix() {
+        let small = FuzzData::parse(" ".into(), " ".into(), vec!["1 <= N <= 1".into(), "input order: N".into()]).unwrap();
+        let large = FuzzData::parse(" ".into(), " ".into(), vec!["2 <= N <= 2".into(), "input order: N".into()]).unwrap();
+        let mut generator = Generator::with_mix(vec![(small, 7), (large, 3)], &RunnerOptions::default());
+
+        let mut small_count = 0;
+        for _ in 0..10000 {
+            let input = generator.generate().unwrap().to_string();
+            assert_eq!(input, (generator.current_spec() + 1).to_string());
+            small_count += (input == "1") as u32;
+        }
+        assert!((6700..=7300).contains(&small_count), "{} small inputs", small_count);
+    }
+
+    #[test]
+    fn test_generator_blocks() {
+        let lines: Vec<String> = vec!["1 <= N,M <= 9".into(), "input order: N M".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+
+        // The same blocks as with --multi-test, minus the count, each on a line of its own.
+        let mut generator = Generator::new(data.clone(), &RunnerOptions { blocks: Some((3, 3)), seed: 5, ..Default::default() });
+        let input = generator.generate().unwrap().to_string();
+        assert_eq!(generator.get_blocks().len(), 3);
+        let mut multi_test = Generator::new(data.clone(), &RunnerOptions { multi_test: Some((3, 3)), seed: 5, ..Default::default() });
+        assert_eq!(multi_test.generate().unwrap(), format!("3 {}", input.replace('\n', " ")));
+
+        let blocks: Vec<Vec<Value>> = input.split('\n').map(|block| block.split(' ').map(|item| item.parse().unwrap()).collect()).collect();
+        let expected: Vec<Vec<Value>> = generator.get_blocks().iter().map(|block| vec![*block.get_var("N").unwrap(), *block.get_var("M").unwrap()]).collect();
+        assert_eq!(blocks, expected);
+
+        let max = generator.generate_sweep(SweepCase::AllMax).unwrap();
+        assert_eq!(max, "9 9\n9 9\n9 9");
+
+        let options = RunnerOptions { blocks: Some((3, 3)), input_record_separator: Some(";".into()), ..Default::default() };
+        let mut generator = Generator::new(data, &options);
+        assert_eq!(generator.generate_sweep(SweepCase::AllMax).unwrap(), "9 9;9 9;9 9");
+    }
+
+    #[test]
+    fn test_generator_strings() {
+        let lines: Vec<String> = vec!["1 <= N <= 20".into(), "S{N} T{3}".into(), "input order: N S T".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions { alphabet: vec!['x', 'y'], ..Default::default() });
+
+        for _ in 0..100 {
+            generator.generate().unwrap();
+            let state = generator.get_state();
+            let s = state.get_str("S").unwrap();
+            let t = state.get_str("T").unwrap();
+            assert_eq!(s.len() as Value, *state.get_var("N").unwrap());
+            assert_eq!(t.len(), 3);
+            assert!(s.chars().chain(t.chars()).all(|ch| ch == 'x' || ch == 'y'));
+            assert_eq!(generator.get_last_input(), format!("{} {} {}", state.get_var("N").unwrap(), s, t));
+        }
+    }
+
+    #[test]
+    fn test_generator_palindromes() {
+        let lines: Vec<String> = vec!["1 <= N <= 20".into(), "P{N}@palindrome S{N}".into(), "input order: N P S".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions { string_distinct: Some(2), ..Default::default() });
+
+        for _ in 0..100 {
+            generator.generate().unwrap();
+            let state = generator.get_state();
+            let p = state.get_str("P").unwrap();
+            let s = state.get_str("S").unwrap();
+            assert_eq!(p.len() as Value, *state.get_var("N").unwrap());
+            assert_eq!(p.chars().rev().collect::<String>(), *p);
+            for string in [p, s] {
+                assert!(string.chars().collect::<std::collections::HashSet<_>>().len() <= 2);
+            }
+        }
+
+        assert_eq!(mirror_half(&['a', 'b', 'c'], 5), "abcba");
+        assert_eq!(mirror_half(&['a', 'b', 'c'], 6), "abccba");
+        assert_eq!(mirror_half(&['a', 'b', 'c'], 3), "aba");
+        assert_eq!(mirror_half(&['a'], 1), "a");
+    }
+
+    #[test]
+    fn test_generator_trees() {
+        let lines: Vec<String> = vec!["1 <= N <= 50".into(), "tree G[N]#".into(), "input order: N G".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+
+        for _ in 0..100 {
+            generator.generate().unwrap();
+            let state = generator.get_state();
+            let nodes = *state.get_var("N").unwrap();
+            let edges = state.get_tree("G").unwrap();
+            assert_eq!(edges.len() as Value, nodes - 1);
+
+            // N - 1 edges joining every node into one component means there is no cycle.
+            let mut component: Vec<Value> = (0..=nodes).collect();
+            fn root(component: &[Value], node: Value) -> Value {
+                let mut node = node;
+                while component[node as usize] != node {
+                    node = component[node as usize];
+                }
+                node
+            }
+            for &(u, v) in edges {
+                assert!((1..=nodes).contains(&u) && (1..=nodes).contains(&v));
+                let (root_u, root_v) = (root(&component, u), root(&component, v));
+                assert_ne!(root_u, root_v);
+                component[root_u as usize] = root_v;
+            }
+
+            let edges: Vec<String> = edges.iter().map(|(u, v)| format!("{} {}", u, v)).collect();
+            assert_eq!(generator.get_last_input(), format!("{} {}", nodes, edges.join("\n")));
+        }
+    }
+
+    #[test]
+    fn test_runner_no_stale_values() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "1 <= A[N]# <= 10".into(), "input order: N A".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from("/bin/echo"), RunnerOptions::default());
+        runner.run_once().unwrap();
+        assert!(runner.get_state().get_arr("A").is_some());
+
+        // Swap in a spec without `A`: none of its values may survive into the next run.
+        let lines: Vec<String> = vec!["1 <= M <= 10".into(), "1 <= B[M]# <= 10".into(), "input order: M B".into()];
+        runner.replace_spec(FuzzData::parse(" ".into(), " ".into(), lines).unwrap());
+        runner.run_once().unwrap();
+        let state = runner.get_state();
+        assert!(state.get_var("N").is_none());
+        assert!(state.get_arr("A").is_none());
+        assert_eq!(state.get_arr("B").unwrap().len() as Value, *state.get_var("M").unwrap());
+        let json = state.to_json();
+        assert_eq!(json["variables"].as_object().unwrap().len(), 1);
+        assert_eq!(json["arrays"].as_object().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_runner_result_fail_outputs() {
+        let lines: Vec<String> = vec!["1 <= N <= 1".into(), "input order: N".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let options = RunnerOptions { args_2: vec!["2".into()], ..Default::default() };
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from("/bin/echo"), options);
+
+        let RunnerResult::Fail(out1, out2) = runner.run_once().unwrap() else {
+            panic!("Expected the run to fail")
+        };
+        assert_eq!(out1, "1");
+        assert_eq!(out2, "2\n");
+    }
+
+    #[test]
+    fn test_runner_empty_outputs() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "input order: N".into()];
+        let silent = vec!["-c".to_string(), "cat > /dev/null".to_string()];
+        let run = |args_2: Vec<String>, require_output| {
+            let data = FuzzData::parse("\n".into(), "\n".into(), lines.clone()).unwrap();
+            let options = RunnerOptions { args_1: silent.clone(), args_2, require_output, ..Default::default() };
+            let mut runner = Runner::new(data, PathBuf::from("/bin/sh"), PathBuf::from("/bin/sh"), options);
+            runner.run_once()
+        };
+
+        // Empty against empty
+        assert!(matches!(run(silent.clone(), false), Ok(RunnerResult::Ok)));
+        assert!(matches!(run(silent.clone(), true), Err(AppError::NoOutput(exe)) if exe == Path::new("/bin/sh")));
+
+        // Empty against nonempty
+        let echoing = vec!["-c".to_string(), "cat".to_string()];
+        assert!(matches!(run(echoing.clone(), false), Ok(RunnerResult::Fail(out1, out2)) if out1.is_empty() && !out2.is_empty()));
+        assert!(matches!(run(echoing, true), Err(AppError::NoOutput(_))));
+    }
+
+    #[test]
+    fn test_runner_crashed() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "input order: N".into()];
+        let data = FuzzData::parse("\n".into(), "\n".into(), lines).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from("/bin/false"), RunnerOptions::default());
+
+        let RunnerResult::Crashed { which, status } = runner.run_once().unwrap() else {
+            panic!("Expected the run to crash")
+        };
+        assert_eq!(which, ExecutableId::B);
+        assert_eq!(status.code(), Some(1));
+    }
+
+    #[test]
+    fn test_runner_run_input() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "input order: N".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("examples/example"), PathBuf::from("/bin/cat"), RunnerOptions::default());
+        assert!(matches!(runner.run_input(Path::new("00000001.txt"), "1\n5".into()).unwrap(), RunnerResult::Fail(output_1, output_2) if output_1 == "6\n" && output_2 == "1\n5"));
+        assert_eq!(runner.get_last_input(), "1\n5");
+        assert_eq!(runner.get_state().get_var("N"), None);
+    }
+
+    #[test]
+    fn test_runner_self_check() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "input order: N".into()];
+        let data = FuzzData::parse("\n".into(), "\n".into(), lines).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), Reference::SelfCheck, RunnerOptions::default());
+        for _ in 0..10 {
+            assert!(matches!(runner.run_once().unwrap(), RunnerResult::Ok));
+        }
+
+        // Nanoseconds of the current time differ between the two runs. The input is read first, as
+        // writing it to a program that exited already fails.
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "input order: N".into()];
+        let data = FuzzData::parse("\n".into(), "\n".into(), lines).unwrap();
+        let options = RunnerOptions { args_1: vec!["-c".into(), "cat > /dev/null; date +%N".into()], ..Default::default() };
+        let mut runner = Runner::new(data, PathBuf::from("/bin/sh"), Reference::SelfCheck, options);
+        assert!(matches!(runner.run_once().unwrap(), RunnerResult::Fail(..)));
+    }
+
+    #[test]
+    fn test_runner_expected_outputs() {
+        let lines: Vec<String> = vec!["1 <= N <= 1".into(), "input order: N".into()];
+        let data = FuzzData::parse("\n".into(), "\n".into(), lines).unwrap();
+        let dir = std::env::temp_dir().join(format!("beanfuzz-expected-{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), Reference::ExpectedOutputs(dir.clone()), RunnerOptions::default());
+        assert!(matches!(runner.run_once(), Err(AppError::InvalidArgument(_))));
+
+        let input_path = Path::new("corpus/00000001.txt");
+        let path = expected_output_path(&dir, input_path);
+        assert_eq!(path, dir.join("00000001.out"));
+        assert!(matches!(runner.run_input(input_path, "1".into()), Err(AppError::NoExpectedOutput(missing)) if missing == path));
+
+        write(&path, "1\n").unwrap();
+        assert!(matches!(runner.run_input(input_path, "1".into()).unwrap(), RunnerResult::Ok));
+
+        write(&path, "2\n").unwrap();
+        let RunnerResult::Fail(output, expected) = runner.run_input(input_path, "1".into()).unwrap() else {
+            panic!("Expected the outputs to differ")
+        };
+        assert_eq!((output.as_str(), expected.as_str()), ("1", "2\n"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_runner_timings() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "input order: N".into()];
+        let data = FuzzData::parse("\n".into(), "\n".into(), lines).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("examples/example"), PathBuf::from("/bin/cat"), RunnerOptions::default());
+        let mut timings = ExecTimings::default();
+
+        for _ in 0..3 {
+            runner.run_once().unwrap();
+            let (duration_1, duration_2) = runner.get_last_durations();
+            assert!(duration_1 > Duration::ZERO && duration_2 > Duration::ZERO);
+            timings.record(duration_1);
+        }
+        assert_eq!(timings.runs, 3);
+        assert!(timings.min.unwrap() <= timings.mean().unwrap() && timings.mean().unwrap() <= timings.max);
+    }
+
+    #[test]
+    fn test_runner_sizes() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "1 <= A[N]# <= 100".into(), "input order: N A".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from("examples/example"), RunnerOptions::default());
+        let mut result = AppResultData::new(None, 0, OutputFormat::Text);
+
+        for _ in 0..3 {
+            runner.run_once().unwrap();
+            let sizes = runner.get_last_sizes();
+            assert_eq!(sizes.0, runner.get_last_input().len());
+            assert_eq!(sizes.1, sizes.0);
+            result.record_sizes(sizes);
+        }
+        assert_eq!(result.input_size.count, 3);
+        assert!(result.input_size.min.unwrap() >= 3 && result.input_size.max <= 2 + 10 * 4);
+        assert_eq!(result.output_size_a, result.input_size);
+        assert_eq!(result.output_size_b.count, 3);
+        assert!(result.to_string().contains(&format!("Input   : {}\n", result.input_size)));
+    }
+
+    #[test]
+    fn test_vars_data_display() {
+        let mut data = VarsData::new();
+        data.set_var("N", 3);
+        data.set_var("B", 1);
+        data.set_arr("A", vec![1, 2, 3]);
+        assert_eq!(data.to_string(), "variables: {B: 1, N: 3}, arrays: {A: [1, 2, 3]}");
+
+        data.set_str("S", "ab".into());
+        assert_eq!(data.to_string(), "variables: {B: 1, N: 3}, arrays: {A: [1, 2, 3]}, strings: {S: \"ab\"}");
+    }
+
+    #[test]
+    fn test_vars_data_order() {
+        let inserts = [("N", 0), ("B", 1), ("Z", 2), ("A", 3), ("K", 4), ("M", 5)];
+        let build = |inserts: &mut dyn Iterator<Item = &(&str, Value)>| {
+            let mut data = VarsData::new();
+            for (name, val) in inserts {
+                data.set_var(name, *val);
+                data.set_arr(&name.to_lowercase(), vec![*val]);
+            }
+            data
+        };
+        let forward = build(&mut inserts.iter());
+        let reversed = build(&mut inserts.iter().rev());
+
+        // Same inserts in a different order, listed the same way whichever way it is formatted.
+        assert_eq!(forward.to_string(), reversed.to_string());
+        assert_eq!(format!("{:?}", forward), format!("{:?}", reversed));
+        assert_eq!(forward.to_json().to_string(), reversed.to_json().to_string());
+        assert!(format!("{:?}", forward).starts_with("VarsData { variables: {\"A\": 3, \"B\": 1, \"K\": 4"));
+    }
+
+    #[test]
+    fn test_runner_state_after_run() {
+        let lines: Vec<String> = vec!["1 <= N <= 10".into(), "1 <= A <= B <= 10".into(), "1 <= C[N]# <= 10".into(), "input order: N A B C".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from("/bin/echo"), RunnerOptions::default());
+        runner.run_once().unwrap();
+
+        let state = runner.get_state();
+        for key in ["N", "A", "B"] {
+            assert!(state.get_var(key).is_some());
+        }
+        assert!(state.get_arr("C").is_some());
+    }
+
+    /// Rough benchmark of the generation speed, ran with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn bench_generate() {
+        let lines: Vec<String> = vec![
+            "1 <= A < B <= C,D < E <= 1000000000".into(),
+            "1 <= F <= G <= H <= 1000".into(),
+            "1 <= N <= 10".into(),
+            "1 <= X[N]# <= 100".into(),
+            "input order: A B C D E F G H N X".into(),
+        ];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut generator = Generator::new(data, &RunnerOptions::default());
+
+        let start = std::time::Instant::now();
+        for _ in 0..1_000_000 {
+            generator.generate().unwrap();
+        }
+        println!("Generated 1000000 inputs in {:?}", start.elapsed());
     }
 }
@@ -0,0 +1,590 @@
+//! Input minimization for failing tests. After a failure is found, the variable store is
+//! repeatedly reduced (numbers toward their lower bound, arrays toward shorter lengths) and ran
+//! against both executables again. Any reduction that still fails is kept. The expressions the
+//! minimized failure depends on can then be singled out, see `Runner::minimal_failing_constraints`.
+
+use std::{collections::{HashMap, HashSet}, mem};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::parser::{parser::{FuzzData, FuzzExpr}, tokenizer::{ComparisonType, ExprVariable, LenExpr, Value}};
+
+use super::{exclusion_holds, mirror_half, recurse_set_variables, PickMode, Runner, RunnerResult, VarsData};
+
+/// Upper limit of how many times both executables are ran while shrinking a single failure. Big
+/// arrays would otherwise take forever to minimize.
+const SHRINK_MAX_RUNS: u64 = 1000;
+
+/// How many times an expression's variables get fresh values when checking whether the minimized
+/// failure depends on it. It only doesn't when every single run still fails.
+const CONSTRAINT_TRIES: u64 = 8;
+
+/// Check whether the values inside a `VarsData` satisfy every constraint of an expression, i.e
+/// the ordering between variable groups, the constant bounds, and the array and matrix sizes.
+///
+/// # Arguments
+/// - `expr`: the expression to check against
+/// - `data`: the variable store to check
+///
+/// # Returns
+/// A boolean indicating whether the expression is satisfied.
+fn expr_satisfied(expr: &FuzzExpr, data: &VarsData) -> bool {
+    let mut prev_max = expr.const_min;
+
+    for (depth, group) in expr.vars.iter().enumerate() {
+        let strict = expr.comparisons[depth] == ComparisonType::LessThan;
+        let mut group_max = prev_max;
+        let mut distinct_vals = HashSet::new();
+
+        for var in group {
+            let values = match var {
+                ExprVariable::Variable(key, flags) => match data.get_var(key) {
+                    Some(val) if flags.distinct && !distinct_vals.insert(*val) => return false,
+                    Some(val) => vec![*val],
+                    None => return false
+                },
+                ExprVariable::Choice(key, choices) => match data.get_var(key) {
+                    Some(val) if choices.contains(val) => vec![*val],
+                    _ => return false
+                },
+                ExprVariable::Array(key, len, flags) => {
+                    let Some(arr) = data.get_arr(key) else {
+                        return false
+                    };
+                    let expected_len = len.resolve(|len_key| data.get_var(len_key).copied());
+                    if expected_len != Some(arr.len() as Value) {
+                        return false
+                    }
+                    if flags.distinct && arr.iter().collect::<HashSet<_>>().len() != arr.len() {
+                        return false
+                    }
+                    if flags.sorted && !arr.windows(2).all(|pair| pair[0] <= pair[1]) {
+                        return false
+                    }
+                    if flags.sum_cap.is_some_and(|cap| arr.iter().fold(0, |sum: Value, val| sum.saturating_add(*val)) > cap) {
+                        return false
+                    }
+                    arr.clone()
+                }
+                ExprVariable::Matrix(key, rows, cols, flags) => {
+                    let Some(matrix) = data.get_matrix(key) else {
+                        return false
+                    };
+                    let expected_rows = rows.resolve(|len_key| data.get_var(len_key).copied());
+                    let expected_cols = cols.resolve(|len_key| data.get_var(len_key).copied());
+                    if expected_rows != Some(matrix.len() as Value) || !matrix.iter().all(|row| expected_cols == Some(row.len() as Value)) {
+                        return false
+                    }
+                    let items: Vec<Value> = matrix.iter().flatten().copied().collect();
+                    if flags.distinct && items.iter().collect::<HashSet<_>>().len() != items.len() {
+                        return false
+                    }
+                    if flags.sorted && !items.windows(2).all(|pair| pair[0] <= pair[1]) {
+                        return false
+                    }
+                    items
+                }
+            };
+
+            for val in values {
+                if val < prev_max || (strict && val == prev_max) {
+                    return false
+                }
+                group_max = group_max.max(val);
+            }
+        }
+
+        prev_max = group_max;
+    }
+
+    let strict = expr.comparisons[expr.vars.len()] == ComparisonType::LessThan;
+    prev_max < expr.const_max || (!strict && prev_max == expr.const_max)
+}
+
+/// Get smaller values to try in place of `val`, ordered from the most aggressive one.
+///
+/// # Arguments
+/// - `val`: the current value
+/// - `lower`: the smallest value allowed
+///
+/// # Returns
+/// A vector of candidate values, all strictly smaller than `val`.
+fn shrink_candidates(val: Value, lower: Value) -> Vec<Value> {
+    let mut candidates = Vec::new();
+    for candidate in [lower, val.saturating_sub_unsigned(val.abs_diff(lower) / 2), val.saturating_sub(1)] {
+        if candidate >= lower && candidate < val && !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+/// Shrink a tree to its first `nodes` nodes. Rooted at node 1, every dropped node is merged into
+/// its nearest kept ancestor, so the result is still a tree.
+///
+/// # Arguments
+/// - `edges`: the tree's edges
+/// - `nodes`: the amount of nodes to keep, at least 1
+///
+/// # Returns
+/// The edges of the shrunk tree.
+fn contract_tree(edges: &[(Value, Value)], nodes: Value) -> Vec<(Value, Value)> {
+    let mut adjacent: HashMap<Value, Vec<Value>> = HashMap::new();
+    for &(u, v) in edges {
+        adjacent.entry(u).or_default().push(v);
+        adjacent.entry(v).or_default().push(u);
+    }
+
+    // Each visited node along with its nearest kept ancestor (itself when kept).
+    let mut kept_ancestor = HashMap::from([(1, 1)]);
+    let mut stack = vec![1];
+    let mut contracted = Vec::new();
+    while let Some(node) = stack.pop() {
+        let ancestor = kept_ancestor[&node];
+        for &next in adjacent.get(&node).into_iter().flatten() {
+            if kept_ancestor.contains_key(&next) {
+                continue
+            }
+            if next <= nodes {
+                contracted.push((ancestor, next));
+                kept_ancestor.insert(next, next);
+            } else {
+                kept_ancestor.insert(next, ancestor);
+            }
+            stack.push(next);
+        }
+    }
+    contracted
+}
+
+impl Runner {
+    /// The smallest value each variable and array may take, keyed by its name. A variable shared
+    /// between expressions takes the highest of their lower bounds.
+    fn lower_bounds(&self) -> HashMap<String, Value> {
+        let mut bounds = HashMap::new();
+        for expr in &self.generator.data().exprs {
+            let lower = expr.group_mins[0];
+            for var in expr.vars.iter().flatten() {
+                let (ExprVariable::Variable(key, _) | ExprVariable::Array(key, ..) | ExprVariable::Matrix(key, ..) | ExprVariable::Choice(key, _)) = var;
+                bounds.entry(key.clone()).and_modify(|bound: &mut Value| *bound = (*bound).max(lower)).or_insert(lower);
+            }
+        }
+        bounds
+    }
+
+    /// The listed values of each variable picked from a list, keyed by its name. Such a variable
+    /// only shrinks to smaller listed values.
+    fn choices(&self) -> HashMap<String, Vec<Value>> {
+        self.generator.data().exprs.iter()
+            .flat_map(|expr| expr.vars.iter().flatten())
+            .filter_map(|var| match var {
+                ExprVariable::Choice(key, values) => Some((key.clone(), values.clone())),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Truncate every array, matrix and string (and contract every tree) of a variable store to
+    /// the size its length variables currently resolve to. Used after a length variable got shrunk.
+    fn refit(&self, data: &mut VarsData) {
+        let resolve = |len: &LenExpr, data: &VarsData| len.resolve(|len_key| data.get_var(len_key).copied()).and_then(|len| usize::try_from(len).ok());
+        for var in self.generator.data().exprs.iter().flat_map(|expr| expr.vars.iter().flatten()) {
+            match var {
+                ExprVariable::Array(key, len, _) => {
+                    if let Some(new_len) = resolve(len, data) {
+                        if let Some(arr) = data.arrays.get_mut(key) {
+                            arr.truncate(new_len);
+                        }
+                    }
+                }
+                ExprVariable::Matrix(key, rows, cols, _) => {
+                    if let (Some(new_rows), Some(new_cols)) = (resolve(rows, data), resolve(cols, data)) {
+                        if let Some(matrix) = data.matrices.get_mut(key) {
+                            matrix.truncate(new_rows);
+                            matrix.iter_mut().for_each(|row| row.truncate(new_cols));
+                        }
+                    }
+                }
+                ExprVariable::Variable(..) | ExprVariable::Choice(..) => ()
+            }
+        }
+        for string in &self.generator.data().strings {
+            if let (Some(new_len), Some(val)) = (resolve(&string.len, data), data.strings.get_mut(&string.name)) {
+                *val = if string.palindrome {
+                    mirror_half(&val.chars().collect::<Vec<_>>(), new_len)
+                } else {
+                    val.chars().take(new_len).collect()
+                };
+            }
+        }
+        for tree in &self.generator.data().trees {
+            if let (Some(nodes), Some(edges)) = (resolve(&tree.nodes, data), data.trees.get_mut(&tree.name)) {
+                if nodes >= 1 && edges.len() >= nodes {
+                    *edges = contract_tree(edges, nodes as Value);
+                }
+            }
+        }
+    }
+
+    /// Swap in reduced variable stores and run them. The reduction is kept when it is valid (see
+    /// `expr_satisfied` and `exclusion_holds`) and still fails, otherwise the previous stores are
+    /// restored.
+    fn try_shrunk(&mut self, candidate: Vec<VarsData>, outputs: &mut (String, String), runs: &mut u64) -> bool {
+        let data = self.generator.data();
+        let valid = |block: &VarsData| data.exprs.iter().all(|expr| expr_satisfied(expr, block)) && data.exclusions.iter().all(|exclusion| exclusion_holds(exclusion, block));
+        if !candidate.iter().all(valid) {
+            return false
+        }
+
+        *runs += 1;
+        let old_blocks = mem::replace(&mut self.generator.blocks, candidate);
+        let old_input = mem::take(&mut self.generator.last_input);
+
+        if let Ok(RunnerResult::Fail(out1, out2)) = self.run_current() {
+            *outputs = (out1, out2);
+            true
+        } else {
+            self.generator.blocks = old_blocks;
+            self.generator.last_input = old_input;
+            false
+        }
+    }
+
+    /// Copy the variable stores with fresh random values for the variables of some expressions,
+    /// every other variable keeping its value.
+    ///
+    /// # Arguments
+    /// - `data`: the spec the stores were generated from
+    /// - `blocks`: the variable stores
+    /// - `exprs`: indices of the expressions to regenerate, in generation order
+    /// - `rng`: the RNG to pick the fresh values with
+    ///
+    /// # Returns
+    /// An `Option` containing the new stores, or `None` when the fresh values don't fit the rest
+    /// of the spec (e.g a shared variable, or a length the other declarations were sized for).
+    fn regenerate(&self, data: &FuzzData, blocks: &[VarsData], exprs: &[usize], rng: &mut StdRng) -> Option<Vec<VarsData>> {
+        let mut blocks = blocks.to_vec();
+        for block in &mut blocks {
+            for var in exprs.iter().flat_map(|&i| data.exprs[i].vars.iter().flatten()) {
+                match var {
+                    ExprVariable::Variable(key, _) | ExprVariable::Choice(key, _) => block.variables.remove(key),
+                    ExprVariable::Array(key, ..) => block.arrays.remove(key).map(|_| 0),
+                    ExprVariable::Matrix(key, ..) => block.matrices.remove(key).map(|_| 0),
+                };
+            }
+            for &i in exprs {
+                let ctx = self.generator.fill_context(PickMode::Random(self.generator.edge_bias), &self.generator.specs[self.generator.current_spec]);
+                recurse_set_variables(rng, &data.exprs[i], block, ctx, None).ok()?;
+            }
+            self.refit(block);
+
+            // Refitting only ever shortens, so anything sized for a now larger length is invalid.
+            let resolve = |len: &LenExpr| len.resolve(|key| block.get_var(key).copied());
+            let strings_fit = data.strings.iter().all(|string| resolve(&string.len) == block.get_str(&string.name).map(|val| val.chars().count() as Value));
+            let trees_fit = data.trees.iter().all(|tree| resolve(&tree.nodes) == block.get_tree(&tree.name).map(|edges| edges.len() as Value + 1));
+            let valid = data.exprs.iter().all(|expr| expr_satisfied(expr, block)) && data.exclusions.iter().all(|exclusion| exclusion_holds(exclusion, block));
+            if !(valid && strings_fit && trees_fit) {
+                return None
+            }
+        }
+        Some(blocks)
+    }
+
+    /// Find the expressions the last (minimized) failure depends on, going through them in
+    /// generation order. An expression doesn't matter when the failure reproduces every time its
+    /// variables (and those of the expressions found not to matter so far) get fresh random values,
+    /// every other variable keeping its minimized value. Meant to be called after `shrink`, and
+    /// the failing test is restored afterwards.
+    ///
+    /// # Returns
+    /// The expressions that matter, as written in the spec.
+    pub fn minimal_failing_constraints(&mut self) -> Vec<String> {
+        let data = self.generator.data().clone();
+        let failing_blocks = self.generator.blocks.clone();
+        let failing_input = self.generator.last_input.clone();
+        // A separate RNG, so the inputs generated afterwards stay the same.
+        let mut rng = StdRng::seed_from_u64(self.options.seed);
+
+        let mut free: Vec<usize> = Vec::new();
+        for i in 0..data.exprs.len() {
+            let candidate: Vec<usize> = free.iter().copied().chain([i]).collect();
+            let reproduces = (0..CONSTRAINT_TRIES).all(|_| match self.regenerate(&data, &failing_blocks, &candidate, &mut rng) {
+                Some(blocks) => {
+                    self.generator.blocks = blocks;
+                    matches!(self.run_current(), Ok(RunnerResult::Fail(..)))
+                }
+                None => false
+            });
+            if reproduces {
+                free.push(i);
+            }
+        }
+
+        self.generator.blocks = failing_blocks;
+        self.generator.last_input = failing_input;
+        data.exprs.iter().enumerate().filter(|(i, _)| !free.contains(i)).map(|(_, expr)| expr.repr.clone()).collect()
+    }
+
+    /// Minimize the last failing input. With multiple test cases per input, test cases are
+    /// dropped first (as long as the minimum test case count allows it). Then each variable is
+    /// shrunk toward its lower bound, and arrays with a variable length get shorter along with
+    /// the length variable. Every reduction is checked against the original expressions before
+    /// being ran. Afterwards, `get_state` and `get_last_input` return the minimized failing test.
+    ///
+    /// # Arguments
+    /// - `outputs`: both executables' outputs of the failing test
+    ///
+    /// # Returns
+    /// Both executables' outputs of the minimized failing test.
+    pub fn shrink(&mut self, mut outputs: (String, String)) -> (String, String) {
+        let lower_bounds = self.lower_bounds();
+        let choices = self.choices();
+        let min_blocks = self.options.multi_test.or(self.options.blocks).map_or(1, |(min, _)| min as usize);
+        let mut runs = 0;
+
+        loop {
+            let mut improved = false;
+
+            let mut b = 0;
+            while b < self.generator.blocks.len() && self.generator.blocks.len() > min_blocks {
+                if runs >= SHRINK_MAX_RUNS {
+                    return outputs
+                }
+
+                let mut candidate = self.generator.blocks.clone();
+                candidate.remove(b);
+                if self.try_shrunk(candidate, &mut outputs, &mut runs) {
+                    improved = true;
+                } else {
+                    b += 1;
+                }
+            }
+
+            for b in 0..self.generator.blocks.len() {
+                let keys: Vec<String> = self.generator.blocks[b].variables.keys().cloned().collect();
+                for key in keys {
+                    let val = self.generator.blocks[b].variables[&key];
+                    let candidates = match choices.get(&key) {
+                        Some(values) => values.iter().copied().filter(|listed| *listed < val).collect(),
+                        None => shrink_candidates(val, lower_bounds[&key])
+                    };
+                    for candidate_val in candidates {
+                        if runs >= SHRINK_MAX_RUNS {
+                            return outputs
+                        }
+
+                        let mut candidate = self.generator.blocks.clone();
+                        candidate[b].set_var(&key, candidate_val);
+                        self.refit(&mut candidate[b]);
+
+                        if self.try_shrunk(candidate, &mut outputs, &mut runs) {
+                            improved = true;
+                            break
+                        }
+                    }
+                }
+
+                let keys: Vec<String> = self.generator.blocks[b].arrays.keys().cloned().collect();
+                for key in keys {
+                    for i in 0..self.generator.blocks[b].arrays[&key].len() {
+                        let val = self.generator.blocks[b].arrays[&key][i];
+                        for candidate_val in shrink_candidates(val, lower_bounds[&key]) {
+                            if runs >= SHRINK_MAX_RUNS {
+                                return outputs
+                            }
+
+                            let mut candidate = self.generator.blocks.clone();
+                            if let Some(arr) = candidate[b].arrays.get_mut(&key) {
+                                arr[i] = candidate_val;
+                            }
+
+                            if self.try_shrunk(candidate, &mut outputs, &mut runs) {
+                                improved = true;
+                                break
+                            }
+                        }
+                    }
+                }
+
+                let keys: Vec<String> = self.generator.blocks[b].matrices.keys().cloned().collect();
+                for key in keys {
+                    let (rows, cols) = {
+                        let matrix = &self.generator.blocks[b].matrices[&key];
+                        (matrix.len(), matrix.first().map_or(0, Vec::len))
+                    };
+                    for (i, j) in (0..rows).flat_map(|i| (0..cols).map(move |j| (i, j))) {
+                        let val = self.generator.blocks[b].matrices[&key][i][j];
+                        for candidate_val in shrink_candidates(val, lower_bounds[&key]) {
+                            if runs >= SHRINK_MAX_RUNS {
+                                return outputs
+                            }
+
+                            let mut candidate = self.generator.blocks.clone();
+                            if let Some(matrix) = candidate[b].matrices.get_mut(&key) {
+                                matrix[i][j] = candidate_val;
+                            }
+
+                            if self.try_shrunk(candidate, &mut outputs, &mut runs) {
+                                improved = true;
+                                break
+                            }
+                        }
+                    }
+                }
+            }
+
+            if !improved {
+                return outputs
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::parser::{parser::{parse_expr_from_line, FuzzData}, tokenizer::tokenize_expr_line};
+
+    use crate::exec::RunnerOptions;
+
+    use super::*;
+
+    #[test]
+    fn test_contract_tree() {
+        // 1 - 4 - 2, 4 - 5 - 3: dropping 4 and 5 attaches 2 and 3 to 1.
+        let edges = vec![(1, 4), (2, 4), (5, 4), (3, 5)];
+        let mut contracted = contract_tree(&edges, 3);
+        contracted.sort();
+        assert_eq!(contracted, vec![(1, 2), (1, 3)]);
+        assert_eq!(contract_tree(&edges, 1), vec![]);
+    }
+
+    #[test]
+    fn test_shrink_candidates() {
+        assert_eq!(shrink_candidates(100, 0), vec![0, 50, 99]);
+        assert_eq!(shrink_candidates(2, 1), vec![1]);
+        assert_eq!(shrink_candidates(1, 1), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn test_expr_satisfied() {
+        let expr_str = "1 < A <= C,D[N]# <= 100";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+        data.set_var("N", 2);
+        data.set_var("A", 2);
+        data.set_var("C", 2);
+        data.set_arr("D", vec![3, 100]);
+        assert!(expr_satisfied(&expr, &data));
+
+        data.set_var("A", 1);
+        assert!(!expr_satisfied(&expr, &data));
+
+        data.set_var("A", 5);
+        assert!(!expr_satisfied(&expr, &data));
+
+        data.set_var("A", 2);
+        data.set_arr("D", vec![3]);
+        assert!(!expr_satisfied(&expr, &data));
+    }
+
+    #[test]
+    fn test_expr_satisfied_distinct() {
+        let expr_str = "1 <= A!,B! <= C[2]#! <= 100";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+        data.set_var("A", 1);
+        data.set_var("B", 2);
+        data.set_arr("C", vec![3, 4]);
+        assert!(expr_satisfied(&expr, &data));
+
+        data.set_var("B", 1);
+        assert!(!expr_satisfied(&expr, &data));
+
+        data.set_var("B", 2);
+        data.set_arr("C", vec![3, 3]);
+        assert!(!expr_satisfied(&expr, &data));
+    }
+
+    #[test]
+    fn test_expr_satisfied_matrix() {
+        let expr_str = "1 <= A[2][N]# <= 100";
+        let expr = parse_expr_from_line(expr_str, &mut tokenize_expr_line(expr_str).unwrap()).unwrap();
+        let mut data = VarsData::new();
+        data.set_var("N", 3);
+        data.set_matrix("A", vec![vec![1, 2, 3], vec![4, 5, 6]]);
+        assert!(expr_satisfied(&expr, &data));
+
+        data.set_matrix("A", vec![vec![1, 2, 3], vec![4, 5]]);
+        assert!(!expr_satisfied(&expr, &data));
+
+        data.set_matrix("A", vec![vec![1, 2, 3], vec![4, 5, 0]]);
+        assert!(!expr_satisfied(&expr, &data));
+    }
+
+    #[test]
+    fn test_shrink_to_lower_bounds() {
+        let lines: Vec<String> = vec![
+            "1 <= N <= 100".into(),
+            "0 <= A[N]# <= 500000".into(),
+            "input order: N A".into(),
+        ];
+        let data = FuzzData::parse("\n".into(), "\n".into(), lines).unwrap();
+
+        // The example program adds one to each item, so it never agrees with `cat`.
+        let mut runner = Runner::new(data, PathBuf::from("examples/example"), PathBuf::from("/bin/cat"), RunnerOptions::default());
+        let RunnerResult::Fail(out1, out2) = runner.run_once().unwrap() else {
+            panic!("Expected the run to fail")
+        };
+
+        let (out1, out2) = runner.shrink((out1, out2));
+        assert_eq!(runner.get_state().get_var("N"), Some(&1));
+        assert_eq!(runner.get_state().get_arr("A"), Some(&vec![0]));
+        assert_eq!(runner.get_last_input(), "1\n0");
+        assert_eq!(out1, "1\n");
+        assert_eq!(out2, "1\n0");
+    }
+
+    #[test]
+    fn test_minimal_failing_constraints() {
+        let lines: Vec<String> = vec!["1 <= N <= 1000".into(), "1 <= M <= 1000".into(), "input order: N M".into()];
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        // Only N matters: the outputs differ for large values of it.
+        let options = RunnerOptions {
+            args_1: vec!["-c".into(), "read n m; [ $n -ge 900 ] && echo large || echo small".into()],
+            args_2: vec!["-c".into(), "read n m; echo small".into()],
+            ..Default::default()
+        };
+        let mut runner = Runner::new(data, PathBuf::from("/bin/sh"), PathBuf::from("/bin/sh"), options);
+        let outputs = loop {
+            if let RunnerResult::Fail(out1, out2) = runner.run_once().unwrap() {
+                break (out1, out2)
+            }
+        };
+
+        runner.shrink(outputs);
+        assert_eq!(runner.get_last_input(), "900 1");
+        assert_eq!(runner.minimal_failing_constraints(), vec!["1 <= N <= 1000".to_string()]);
+        assert_eq!(runner.get_last_input(), "900 1");
+    }
+
+    #[test]
+    fn test_shrink_multi_test() {
+        let lines: Vec<String> = vec![
+            "1 <= N <= 100".into(),
+            "0 <= A[N]# <= 500000".into(),
+            "input order: N A".into(),
+        ];
+        let data = FuzzData::parse("\n".into(), "\n".into(), lines).unwrap();
+
+        let options = RunnerOptions { multi_test: Some((1, 5)), ..Default::default() };
+        let mut runner = Runner::new(data, PathBuf::from("examples/example"), PathBuf::from("/bin/cat"), options);
+        let RunnerResult::Fail(out1, out2) = runner.run_once().unwrap() else {
+            panic!("Expected the run to fail")
+        };
+
+        runner.shrink((out1, out2));
+        assert_eq!(runner.get_last_input(), "1\n1\n0");
+    }
+}
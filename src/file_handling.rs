@@ -1,31 +1,384 @@
-use std::{fs::File, io::{BufRead, BufReader}, path::Path};
+use std::{fs::{read_dir, read_to_string, File, OpenOptions}, io::{self, BufRead, BufReader, ErrorKind, Lines, Write}, path::{Path, PathBuf}, process};
 
-use crate::{error::AppResult, parser::parser::FuzzData};
+use beanfuzz::{AppError, AppResult, FuzzData, ParseOptions, ParseTracer, Value};
 
-struct BufReaderLines {
-    buf_reader: BufReader<File>,
+/// The fuzz information path that means "read from stdin".
+pub const STDIN_PATH: &str = "-";
+
+/// The line between a sanity case's input and its expected output.
+const SANITY_OUTPUT_MARKER: &str = "----";
+
+/// The line between two sanity cases.
+const SANITY_CASE_SEPARATOR: &str = "~~~~";
+
+/// Iterator over the lines of a reader, for streaming them into the parser. Iteration stops at
+/// the first I/O error, which is kept so the caller can return it after parsing.
+struct BufReaderLines<R: BufRead> {
+    lines: Lines<R>,
+    error: Option<io::Error>,
+    /// How many lines were read so far.
+    count: u64,
+}
+
+impl<R: BufRead> BufReaderLines<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            error: None,
+            count: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for BufReaderLines<R> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None
+        }
+
+        match self.lines.next()? {
+            Ok(line) => {
+                self.count += 1;
+                Some(line)
+            }
+            Err(err) => {
+                self.error = Some(err);
+                None
+            }
+        }
+    }
+}
+
+/// Parse the data needed for the fuzzing from readers, whose lines are parsed one after the other
+/// as a single spec. The lines are parsed while being read, so they never have to fit in memory as
+/// a whole.
+///
+/// # Arguments
+/// - `input_separator`: input separator for the fuzzing data.
+/// - `input_separator`: output separator for the fuzzing data.
+/// - `readers`: the readers to read the lines from, along with the path each one reads
+/// - `debug_parse`: whether to trace every expression line to stderr, see `ParseTracer`
+/// - `default_max`: the maximum of expressions like `1 <= A`, see `ParseOptions::default_max`
+/// - `allow_empty_arrays`: whether array lengths may be 0, see `ParseOptions::allow_empty_arrays`
+/// - `bigint`: whether values may lie outside the `i64` range, see `ParseOptions::bigint`
+///
+/// # Returns
+/// An `AppResult` containing `FuzzData` when parse is successful, an `AppErr` otherwise. An I/O
+/// error takes precedence over parse errors, as those may be caused by the input being cut short.
+/// With multiple readers, a parse error at a line is wrapped in an `AppError::InSpecFile`.
+fn read_fuzz_data<R: BufRead>(input_separator: &str, output_separator: &str, readers: Vec<(PathBuf, R)>, debug_parse: bool, default_max: Option<Value>, allow_empty_arrays: bool, bigint: bool) -> AppResult<FuzzData> {
+    let (paths, mut sources): (Vec<PathBuf>, Vec<BufReaderLines<R>>) = readers.into_iter().map(|(path, reader)| (path, BufReaderLines::new(reader))).unzip();
+    let (mut trace_out, mut warning_out) = (io::stderr(), io::stderr());
+    let mut options = ParseOptions::default();
+    options.tracer = debug_parse.then(|| ParseTracer::new(&mut trace_out));
+    options.warnings = Some(&mut warning_out);
+    options.default_max = default_max;
+    options.allow_empty_arrays = allow_empty_arrays;
+    options.bigint = bigint;
+    let result = FuzzData::parse_with(input_separator.to_string(), output_separator.to_string(), sources.iter_mut().flatten(), options);
+
+    if let Some(err) = sources.iter_mut().find_map(|source| source.error.take()) {
+        return Err(err.into())
+    }
+    if sources.len() == 1 {
+        return result
+    }
+
+    // Find the file a line number falls into, and count the line from the start of that file.
+    result.map_err(|err| {
+        let (AppError::InvalidExpression(line, _) | AppError::InvalidSyntax(line, _) | AppError::ValueTooLarge(line, _) | AppError::RangeTooSmall { line, .. }) = &err else {
+            return err
+        };
+        let mut local_line = *line;
+        for (path, source) in paths.iter().zip(&sources) {
+            if local_line <= source.count {
+                let err = match err {
+                    AppError::InvalidExpression(_, expr) => AppError::InvalidExpression(local_line, expr),
+                    AppError::InvalidSyntax(_, str) => AppError::InvalidSyntax(local_line, str),
+                    AppError::ValueTooLarge(_, expr) => AppError::ValueTooLarge(local_line, expr),
+                    AppError::RangeTooSmall { expr, min, max, needed, .. } => AppError::RangeTooSmall { line: local_line, expr, min, max, needed },
+                    err => err
+                };
+                return AppError::InSpecFile(path.clone(), Box::new(err))
+            }
+            local_line -= source.count;
+        }
+        err
+    })
 }
 
-/// Try to open a file and get the data needed for the fuzzing.
+/// Try to open files and get the data needed for the fuzzing, parsing their lines one after the
+/// other as a single spec. A path of `-` reads from stdin instead.
 ///
 /// # Arguments
 /// - `input_separator`: input separator for the fuzzing data.
 /// - `input_separator`: output separator for the fuzzing data.
+/// - `path`: the spec file's path
+/// - `extra_paths`: the paths of files whose lines come after the spec file's, see `--spec`
+/// - `debug_parse`: whether to trace every expression line to stderr, see `ParseTracer`
+/// - `default_max`: the maximum of expressions like `1 <= A`, see `ParseOptions::default_max`
+/// - `allow_empty_arrays`: whether array lengths may be 0, see `ParseOptions::allow_empty_arrays`
+/// - `bigint`: whether values may lie outside the `i64` range, see `ParseOptions::bigint`
 ///
 /// # Returns
 /// An `AppResult` containing `FuzzData` when parse is successful, an `AppErr` otherwise.
-pub fn get_fuzz_data(input_separator: &str, output_separator: &str, path: &Path) -> AppResult<FuzzData> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut lines = Vec::new();
-
-    // We can't conveniently turn the Result's into Strings while returning any error encountered,
-    // so we loop instead.
-    // This should be okay as our fuzz info files aren't supposed to be long.
-    // Y'all shouldn't abuse this lil boy—coz we read the whole file to memory.
-    for line in reader.lines() {
-        lines.push(line?);
+#[allow(clippy::too_many_arguments)]
+pub fn get_fuzz_data(input_separator: &str, output_separator: &str, path: &Path, extra_paths: &[PathBuf], debug_parse: bool, default_max: Option<Value>, allow_empty_arrays: bool, bigint: bool) -> AppResult<FuzzData> {
+    let readers = std::iter::once(path).chain(extra_paths.iter().map(PathBuf::as_path))
+        .map(|path| {
+            let reader: Box<dyn BufRead> = if path == Path::new(STDIN_PATH) {
+                Box::new(io::stdin().lock())
+            } else {
+                Box::new(BufReader::new(File::open(path)?))
+            };
+            Ok((path.to_path_buf(), reader))
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    read_fuzz_data(input_separator, output_separator, readers, debug_parse, default_max, allow_empty_arrays, bigint)
+}
+
+/// Write a failing test's input and both outputs into a directory, so the input can be fed back
+/// into the executables later (e.g `./solution < fail_3.txt`). The files are named
+/// `fail_<test_no>.txt`, `fail_<test_no>.a.out` and `fail_<test_no>.b.out`. When the input file
+/// already exists (e.g another beanfuzz process dumps into the same directory), the process ID is
+/// appended to the name instead. No existing file is ever overwritten: when a name is still
+/// taken, this fails instead.
+///
+/// # Arguments
+/// - `dir`: the directory to write into. Must already exist.
+/// - `test_no`: the (1-indexed) test number
+/// - `input`: the input fed into both executables
+/// - `output_a`: executable A's output
+/// - `output_b`: executable B's output
+///
+/// # Returns
+/// An `AppResult` containing nothing when all files are written, an `AppErr` otherwise.
+pub fn dump_failure(dir: &Path, test_no: u64, input: &str, output_a: &str, output_b: &str) -> AppResult<()> {
+    let create_new = |name: String| OpenOptions::new().write(true).create_new(true).open(dir.join(name));
+    let mut stem = format!("fail_{}", test_no);
+    let mut input_file = match create_new(format!("{}.txt", stem)) {
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+            stem = format!("fail_{}_{}", test_no, process::id());
+            create_new(format!("{}.txt", stem))?
+        }
+        file => file?
     };
 
-    FuzzData::parse(input_separator.to_string(), output_separator.to_string(), lines)
+    input_file.write_all(input.as_bytes())?;
+    create_new(format!("{}.a.out", stem))?.write_all(output_a.as_bytes())?;
+    create_new(format!("{}.b.out", stem))?.write_all(output_b.as_bytes())?;
+
+    Ok(())
+}
+
+/// Find the highest index of the inputs already saved in a corpus directory, so a new run numbers
+/// its inputs after them instead of mixing with them. Files not named like `00000042.txt` are
+/// ignored.
+///
+/// # Arguments
+/// - `dir`: the corpus directory. Must already exist.
+///
+/// # Returns
+/// An `AppResult` containing the highest index, or 0 when the directory holds no inputs.
+pub fn corpus_last_index(dir: &Path) -> AppResult<u64> {
+    let mut last = 0;
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "txt") {
+            if let Some(index) = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse().ok()) {
+                last = last.max(index);
+            }
+        }
+    }
+    Ok(last)
+}
+
+/// Save a test's input into a corpus directory, to be replayed later with `read_corpus`. The file
+/// is named after its index, zero-padded so the files sort in the order they were ran
+/// (e.g `00000042.txt`). An existing file is never overwritten, see `corpus_last_index`.
+///
+/// # Arguments
+/// - `dir`: the corpus directory. Must already exist.
+/// - `index`: the (1-indexed) index of the input in the corpus
+/// - `input`: the input fed into both executables
+///
+/// # Returns
+/// An `AppResult` containing nothing, an `AppError` when the file can't be created or already exists.
+pub fn save_corpus_input(dir: &Path, index: u64, input: &str) -> AppResult<()> {
+    OpenOptions::new().write(true).create_new(true).open(dir.join(format!("{:08}.txt", index)))?
+        .write_all(input.as_bytes())?;
+    Ok(())
+}
+
+/// Read every input saved in a corpus directory, sorted by file name. Only `.txt` files are
+/// inputs, like the ones `save_corpus_input` writes: anything else (e.g the expected outputs of
+/// `--expected`) and subdirectories are skipped.
+///
+/// # Arguments
+/// - `dir`: the corpus directory
+///
+/// # Returns
+/// An `AppResult` containing the file paths and their inputs, an `AppError` when the directory
+/// or a file can't be read.
+pub fn read_corpus(dir: &Path) -> AppResult<Vec<(PathBuf, String)>> {
+    let mut paths = Vec::new();
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "txt") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths.into_iter().map(|path| {
+        let input = read_to_string(&path)?;
+        Ok((path, input))
+    }).collect()
+}
+
+/// Split the contents of a `--sanity-input` file into its cases. Each case is its input, a `----`
+/// line, then its expected output, and cases are separated by `~~~~` lines.
+///
+/// # Arguments
+/// - `contents`: the file's contents
+///
+/// # Returns
+/// An `AppResult` containing the input and expected output of every case, or an
+/// `AppError::InvalidArgument` when there are no cases or a case has no `----` line.
+fn parse_sanity_cases(contents: &str) -> AppResult<Vec<(String, String)>> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut cases = Vec::new();
+    for (i, case) in lines.split(|line| *line == SANITY_CASE_SEPARATOR).enumerate() {
+        let Some(marker) = case.iter().position(|line| *line == SANITY_OUTPUT_MARKER) else {
+            return Err(AppError::InvalidArgument(format!("sanity case #{} has no {:?} line before its expected output", i + 1, SANITY_OUTPUT_MARKER)))
+        };
+        cases.push((case[..marker].join("\n"), case[marker + 1..].join("\n")));
+    }
+    Ok(cases)
+}
+
+/// Read the cases of a `--sanity-input` file, see `parse_sanity_cases`.
+///
+/// # Arguments
+/// - `path`: the file's path
+///
+/// # Returns
+/// An `AppResult` containing the input and expected output of every case, an `AppErr` otherwise.
+pub fn read_sanity_cases(path: &Path) -> AppResult<Vec<(String, String)>> {
+    parse_sanity_cases(&read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::{create_dir_all, remove_dir_all, remove_file, write}, io::Cursor};
+
+    use beanfuzz::{Runner, RunnerOptions, RunnerResult};
+
+    use super::*;
+
+    #[test]
+    fn test_read_fuzz_data_in_memory() {
+        let spec = "1 <= N <= 10 # size\n\n1 <= A[N]# <= 100\ninput order: N A\n";
+        let result = read_fuzz_data(" ", " ", vec![(PathBuf::from("spec.fuzz"), Cursor::new(spec))], false, None, false, false).unwrap();
+
+        let lines = spec.lines().map(String::from);
+        assert_eq!(result, FuzzData::parse(" ".into(), " ".into(), lines).unwrap());
+    }
+
+    #[test]
+    fn test_read_multiple_specs() {
+        let shared = temp_dir().join(format!("beanfuzz-shared-{}.fuzz", process::id()));
+        let problem = temp_dir().join(format!("beanfuzz-problem-{}.fuzz", process::id()));
+        write(&shared, "define MAXN 10\n1 <= N <= MAXN\n1 <= A[N]# <= 100\n").unwrap();
+        write(&problem, "# Only the input order\ninput order: N A\n").unwrap();
+        let extra = [problem.clone()];
+
+        let result = get_fuzz_data(" ", " ", &shared, &extra, false, None, false, false).unwrap();
+        let lines = ["1 <= N <= 10", "1 <= A[N]# <= 100", "input order: N A"].map(String::from);
+        assert_eq!(result.list_vars(), FuzzData::parse(" ".into(), " ".into(), lines).unwrap().list_vars());
+
+        assert_eq!(get_fuzz_data(" ", " ", &problem, &extra, false, None, false, false).unwrap_err(), AppError::MultipleInputOrder);
+
+        write(&problem, "# Only the input order\ninput order: N A,,\n").unwrap();
+        let result = get_fuzz_data(" ", " ", &shared, &extra, false, None, false, false).unwrap_err();
+        assert_eq!(result, AppError::InSpecFile(problem.clone(), Box::new(AppError::InvalidSyntax(2, "input order: N A,,".into()))));
+
+        remove_file(&shared).unwrap();
+        remove_file(&problem).unwrap();
+    }
+
+    #[test]
+    fn test_corpus_round_trip() {
+        let dir = temp_dir().join(format!("beanfuzz-corpus-{}", process::id()));
+        create_dir_all(&dir).unwrap();
+        let lines = ["1 <= N <= 10", "1 <= A[N]# <= 100", "input order: N A"].map(String::from);
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("examples/example"), PathBuf::from("/bin/cat"), RunnerOptions::default());
+
+        // More than 9 tests, so sorting by name only works thanks to the padding.
+        let mut saved = Vec::new();
+        for test_no in 1..=12 {
+            let result = runner.run_once().unwrap();
+            save_corpus_input(&dir, test_no, runner.get_last_input()).unwrap();
+            saved.push((runner.get_last_input().to_string(), matches!(result, RunnerResult::Ok)));
+        }
+
+        let corpus = read_corpus(&dir).unwrap();
+        assert_eq!(corpus.iter().map(|(path, _)| path.file_name().unwrap().to_str().unwrap()).next(), Some("00000001.txt"));
+        for ((path, input), (saved_input, saved_ok)) in corpus.into_iter().zip(&saved) {
+            let result = runner.run_input(&path, input).unwrap();
+            assert_eq!(runner.get_last_input(), saved_input);
+            assert_eq!(matches!(result, RunnerResult::Ok), *saved_ok);
+        }
+
+        // Saving again continues after the existing inputs rather than replacing them.
+        assert_eq!(corpus_last_index(&dir).unwrap(), 12);
+        assert!(save_corpus_input(&dir, 12, "1\n1\n").is_err());
+        save_corpus_input(&dir, corpus_last_index(&dir).unwrap() + 1, "1\n1\n").unwrap();
+        assert_eq!(corpus_last_index(&dir).unwrap(), 13);
+        assert_eq!(read_to_string(dir.join("00000012.txt")).unwrap(), saved[11].0);
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_corpus_inputs_only() {
+        let dir = temp_dir().join(format!("beanfuzz-corpus-filter-{}", process::id()));
+        create_dir_all(&dir).unwrap();
+        save_corpus_input(&dir, 1, "1 2\n").unwrap();
+        write(dir.join("00000001.out"), "3\n").unwrap();
+        write(dir.join("README"), "notes").unwrap();
+        write(dir.join("00000001.txt~"), [0xff, 0xfe]).unwrap();
+
+        let corpus = read_corpus(&dir).unwrap();
+        assert_eq!(corpus, vec![(dir.join("00000001.txt"), "1 2\n".to_string())]);
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dump_failure_no_overwrite() {
+        let dir = temp_dir().join(format!("beanfuzz-dump-{}", process::id()));
+        create_dir_all(&dir).unwrap();
+
+        dump_failure(&dir, 3, "first", "a", "b").unwrap();
+        dump_failure(&dir, 3, "second", "a", "b").unwrap();
+        assert_eq!(read_to_string(dir.join("fail_3.txt")).unwrap(), "first");
+        assert_eq!(read_to_string(dir.join(format!("fail_3_{}.txt", process::id()))).unwrap(), "second");
+        // Both names are taken now, and neither gets clobbered.
+        assert!(dump_failure(&dir, 3, "third", "a", "b").is_err());
+        assert_eq!(read_to_string(dir.join(format!("fail_3_{}.txt", process::id()))).unwrap(), "second");
+
+        remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_sanity_cases() {
+        let cases = parse_sanity_cases("3\n1 2 3\n----\n6\n~~~~\n1\n5\n----\n5\n").unwrap();
+        assert_eq!(cases, [("3\n1 2 3".to_string(), "6".to_string()), ("1\n5".to_string(), "5".to_string())]);
+
+        assert!(matches!(parse_sanity_cases("3\n1 2 3\n~~~~\n1\n----\n1"), Err(AppError::InvalidArgument(..))));
+        assert!(matches!(parse_sanity_cases(""), Err(AppError::InvalidArgument(..))));
+    }
 }
@@ -0,0 +1,30 @@
+//! Beanfuzz as a library, for building custom harnesses around its input generator.
+//!
+//! A fuzz specification is parsed into a `FuzzData`, which a `Generator` turns into random inputs
+//! (or a `Runner` feeds into two executables and compares their outputs):
+//!
+//! ```
+//! use beanfuzz::{FuzzData, Generator, RunnerOptions};
+//!
+//! let lines = ["1 <= N <= 10", "1 <= A[N]# <= 100", "input order: N A"].map(String::from);
+//! let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+//! let mut options = RunnerOptions::default();
+//! options.seed = 42;
+//! let mut generator = Generator::new(data, &options);
+//!
+//! let input = generator.generate().unwrap().to_string();
+//! let n = *generator.get_state().get_var("N").unwrap();
+//! assert_eq!(input.split(' ').count() as i128, n + 1);
+//! ```
+//!
+//! Expressions can also be built in code with a `FuzzExprBuilder`, and turned into a `FuzzData`
+//! with `FuzzData::from_exprs`.
+
+mod error;
+mod exec;
+mod parser;
+
+pub use error::{AppError, AppResult, AppResultData, ExecTimings, OutputFormat, SizeStats, EXIT_HARNESS_ERROR, EXIT_MISMATCH, EXIT_USAGE_ERROR};
+pub use exec::{ExecutableId, Generator, InputMode, Reference, Runner, RunnerOptions, RunnerResult, SweepCase, VarsData, DEFAULT_MAX_INPUT_BYTES, DEFAULT_MAX_RETRIES, DEFAULT_RECORD_SEPARATOR};
+pub use parser::parser::{FuzzData, FuzzExpr, FuzzExprBuilder, ParseOptions, ParseTracer, VarKind, VarListing};
+pub use parser::tokenizer::{unescape, ComparisonType, ExprVariable, LenExpr, LenOp, Value, VarFlags, VariableGroup};
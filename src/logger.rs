@@ -0,0 +1,294 @@
+//! Writing the log file. The log is either free-form text meant to be read by people, or JSON
+//! lines (one object per failing, crashing or slow test) meant to be post-processed by scripts.
+
+use std::{fs::{File, OpenOptions}, io::Write, path::Path, process::ExitStatus, time::{SystemTime, UNIX_EPOCH}};
+
+use serde_json::json;
+
+use beanfuzz::{AppResult, AppResultData, ExecutableId, Runner, VarsData};
+
+use crate::cli::{CLIArgs, LogFormat};
+
+/// The variable state, input and outputs of a single test, captured from a `Runner`.
+pub(crate) struct TestSnapshot {
+    /// The variable stores, one for each test case block.
+    pub(crate) blocks: Vec<VarsData>,
+    pub(crate) input: String,
+    pub(crate) output_a: String,
+    pub(crate) output_b: String,
+    /// Where the outputs first differ, see `Runner::describe_mismatch`.
+    pub(crate) mismatch: String,
+    /// The spec's expressions the failure depends on, see `Runner::minimal_failing_constraints`.
+    /// Only found for minimized failures, empty otherwise.
+    pub(crate) constraints: Vec<String>,
+}
+
+impl TestSnapshot {
+    /// Capture the runner's last run along with both executables' outputs.
+    pub(crate) fn capture(runner: &Runner, output_a: String, output_b: String) -> Self {
+        Self {
+            blocks: runner.get_blocks().to_vec(),
+            input: runner.get_last_input().to_string(),
+            mismatch: runner.describe_mismatch(&output_a, &output_b),
+            output_a,
+            output_b,
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Format the variable state. With multiple test cases per input, every test case's state is
+    /// included, one per line.
+    fn format_state(&self) -> String {
+        if self.blocks.len() == 1 {
+            self.blocks[0].to_string()
+        } else {
+            self.blocks.iter().enumerate().map(|(i, block)| format!("\n  #{}: {}", i + 1, block)).collect()
+        }
+    }
+
+    /// The variable state as JSON. With multiple test cases per input, this is an array holding
+    /// every test case's state.
+    fn state_json(&self) -> serde_json::Value {
+        if self.blocks.len() == 1 {
+            self.blocks[0].to_json()
+        } else {
+            self.blocks.iter().map(VarsData::to_json).collect()
+        }
+    }
+}
+
+pub(crate) struct Logger {
+    file: File,
+    format: LogFormat,
+    /// Whether text logs show failures with both full outputs, not just their first mismatch.
+    /// JSONL logs always hold both outputs.
+    full_outputs: bool,
+}
+
+impl Logger {
+    /// Open the log file, truncating it unless `append` is set. With `full_outputs`, text logs show
+    /// failures with both full outputs.
+    pub(crate) fn open(path: &Path, append: bool, format: LogFormat, full_outputs: bool) -> AppResult<Self> {
+        let mut options = OpenOptions::new();
+        if append {
+            options.append(true);
+        } else {
+            options.write(true).truncate(true);
+        }
+
+        Ok(Self {
+            file: options.create(true).open(path)?,
+            format,
+            full_outputs,
+        })
+    }
+
+    fn write(&mut self, string: &str) -> AppResult<()> {
+        self.file.write_all(string.as_bytes())?;
+        Ok(())
+    }
+
+    /// Write the parameters of this run, preceded by a session separator when appending. Only
+    /// written to text logs.
+    pub(crate) fn session_start(&mut self, args: &CLIArgs) -> AppResult<()> {
+        if self.format != LogFormat::Text {
+            return Ok(())
+        }
+
+        if args.log_append {
+            let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+            self.write(&format!("\n=========\nNew session, started at {} (seconds since the Unix epoch)\n=========\n", started))?;
+        }
+        self.write(&format!("---------\nBeanfuzz ran with parameters: {}\n---------", args))
+    }
+
+    /// Log a failing test, along with its minimized version when the failure got shrunk.
+    ///
+    /// # Arguments
+    /// - `test_no`: the (1-indexed) test number
+    /// - `spec`: the spec file the test was generated from, when mixing multiple ones
+    /// - `failure`: the failing test
+    /// - `minimized`: the minimized failing test, if any
+    pub(crate) fn failure(&mut self, test_no: u64, spec: Option<&Path>, failure: &TestSnapshot, minimized: Option<&TestSnapshot>) -> AppResult<()> {
+        match self.format {
+            LogFormat::Text => {
+                let mut string = String::new();
+                string.push_str("\n------------------------\n");
+                string.push_str(&format!("Test #{} FAILED: {}.\n", test_no, failure.mismatch));
+                if let Some(spec) = spec {
+                    string.push_str(&format!("Spec: {}\n", spec.display()));
+                }
+                string.push_str(&format!("Hashmap: {}\n\n", failure.format_state()));
+                string.push_str(&format!("Input:\n~~~~\n{}\n~~~~\n", failure.input));
+                if self.full_outputs {
+                    string.push_str(&format!("Executable A output:\n~~~~\n{}\n~~~~\n", failure.output_a));
+                    string.push_str(&format!("Executable B output:\n~~~~\n{}\n~~~~\n", failure.output_b));
+                }
+                if let Some(minimized) = minimized {
+                    string.push_str(&format!("\nMinimized ({}) hashmap: {}\n\n", minimized.mismatch, minimized.format_state()));
+                    string.push_str(&format!("Minimized input:\n~~~~\n{}\n~~~~\n", minimized.input));
+                    if !minimized.constraints.is_empty() {
+                        string.push_str(&format!("Minimal failing constraints:\n~~~~\n{}\n~~~~\n", minimized.constraints.join("\n")));
+                    }
+                    if self.full_outputs {
+                        string.push_str(&format!("Executable A output:\n~~~~\n{}\n~~~~\n", minimized.output_a));
+                        string.push_str(&format!("Executable B output:\n~~~~\n{}\n~~~~\n", minimized.output_b));
+                    }
+                }
+                string.push_str("\n------------------------\n");
+                self.write(&string)
+            }
+            LogFormat::Jsonl => {
+                let mut object = json!({
+                    "type": "failure",
+                    "test": test_no,
+                    "variables": failure.state_json(),
+                    "input": failure.input,
+                    "output_a": failure.output_a,
+                    "output_b": failure.output_b,
+                    "mismatch": failure.mismatch,
+                });
+                if let Some(spec) = spec {
+                    object["spec"] = json!(spec.display().to_string());
+                }
+                if let Some(minimized) = minimized {
+                    object["minimized"] = json!({
+                        "variables": minimized.state_json(),
+                        "input": minimized.input,
+                        "output_a": minimized.output_a,
+                        "output_b": minimized.output_b,
+                        "mismatch": minimized.mismatch,
+                        "constraints": minimized.constraints,
+                    });
+                }
+                self.write(&format!("{}\n", object))
+            }
+        }
+    }
+
+    /// Log a test where an executable crashed.
+    ///
+    /// # Arguments
+    /// - `test_no`: the (1-indexed) test number
+    /// - `spec`: the spec file the test was generated from, when mixing multiple ones
+    /// - `runner`: the runner that ran the test
+    /// - `which`: the executable that crashed
+    /// - `status`: the crashed executable's exit status
+    pub(crate) fn crash(&mut self, test_no: u64, spec: Option<&Path>, runner: &Runner, which: ExecutableId, status: ExitStatus) -> AppResult<()> {
+        let snapshot = TestSnapshot::capture(runner, String::new(), String::new());
+        match self.format {
+            LogFormat::Text => {
+                let mut string = String::new();
+                string.push_str("\n------------------------\n");
+                string.push_str(&format!("Test #{} CRASHED.\n", test_no));
+                if let Some(spec) = spec {
+                    string.push_str(&format!("Spec: {}\n", spec.display()));
+                }
+                string.push_str(&format!("Executable {} exited with {}\n", which, status));
+                string.push_str(&format!("Hashmap: {}\n\n", snapshot.format_state()));
+                string.push_str(&format!("Input:\n~~~~\n{}\n~~~~\n", snapshot.input));
+                string.push_str("\n------------------------\n");
+                self.write(&string)
+            }
+            LogFormat::Jsonl => {
+                let mut object = json!({
+                    "type": "crash",
+                    "test": test_no,
+                    "variables": snapshot.state_json(),
+                    "input": snapshot.input,
+                    "executable": which.to_string(),
+                    "status": status.to_string(),
+                });
+                if let Some(spec) = spec {
+                    object["spec"] = json!(spec.display().to_string());
+                }
+                self.write(&format!("{}\n", object))
+            }
+        }
+    }
+
+    /// Log a test where one executable ran much longer than the other, see `--tle-ratio`.
+    ///
+    /// # Arguments
+    /// - `test_no`: the (1-indexed) test number
+    /// - `spec`: the spec file the test was generated from, when mixing multiple ones
+    /// - `runner`: the runner that ran the test
+    /// - `which`: the slow executable
+    /// - `ratio`: how many times longer the slow executable ran
+    pub(crate) fn slow(&mut self, test_no: u64, spec: Option<&Path>, runner: &Runner, which: ExecutableId, ratio: f64) -> AppResult<()> {
+        let snapshot = TestSnapshot::capture(runner, String::new(), String::new());
+        let (duration_a, duration_b) = runner.get_last_durations();
+        match self.format {
+            LogFormat::Text => {
+                let mut string = String::new();
+                string.push_str("\n------------------------\n");
+                string.push_str(&format!("Test #{} SLOW.\n", test_no));
+                if let Some(spec) = spec {
+                    string.push_str(&format!("Spec: {}\n", spec.display()));
+                }
+                string.push_str(&format!("Executable {} ran {:.1}x longer (A: {:.2?}, B: {:.2?})\n", which, ratio, duration_a, duration_b));
+                string.push_str(&format!("Hashmap: {}\n\n", snapshot.format_state()));
+                string.push_str(&format!("Input:\n~~~~\n{}\n~~~~\n", snapshot.input));
+                string.push_str("\n------------------------\n");
+                self.write(&string)
+            }
+            LogFormat::Jsonl => {
+                let mut object = json!({
+                    "type": "slow",
+                    "test": test_no,
+                    "variables": snapshot.state_json(),
+                    "input": snapshot.input,
+                    "executable": which.to_string(),
+                    "time_a_ms": duration_a.as_secs_f64() * 1000.0,
+                    "time_b_ms": duration_b.as_secs_f64() * 1000.0,
+                });
+                if let Some(spec) = spec {
+                    object["spec"] = json!(spec.display().to_string());
+                }
+                self.write(&format!("{}\n", object))
+            }
+        }
+    }
+
+    /// Write the summary of the whole run. Only written to text logs, so JSON lines logs only
+    /// hold per-test objects.
+    pub(crate) fn summary(&mut self, result: &AppResultData) -> AppResult<()> {
+        match self.format {
+            LogFormat::Text => self.write(&result.to_string()),
+            LogFormat::Jsonl => Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::{read_to_string, remove_file}, path::PathBuf, process};
+
+    use beanfuzz::{FuzzData, RunnerOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_failure_logs_input() {
+        let lines = ["1 <= N <= 10", "1 <= A[N]# <= 100", "input order: N A"].map(String::from);
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from("examples/example"), RunnerOptions::default());
+        runner.run_once().unwrap();
+        let input = runner.get_last_input().to_string();
+        let failure = TestSnapshot::capture(&runner, "1".into(), "2".into());
+
+        let path = temp_dir().join(format!("beanfuzz-log-input-{}.txt", process::id()));
+        let mut logger = Logger::open(&path, false, LogFormat::Text, false).unwrap();
+        logger.failure(1, None, &failure, None).unwrap();
+        assert!(read_to_string(&path).unwrap().contains(&format!("Input:\n~~~~\n{}\n~~~~\n", input)));
+
+        let mut logger = Logger::open(&path, false, LogFormat::Jsonl, false).unwrap();
+        logger.failure(1, None, &failure, None).unwrap();
+        let object: serde_json::Value = serde_json::from_str(&read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(object["input"], json!(input));
+        // Without --log-full-outputs too, as JSONL logs are read by tools rather than people.
+        assert_eq!((&object["output_a"], &object["output_b"]), (&json!("1"), &json!("2")));
+
+        remove_file(&path).unwrap();
+    }
+}
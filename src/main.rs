@@ -1,67 +1,398 @@
 
 mod file_handling;
-mod parser;
-mod error;
 mod cli;
-mod exec;
+mod logger;
+mod reporter;
 
-use std::{fs::{OpenOptions}, io::Write};
+use std::{fs::create_dir_all, io::{self, ErrorKind, Write}, path::{Path, PathBuf}, process::{ExitCode, Termination}, time::Instant};
 
-use error::{AppResult, AppResultData};
-use exec::Runner;
-use file_handling::get_fuzz_data;
+use beanfuzz::{AppError, AppResult, AppResultData, Generator, Reference, Runner, RunnerOptions, RunnerResult, VarListing};
+use cli::CLIArgs;
+use file_handling::{corpus_last_index, dump_failure, get_fuzz_data, read_corpus, read_sanity_cases, save_corpus_input};
+use logger::{Logger, TestSnapshot};
+use reporter::Reporter;
 
-fn main() -> AppResult<AppResultData> {
-    let args = cli::CLIArgs::checked_parse()?;
-    let data = get_fuzz_data(&args.input_sep, &args.output_sep, &args.fuzz_data_filepath)?;
+/// Count and report the result of a single test: report it, dump and log failures and crashes.
+///
+/// # Arguments
+/// - `args`: the command line arguments
+/// - `spec_paths`: the paths of the specs the inputs are generated from, see `CLIArgs::spec_mix`
+/// - `test_no`: the (1-indexed) test number
+/// - `result`: the test's result
+/// - `runner`: the runner that ran the test
+/// - `logger`: the log file's logger, if logging is enabled
+/// - `reporter`: the terminal output
+/// - `fuzz_result`: the counters to update
+/// - `corpus_offset`: how many inputs the `--corpus-out` directory held before this run
+#[allow(clippy::too_many_arguments)]
+fn handle_result(args: &CLIArgs, spec_paths: &[PathBuf], test_no: u64, result: AppResult<RunnerResult>, runner: &mut Runner, logger: &mut Option<Logger>, reporter: &mut Reporter, fuzz_result: &mut AppResultData, corpus_offset: u64) -> AppResult<()> {
+    // Which spec a failure or crash came from is only worth logging when mixing multiple ones.
+    let spec = (spec_paths.len() > 1).then(|| spec_paths[runner.current_spec()].as_path());
 
-    let mut log_file = if let Some(path) = &args.log_file {
-        Some(OpenOptions::new().create(true).write(true).truncate(true).open(path)?)
-    } else {
-        None
-    };
-
-    if let Some(log_file) = &mut log_file {
-        log_file.write(&format!("---------\nBeanfuzz ran with parameters: {}\n---------", &args).into_bytes())?;
+    if result.is_ok() {
+        let (duration_a, duration_b) = runner.get_last_durations();
+        fuzz_result.record_durations(test_no, duration_a, duration_b);
+        // Logged before the outcome, as shrinking a failure replaces the runner's last input.
+        if let Some((which, ratio)) = fuzz_result.record_slow(test_no, duration_a, duration_b) {
+            reporter.slow(&format!("Test #{} is slow: executable {} ran {:.1}x longer than the other", test_no, which, ratio));
+            if let Some(logger) = logger {
+                logger.slow(test_no, spec, runner, which, ratio)?;
+            }
+        }
+        fuzz_result.record_sizes(runner.get_last_sizes());
+        if let Some(dir) = &args.corpus_out {
+            save_corpus_input(dir, corpus_offset + test_no, runner.get_last_input())?;
+        }
     }
 
-    let mut runner = Runner::new(data, args.executable_a, args.executable_b);
-    let mut fuzz_result = AppResultData::new(args.log_file);
-
-    for i in 0..args.how_many_times {
-        let result = runner.run_once();
-        match result {
-            Ok(result) => match result {
-                exec::RunnerResult::Ok => {
-                    fuzz_result.successful_tests += 1;
-                    println!("Test #{} succeeded", i+1);
+    match result {
+        Ok(result) => match result {
+            RunnerResult::Ok => {
+                fuzz_result.successful_tests += 1;
+                reporter.success(test_no);
+            }
+            RunnerResult::Fail(out1, out2) => {
+                fuzz_result.failed_tests += 1;
+                fuzz_result.failed_test_indices.push(test_no);
+                if let Some(dir) = &args.dump_failures {
+                    dump_failure(dir, test_no, runner.get_last_input(), &out1, &out2)?;
                 }
-                exec::RunnerResult::Fail(out1, out2) => {
-                    fuzz_result.failed_tests += 1;
-                    if let Some(log_file) = &mut log_file {
-                        println!("Test #{} failed! See log file for details.", i+1);
-                        log_file.write(b"\n------------------------\n")?;
-                        log_file.write(&format!("Test #{} FAILED.\n", i + 1).into_bytes())?;
-                        log_file.write(&format!("Hashmap: {:?}\n\n", runner.get_state()).into_bytes())?;
-                        log_file.write(&format!("Executable A output:\n~~~~\n{}\n~~~~\n", out1).into_bytes())?;
-                        log_file.write(&format!("Executable B output:\n~~~~\n{}\n~~~~\n", out2).into_bytes())?;
-                        log_file.write(b"\n------------------------\n")?;
+                // Failures logged so far, not counting this one.
+                let logged = fuzz_result.failed_tests - 1 - fuzz_result.unlogged_failures;
+                if logger.is_some() && args.fail_sample.is_some_and(|sample| logged >= sample) {
+                    fuzz_result.unlogged_failures += 1;
+                    reporter.problem(test_no, "output mismatch", &format!("Test #{} failed! Past --fail-sample, so it isn't logged.", test_no));
+                } else if logger.is_none() && !args.shrink {
+                    reporter.problem(test_no, "output mismatch", &format!("Test #{} failed! Enable logging to see output.", test_no));
+                } else {
+                    let failure = TestSnapshot::capture(runner, out1, out2);
+                    let minimized = if args.shrink {
+                        let (out1, out2) = runner.shrink((failure.output_a.clone(), failure.output_b.clone()));
+                        let mut minimized = TestSnapshot::capture(runner, out1, out2);
+                        minimized.constraints = runner.minimal_failing_constraints();
+                        Some(minimized)
                     } else {
-                        println!("Test #{} failed! Enable logging to see output.", i+1);
+                        None
+                    };
+                    if let Some(logger) = logger {
+                        reporter.problem(test_no, "output mismatch", &format!("Test #{} failed! See log file for details.", test_no));
+                        logger.failure(test_no, spec, &failure, minimized.as_ref())?;
+                    } else if let Some(minimized) = minimized {
+                        // Without a log file, the minimized input would be lost otherwise.
+                        reporter.problem(test_no, "output mismatch", &format!("Test #{} failed! Minimized input:\n{}", test_no, minimized.input));
                     }
                 }
             }
-            Err(err) => {
-                println!("An error occurred with test #{}: {:?}, skipping..", i+1, err);
-                fuzz_result.error_tests += 1; 
+            RunnerResult::Crashed { which, status } => {
+                fuzz_result.crashed_tests += 1;
+                reporter.problem(test_no, "crash", &format!("Test #{} crashed: executable {} exited with {}", test_no, which, status));
+                if let Some(logger) = logger {
+                    logger.crash(test_no, spec, runner, which, status)?;
+                }
+            }
+        }
+        Err(err) => {
+            reporter.error(test_no, &format!("An error occurred with test #{}: {}, skipping..", test_no, err));
+            fuzz_result.record_error(&err);
+        }
+    }
+    reporter.update(fuzz_result);
+    Ok(())
+}
+
+/// Run the random tests: `-n` of them, or as many as start before the deadline when given.
+///
+/// # Arguments
+/// - `args`: the command line arguments
+/// - `offset`: how many tests ran before (e.g sweep cases), for numbering
+/// - `deadline`: when to stop starting tests, if running until a time budget elapses
+/// - `spec_paths`, `runner`, `logger`, `reporter`, `fuzz_result`, `corpus_offset`: see
+///   `handle_result`
+#[allow(clippy::too_many_arguments)]
+fn run_random_tests(args: &CLIArgs, spec_paths: &[PathBuf], offset: u64, deadline: Option<Instant>, runner: &mut Runner, logger: &mut Option<Logger>, reporter: &mut Reporter, fuzz_result: &mut AppResultData, corpus_offset: u64) -> AppResult<()> {
+    let mut i = 0;
+    while deadline.map_or(i < args.how_many_times, |deadline| Instant::now() < deadline) {
+        let result = runner.run_once();
+        handle_result(args, spec_paths, offset + i + 1, result, runner, logger, reporter, fuzz_result, corpus_offset)?;
+        i += 1;
+    }
+    Ok(())
+}
+
+/// Run every case of a `--sanity-input` file through the runner's second executable, so a
+/// reference that is wrong itself is caught before any fuzzing.
+///
+/// # Arguments
+/// - `runner`: the runner whose second executable to check
+/// - `path`: the sanity input file
+///
+/// # Returns
+/// An `AppResult` containing nothing when every case passes, or an `AppError::SanityCheckFailed`
+/// for the first case that doesn't.
+fn sanity_check(runner: &Runner, path: &Path) -> AppResult<()> {
+    for (i, (input, expected)) in read_sanity_cases(path)?.iter().enumerate() {
+        if let Some(msg) = runner.check_reference(input, expected)? {
+            return Err(AppError::SanityCheckFailed(i as u64 + 1, msg))
+        }
+    }
+    Ok(())
+}
+
+/// Format a `--list-vars` listing as a table, one declared name per row.
+///
+/// # Arguments
+/// - `listings`: the declared names, see `FuzzData::list_vars`
+///
+/// # Returns
+/// The table, with a header row and columns padded to their widest cell.
+fn var_table(listings: &[VarListing]) -> String {
+    let mut rows = vec![["Name", "Kind", "Min", "Max", "Length"].map(String::from)];
+    for listing in listings {
+        let (min, max) = listing.bounds.map_or((String::new(), String::new()), |(min, max)| (min.to_string(), max.to_string()));
+        rows.push([listing.name.clone(), listing.kind.to_string(), min, max, listing.length().unwrap_or_default()]);
+    }
+
+    let widths: Vec<usize> = (0..5).map(|col| rows.iter().map(|row| row[col].chars().count()).max().unwrap_or(0)).collect();
+    rows.iter()
+        .map(|row| row.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect::<Vec<_>>().join("  ").trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse the arguments and the specs, then fuzz.
+///
+/// # Returns
+/// An `AppResult` containing the result of the run, or the error that stopped it.
+fn run() -> AppResult<AppResultData> {
+    let args = CLIArgs::checked_parse()?;
+    let (spec_paths, weights): (Vec<PathBuf>, Vec<u32>) = args.spec_mix()?.into_iter().unzip();
+    let specs = spec_paths.iter().zip(weights)
+        .map(|(path, weight)| Ok((get_fuzz_data(&args.input_sep, &args.output_sep, path, &args.spec, args.debug_parse, args.default_max, args.allow_empty_arrays, args.bigint)?, weight)))
+        .collect::<AppResult<Vec<_>>>()?;
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let (interpreter_1, interpreter_2) = args.executable_interpreters()?;
+    let (args_1, args_2) = args.executable_args()?;
+    let (env_1, env_2) = args.executable_envs()?;
+    if cfg!(not(unix)) && args.mem_limit.is_some() {
+        eprintln!("Warning: --mem-limit is only supported on Unix, so the executables run without a memory limit");
+    }
+    let mut options = RunnerOptions::default();
+    options.multi_test = args.multi_test_range();
+    options.blocks = args.blocks_range();
+    options.seed = seed;
+    options.args_1 = args_1;
+    options.args_2 = args_2;
+    options.env_1 = env_1;
+    options.env_2 = env_2;
+    options.cwd = args.cwd.clone();
+    options.mem_limit = args.mem_limit.map(|limit| limit.saturating_mul(1024 * 1024));
+    options.judge = args.interactive.clone();
+    options.interpreter_1 = interpreter_1;
+    options.interpreter_2 = interpreter_2;
+    options.alphabet = args.alphabet.chars().collect();
+    options.string_distinct = args.string_distinct;
+    options.edge_bias = args.edge_bias;
+    options.input_mode = args.input_mode;
+    options.output_record_separator = args.output_record_sep.clone();
+    options.input_record_separator = args.input_record_sep.clone();
+    options.unordered = args.unordered;
+    options.ignore_case = args.ignore_case;
+    options.max_input_bytes = args.max_input_bytes;
+    options.independent_vars = args.independent_vars;
+    options.require_output = args.require_output;
+    options.max_retries = args.max_retries;
+    options.allow_empty_arrays = args.allow_empty_arrays;
+
+    if args.list_vars {
+        let mut fuzz_result = AppResultData::new(None, seed, args.format);
+        fuzz_result.dry_run = true;
+        for (path, (data, _)) in spec_paths.iter().zip(&specs) {
+            if specs.len() > 1 {
+                println!("{}:", path.display());
+            }
+            println!("{}", var_table(&data.list_vars()));
+        }
+        return Ok(fuzz_result)
+    }
+
+    if args.dry_run {
+        let mut generator = Generator::with_mix(specs, &options);
+        let mut fuzz_result = AppResultData::new(None, seed, args.format);
+        fuzz_result.dry_run = true;
+        let mut stdout = io::stdout().lock();
+        for i in 0..args.how_many_times {
+            let input = generator.generate()?;
+            let written = if i > 0 { writeln!(stdout, "~~~~") } else { Ok(()) }.and_then(|_| writeln!(stdout, "{}", input));
+            match written {
+                // The reader is gone (e.g piped into `head`), so nobody wants the other inputs.
+                Err(err) if err.kind() == ErrorKind::BrokenPipe => break,
+                written => written?
             }
         }
+        return Ok(fuzz_result)
+    }
+
+    let mut logger = match &args.log_file {
+        Some(path) => Some(Logger::open(path, args.log_append, args.log_format, args.log_full_outputs)?),
+        None => None
+    };
+
+    if let Some(logger) = &mut logger {
+        logger.session_start(&args)?;
+    }
+
+    for dir in [&args.dump_failures, &args.corpus_out].into_iter().flatten() {
+        create_dir_all(dir)?;
+    }
+    let corpus_offset = match &args.corpus_out {
+        Some(dir) => corpus_last_index(dir)?,
+        None => 0
+    };
+
+    let executable_a = args.executable_a.clone().expect("Executables are required outside of dry runs");
+    let reference = match (&args.expected, &args.executable_b) {
+        (Some(dir), _) => Reference::ExpectedOutputs(dir.clone()),
+        (None, Some(executable_b)) => Reference::Executable(executable_b.clone()),
+        (None, None) if args.self_check => Reference::SelfCheck,
+        (None, None) => unreachable!("The second executable is required outside of dry runs without --expected or --self-check")
+    };
+    let mut runner = Runner::with_mix(specs, executable_a, reference, options);
+    if let Some(path) = &args.sanity_input {
+        sanity_check(&runner, path)?;
+    }
+    let mut fuzz_result = AppResultData::new(args.log_file.clone(), seed, args.format);
+    fuzz_result.error_is_failure = args.error_is_failure;
+    fuzz_result.warmup = args.warmup;
+    fuzz_result.tle_ratio = args.tle_ratio;
+
+    if let Some(dir) = &args.corpus_in {
+        let corpus = read_corpus(dir)?;
+        let mut reporter = Reporter::new(&args, Some(corpus.len() as u64));
+        for (i, (path, input)) in corpus.into_iter().enumerate() {
+            let result = runner.run_input(&path, input);
+            handle_result(&args, &spec_paths, i as u64 + 1, result, &mut runner, &mut logger, &mut reporter, &mut fuzz_result, corpus_offset)?;
+        }
+        reporter.finish();
+    } else {
+        // The budget covers the sweep cases too.
+        let deadline = args.time_budget()?.map(|budget| Instant::now() + budget);
+        let sweep_cases = if args.sweep { runner.sweep_cases() } else { Vec::new() };
+        let offset = sweep_cases.len() as u64;
+        let mut reporter = Reporter::new(&args, deadline.is_none().then_some(offset + args.how_many_times));
+        for (i, case) in sweep_cases.iter().enumerate() {
+            let result = runner.run_sweep(*case);
+            handle_result(&args, &spec_paths, i as u64 + 1, result, &mut runner, &mut logger, &mut reporter, &mut fuzz_result, corpus_offset)?;
+        }
 
+        run_random_tests(&args, &spec_paths, offset, deadline, &mut runner, &mut logger, &mut reporter, &mut fuzz_result, corpus_offset)?;
+        reporter.finish();
     }
 
-    if let Some(log_file) = &mut log_file {
-        log_file.write(&format!("{}", &fuzz_result).into_bytes())?;
+    if let Some(logger) = &mut logger {
+        logger.summary(&fuzz_result)?;
     }
 
     Ok(fuzz_result)
 }
+
+/// Exits with 0 when every test passes, `EXIT_MISMATCH` when a test fails or crashes,
+/// `EXIT_HARNESS_ERROR` when the run couldn't go on and `EXIT_USAGE_ERROR` when its arguments,
+/// config file or spec are invalid, see `beanfuzz::AppError`.
+fn main() -> ExitCode {
+    match run() {
+        Ok(result) => result.report(),
+        Err(err) => err.report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::{read_to_string, remove_file, write}, path::PathBuf, process};
+
+    use beanfuzz::FuzzData;
+
+    use super::*;
+
+    /// Run the random tests of a command line the way `run` does, logging into a temporary file.
+    /// Executable A is `cat`, and executable B is `sh` running a script on the input.
+    ///
+    /// # Arguments
+    /// - `name`: the test's name, to keep its log file apart from the other tests'
+    /// - `script_b`: the shell script executable B runs
+    /// - `extra_args`: the command line arguments after the executables
+    ///
+    /// # Returns
+    /// The result of the run, the contents of its log and the deadline it ran until, if any.
+    fn run_against_cat(name: &str, script_b: &str, extra_args: &[&str]) -> (AppResultData, String, Option<Instant>) {
+        let path = temp_dir().join(format!("beanfuzz-{}-{}.txt", name, process::id()));
+        let (args_b, log_file) = (format!("-c '{}'", script_b), path.to_str().unwrap().to_string());
+        let cli = ["beanfuzz", "examples/sample.fuzz", "/bin/cat", "/bin/sh", "--args-b", &args_b, "-f", &log_file, "--quiet"];
+        let args = CLIArgs::checked_parse_from(cli.iter().chain(extra_args)).unwrap();
+        let data = get_fuzz_data(&args.input_sep, &args.output_sep, &args.fuzz_data_filepath, &args.spec, args.debug_parse, args.default_max, args.allow_empty_arrays, args.bigint).unwrap();
+        let mut options = RunnerOptions::default();
+        options.args_2 = args.executable_args().unwrap().1;
+        let mut runner = Runner::new(data, args.executable_a.clone().unwrap(), args.executable_b.clone().unwrap(), options);
+        let mut logger = Some(Logger::open(&path, args.log_append, args.log_format, args.log_full_outputs).unwrap());
+        let deadline = args.time_budget().unwrap().map(|budget| Instant::now() + budget);
+        let mut reporter = Reporter::new(&args, deadline.is_none().then_some(args.how_many_times));
+        let mut fuzz_result = AppResultData::new(args.log_file.clone(), 0, args.format);
+        fuzz_result.tle_ratio = args.tle_ratio;
+
+        run_random_tests(&args, &[], 0, deadline, &mut runner, &mut logger, &mut reporter, &mut fuzz_result, 0).unwrap();
+        drop(logger);
+        let log = read_to_string(&path).unwrap();
+        remove_file(&path).unwrap();
+        (fuzz_result, log, deadline)
+    }
+
+    #[test]
+    fn test_run_until() {
+        let (fuzz_result, _, deadline) = run_against_cat("run-until", "cat", &["--until", "1ms"]);
+        assert!(Instant::now() >= deadline.unwrap());
+        // Nothing but passing tests, however many fit in the budget, and still a full summary.
+        assert_eq!((fuzz_result.failed_tests, fuzz_result.crashed_tests, fuzz_result.error_tests), (0, 0, 0));
+        let summary = fuzz_result.to_string();
+        assert!(summary.contains(&format!("Ok      : {}", fuzz_result.successful_tests)), "{}", summary);
+    }
+
+    #[test]
+    fn test_fail_sample() {
+        // Every test fails.
+        let (fuzz_result, log, _) = run_against_cat("fail-sample", "cat > /dev/null; echo different", &["-n", "5", "--fail-sample", "2"]);
+        assert_eq!(log.matches("FAILED").count(), 2, "{}", log);
+        assert_eq!((fuzz_result.failed_tests, fuzz_result.unlogged_failures), (5, 3));
+        assert!(fuzz_result.to_string().contains("Failed  : 5 (3 not logged)"));
+    }
+
+    #[test]
+    fn test_tle_ratio() {
+        // Same output, but B sleeps first.
+        let (fuzz_result, log, _) = run_against_cat("tle-ratio", "sleep 0.1; cat", &["-n", "3", "--tle-ratio", "3"]);
+        // Slow tests still pass.
+        assert_eq!((fuzz_result.successful_tests, fuzz_result.slow_tests), (3, 3));
+        assert_eq!(log.matches("SLOW").count(), 3, "{}", log);
+        assert!(log.contains("Executable B ran"), "{}", log);
+        assert!(fuzz_result.to_string().contains("Slow    : 3"));
+
+        for ratio in ["1", "0.5", "inf"] {
+            let result = CLIArgs::checked_parse_from(["beanfuzz", "examples/sample.fuzz", "--dry-run", "--tle-ratio", ratio]);
+            assert!(matches!(result, Err(AppError::InvalidArgument(_))), "{}", ratio);
+        }
+    }
+
+    #[test]
+    fn test_sanity_check() {
+        let lines = ["1 <= N <= 10", "input order: N"].map(String::from);
+        let data = FuzzData::parse(" ".into(), " ".into(), lines).unwrap();
+        let runner = Runner::new(data, PathBuf::from("examples/example"), PathBuf::from("/bin/cat"), RunnerOptions::default());
+        let path = temp_dir().join(format!("beanfuzz-sanity-{}.txt", process::id()));
+
+        write(&path, "1 2\n----\n1 2\n~~~~\n3\n----\n3\n").unwrap();
+        assert_eq!(sanity_check(&runner, &path), Ok(()));
+
+        write(&path, "1 2\n----\n1 2\n~~~~\n3\n----\n4\n").unwrap();
+        assert!(matches!(sanity_check(&runner, &path), Err(AppError::SanityCheckFailed(2, _))));
+
+        remove_file(&path).unwrap();
+    }
+}
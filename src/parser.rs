@@ -1,2 +1,3 @@
 pub mod tokenizer;
+#[allow(clippy::module_inception)]
 pub mod parser;
@@ -1,17 +1,18 @@
-use std::{collections::VecDeque, fmt::Display, iter::IntoIterator};
+use std::{collections::{HashMap, HashSet, VecDeque}, fmt::Display, io::Write, iter::IntoIterator};
 
 use crate::error::{AppError, AppResult};
 
-use super::tokenizer::{tokenize_expr_line, ComparisonType, ExprVariable, Token, VariableGroup};
+use super::tokenizer::{string_to_variable, tokenize_choice_line, tokenize_define, tokenize_exclusion, tokenize_expr_line, tokenize_interval_line, tokenize_string_decl, tokenize_tree_decl, unescape, uses_lte_alias, ComparisonType, ExprVariable, LenExpr, Token, Value, VarFlags, VariableGroup};
 
-#[derive(Default, Debug, PartialEq)]
-/// A single expression for the fuzzer. An example of an expression is `0 <= A <= 1000`.
-pub(crate) struct FuzzExpr {
+#[derive(Default, Debug, PartialEq, Clone)]
+/// A single expression for the fuzzer. An example of an expression is `0 <= A <= 1000`. Parsed
+/// from a spec, or built with a `FuzzExprBuilder`.
+pub struct FuzzExpr {
     /// The constant minimum of the expression.
-    pub(crate) const_min: i64,
+    pub(crate) const_min: Value,
 
     /// The constant maximum of the expression.
-    pub(crate) const_max: i64,
+    pub(crate) const_max: Value,
 
     /// Variable groups declared inside the expression. For example, `0 <= B <= C,D <= 1000` will
     /// give `vec[(B), (C, D)]`.
@@ -22,16 +23,26 @@ pub(crate) struct FuzzExpr {
     /// (we're talking inclusive range).
     pub(crate) comparisons: Vec<ComparisonType>,
 
-    /// When the expression contains an array, we store it in a separate vector to evaluate later.
-    /// This is because the array may contain another variable for the length, and since I don't
-    /// want to bother with dependency resolving, this is good enough. However, cases with single
-    /// expression like `0 <= A[N]# <= N <= 2000` will still not be allowed (as the `N` is declared
-    /// _after_ `A[N]#`).
-    pub(crate) contains_array: bool,
-
     /// How many less than's are in the expression. This is used to compute ranges and other stuff.
     pub(crate) less_than_count: u64,
 
+    /// The smallest value each variable group may take, i.e the constant minimum plus the less
+    /// than's up to the group. Computed once here instead of on every generation.
+    pub(crate) group_mins: Vec<Value>,
+
+    /// The largest value each variable group may take, i.e the constant maximum minus the less
+    /// than's after the group. Computed once here instead of on every generation.
+    pub(crate) group_maxes: Vec<Value>,
+
+    /// Whether the expression ends with a variable group instead of a constant (an open chain,
+    /// e.g `1 <= A <= B`). Its constant maximum is then the largest value the last group may take
+    /// in the other expressions, see `resolve_open_maxes`.
+    pub(crate) open_max: bool,
+
+    /// The (1-indexed) line of the spec the expression was parsed from, reported in errors. 0
+    /// when the expression wasn't parsed from a spec.
+    pub(crate) line: u64,
+
     /// The string representation of the expression. Used for debugging.
     pub(crate) repr: String
 
@@ -44,37 +55,146 @@ impl Display for FuzzExpr {
     
 }
 
-/// Loop through given slice and check if any of its item is an array variable. This is an O(n)
-/// operation.
+/// Count how many `LessThan` comparisons are found in a slice.
 ///
 /// # Arguments
-/// - `slice`: the slice containing `ExprVariable`s.
+/// - `slice`: the slice containing `ComparisonType`s.
 ///
 /// # Returns
-/// A boolean indicating the existence of an array variable inside the slice.
-fn expr_var_arr_contains_arr_var(slice: &[ExprVariable]) -> bool {
-    for item in slice {
-        if let ExprVariable::Array(_, _) = item {
-            return true
-        }
-    }
-    false
+/// The count of `LessThan` comparison enums.
+fn count_less_thans(slice: &[ComparisonType]) -> u64 {
+    slice.iter().filter(|x| x == &&ComparisonType::LessThan).count() as u64
+}
+
+/// Set the constant maximum of an expression, along with everything computed from it.
+///
+/// # Arguments
+/// - `expr`: the expression, whose comparisons are all parsed
+/// - `max`: the constant maximum
+///
+/// # Returns
+/// A boolean indicating whether `max` is at least the constant minimum. Whether the range can
+/// fit the expression's `<` comparisons is left to `check_range`.
+fn close_expr(expr: &mut FuzzExpr, max: Value) -> bool {
+    expr.const_max = max;
+    expr.less_than_count = count_less_thans(&expr.comparisons);
+    expr.group_mins = (0..expr.vars.len())
+        .map(|depth| expr.const_min.saturating_add(count_less_thans(&expr.comparisons[..=depth]) as Value))
+        .collect();
+    expr.group_maxes = (0..expr.vars.len())
+        .map(|depth| max.saturating_sub(count_less_thans(&expr.comparisons[depth + 1..]) as Value))
+        .collect();
 
+    max >= expr.const_min
 }
 
-/// Count how many `LessThan` comparisons are found in a slice.
+/// Check that the range of a closed expression fits its `<` comparisons, e.g reject
+/// `0 < A < B < 2`, whose chain needs 4 distinct values while the range only holds 3.
 ///
 /// # Arguments
-/// - `slice`: the slice containing `ComparisonType`s.
+/// - `expr`: the expression, with its constant maximum set
 ///
 /// # Returns
-/// The count of `LessThan` comparison enums.
-fn count_less_thans(slice: &[ComparisonType]) -> u64 {
-    slice.iter().filter(|x| x == &&ComparisonType::LessThan).count() as u64
+/// An `AppResult` containing nothing, or an `AppError::RangeTooSmall` when the range is too small.
+fn check_range(expr: &FuzzExpr) -> AppResult<()> {
+    if expr.const_max.abs_diff(expr.const_min) < expr.less_than_count as u128 {
+        return Err(AppError::RangeTooSmall {
+            line: expr.line,
+            expr: expr.to_string(),
+            min: expr.const_min,
+            max: expr.const_max,
+            needed: expr.less_than_count + 1
+        })
+    }
+    Ok(())
+}
+
+/// Check that the constant bounds of an expression fit in an `i64`, as every value has to unless
+/// big integers are enabled (see `ParseOptions::bigint`).
+///
+/// # Arguments
+/// - `expr`: the expression
+///
+/// # Returns
+/// An `AppResult` containing nothing, or an `AppError::ValueTooLarge` when a bound doesn't fit.
+fn check_i64(expr: &FuzzExpr) -> AppResult<()> {
+    if [expr.const_min, expr.const_max].into_iter().any(|bound| i64::try_from(bound).is_err()) {
+        return Err(AppError::ValueTooLarge(expr.line, expr.to_string()))
+    }
+    Ok(())
+}
+
+/// Builds a `FuzzExpr` in code instead of parsing it from a spec. Variable groups are added from
+/// left to right, e.g `1 <= N <= 10` is built with:
+///
+/// ```
+/// use beanfuzz::{ComparisonType, ExprVariable, FuzzExprBuilder};
+///
+/// let expr = FuzzExprBuilder::new(1)
+///     .group(ComparisonType::LessThanOrEqualTo, vec![ExprVariable::Variable("N".into(), Default::default())])
+///     .build(ComparisonType::LessThanOrEqualTo, 10)
+///     .unwrap();
+/// assert_eq!(expr.to_string(), "1 <= N <= 10");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FuzzExprBuilder {
+    const_min: Value,
+    comparisons: Vec<ComparisonType>,
+    vars: Vec<VariableGroup>
+}
+
+impl FuzzExprBuilder {
+    /// Start an expression with its constant minimum.
+    pub fn new(min: Value) -> Self {
+        Self { const_min: min, comparisons: Vec::new(), vars: Vec::new() }
+    }
+
+    /// Add a variable group, compared with whatever comes before it (the constant minimum or the
+    /// previous group).
+    pub fn group(mut self, comparison: ComparisonType, group: VariableGroup) -> Self {
+        self.comparisons.push(comparison);
+        self.vars.push(group);
+        self
+    }
+
+    /// Finish the expression with its constant maximum, compared with the last group. The
+    /// expression goes through the same checks as a parsed one.
+    ///
+    /// # Returns
+    /// An `AppResult` containing the `FuzzExpr`. An `AppError::InvalidSyntax` (at line 0) when
+    /// there is no variable group, a group is empty, a variable is invalid (e.g both distinct and
+    /// `@log`) or the maximum is below the minimum, or an `AppError::RangeTooSmall` when the range
+    /// doesn't fit the `<` comparisons.
+    pub fn build(self, comparison: ComparisonType, max: Value) -> AppResult<FuzzExpr> {
+        let mut repr = self.const_min.to_string();
+        for (comparison, group) in self.comparisons.iter().zip(&self.vars) {
+            let group: Vec<String> = group.iter().map(ExprVariable::to_string).collect();
+            repr.push_str(&format!(" {} {}", comparison, group.join(",")));
+        }
+        repr.push_str(&format!(" {} {}", comparison, max));
+
+        // A variable is valid exactly when its declaration parses back into it.
+        let valid = !self.vars.is_empty() && self.vars.iter().all(|group| !group.is_empty())
+            && self.vars.iter().flatten().all(|var| string_to_variable(&var.to_string()).as_ref() == Some(var));
+        let mut expr = FuzzExpr {
+            const_min: self.const_min,
+            comparisons: self.comparisons.into_iter().chain([comparison]).collect(),
+            vars: self.vars,
+            repr,
+            ..Default::default()
+        };
+        if !valid || !close_expr(&mut expr, max) {
+            return Err(AppError::InvalidSyntax(0, expr.repr))
+        }
+        check_range(&expr)?;
+        Ok(expr)
+    }
 }
 
 /// Try to parse a vector of tokens from a single line of file into an expression. Consumes the
-/// given tokens (thus the mutable borrow) and moves it into the resulting `FuzzExpr`.
+/// given tokens (thus the mutable borrow) and moves it into the resulting `FuzzExpr`. An
+/// expression ending with a group of plain variables is open (see `FuzzExpr::open_max`), and
+/// gets its constant maximum later.
 ///
 /// # Arguments
 /// - `tokens`: slice of tokens to parse
@@ -87,9 +207,10 @@ pub(crate) fn parse_expr_from_line(repr: &str, tokens: &mut VecDeque<Token>) ->
     if tokens.len() < 5 {
         return None
     }
-    let mut fuzz_expr = FuzzExpr::default();
-
-    fuzz_expr.repr = repr.to_string();
+    let mut fuzz_expr = FuzzExpr {
+        repr: repr.to_string(),
+        ..Default::default()
+    };
 
     if let Token::NumValue(x) = tokens.pop_front()? {
         fuzz_expr.const_min = x;
@@ -105,20 +226,13 @@ pub(crate) fn parse_expr_from_line(repr: &str, tokens: &mut VecDeque<Token>) ->
     };
 
     if let Token::VariableGroup(vars) = tokens.pop_front()? {
-        // TODO: maybe this O(n) operation can be improved? This should be fine though as the
-        // vector shouldn't contain too many items.
-
-        if !fuzz_expr.contains_array && expr_var_arr_contains_arr_var(&vars) {
-            fuzz_expr.contains_array = true;
-        }
-
         fuzz_expr.vars.push(vars);
     } else {
         return None;
     }
 
     // Parse the rest of the tokens. Parse chunks of two tokens.
-    while tokens.len() > 0 {
+    while !tokens.is_empty() {
         if let Token::Comparison(comp) = tokens.pop_front()? {
             fuzz_expr.comparisons.push(comp);
         } else {
@@ -127,22 +241,9 @@ pub(crate) fn parse_expr_from_line(repr: &str, tokens: &mut VecDeque<Token>) ->
 
         let second_token = tokens.pop_front()?;
         if let Token::VariableGroup(vars) = second_token {
-            if !fuzz_expr.contains_array && expr_var_arr_contains_arr_var(&vars) {
-                fuzz_expr.contains_array = true;
-            }
             fuzz_expr.vars.push(vars);
         } else if let Token::NumValue(x) = second_token { // last item is a constant so we should stop parsing.
-            fuzz_expr.const_max = x;
-
-            fuzz_expr.less_than_count = count_less_thans(&fuzz_expr.comparisons);
-
-            // invalid if max is smaller than min
-            if x < fuzz_expr.const_min {
-                return None
-            }
-
-            // also invalid when the possible range cannot fit the variables.
-            if (fuzz_expr.const_max - fuzz_expr.const_min).unsigned_abs() < fuzz_expr.less_than_count {
+            if !close_expr(&mut fuzz_expr, x) {
                 return None
             }
             return Some(fuzz_expr)
@@ -151,61 +252,628 @@ pub(crate) fn parse_expr_from_line(repr: &str, tokens: &mut VecDeque<Token>) ->
         }
     };
 
-    None
+    // The last group bounds the rest, and is itself bounded by its other expressions.
+    let last_is_scalar = fuzz_expr.vars.last()?.iter().all(|var| matches!(var, ExprVariable::Variable(..)));
+    if fuzz_expr.vars.len() < 2 || !last_is_scalar {
+        return None
+    }
+    fuzz_expr.comparisons.push(ComparisonType::LessThanOrEqualTo);
+    fuzz_expr.open_max = true;
+    Some(fuzz_expr)
+}
+
+/// Give every open expression (see `FuzzExpr::open_max`) its constant maximum: the smallest of
+/// the largest values its last group's variables may take in the other expressions. An open
+/// expression may rely on another open one, which is then resolved first.
+///
+/// # Arguments
+/// - `exprs`: the expressions
+///
+/// # Returns
+/// An `AppResult` containing nothing. An `AppError::UnknownBound` when an open expression's last
+/// group holds a name no expression gives a maximum, either because no other expression declares
+/// it (e.g a misspelled `define`) or because only open expressions relying on each other do. An
+/// `AppError::InvalidSyntax` when its maximum leaves no room for its variables.
+fn resolve_open_maxes(exprs: &mut [FuzzExpr]) -> AppResult<()> {
+    let mut unresolved: Vec<usize> = (0..exprs.len()).filter(|&i| exprs[i].open_max).collect();
+    while !unresolved.is_empty() {
+        let mut maxes: HashMap<String, Value> = HashMap::new();
+        for expr in exprs.iter().enumerate().filter(|(i, _)| !unresolved.contains(i)).map(|(_, expr)| expr) {
+            for (group, group_max) in expr.vars.iter().zip(&expr.group_maxes) {
+                for var in group {
+                    if let ExprVariable::Variable(key, _) = var {
+                        maxes.entry(key.clone()).and_modify(|prev| *prev = (*prev).min(*group_max)).or_insert(*group_max);
+                    }
+                }
+            }
+        }
+
+        let mut still_unresolved = Vec::new();
+        for &i in &unresolved {
+            let last = exprs[i].vars.last().expect("An open expression has at least two groups");
+            let max = last.iter().map(|var| match var {
+                ExprVariable::Variable(key, _) => maxes.get(key).copied(),
+                _ => None
+            }).collect::<Option<Vec<Value>>>().and_then(|maxes| maxes.into_iter().min());
+            match max {
+                Some(max) if close_expr(&mut exprs[i], max) => check_range(&exprs[i])?,
+                Some(_) => return Err(AppError::InvalidSyntax(exprs[i].line, exprs[i].to_string())),
+                None => still_unresolved.push(i)
+            }
+        }
+
+        if still_unresolved.len() == unresolved.len() {
+            let i = unresolved[0];
+            let unbounded = exprs[i].vars.last().expect("An open expression has at least two groups").iter()
+                .find_map(|var| match var {
+                    ExprVariable::Variable(key, _) if !maxes.contains_key(key) => Some(key.clone()),
+                    _ => None
+                })
+                .expect("An unresolved expression has an unbounded variable");
+            return Err(AppError::UnknownBound(unbounded, exprs[i].to_string()))
+        }
+        unresolved = still_unresolved;
+    }
+    Ok(())
+}
+
+/// Order the expressions so that every variable used as an array, matrix or string length is
+/// generated before it is used, regardless of the order they are written in. Otherwise the file
+/// order is kept. Inside a single expression, variables are generated from left to right, so a
+/// length variable must be written before the array using it (e.g `1 <= N <= A[N]# <= 100`).
+///
+/// # Arguments
+/// - `exprs`: the expressions, in file order
+/// - `strings`: the string variables, which are generated after every expression
+/// - `trees`: the trees, which are also generated after every expression
+///
+/// # Returns
+/// An `AppResult` containing the ordered expressions. An `AppError::UndeclaredVariable` when a
+/// length refers to an undeclared variable, or an `AppError::CyclicDependency` when the lengths
+/// depend on each other in a cycle. A length variable shared between expressions has to be
+/// generated by all of them first. Likewise, the last group of an open expression (e.g the `B` of
+/// `1 <= A <= B`) is generated by its other expressions first, so its value bounds the rest.
+fn order_exprs(exprs: Vec<FuzzExpr>, strings: &[FuzzString], trees: &[FuzzTree]) -> AppResult<Vec<FuzzExpr>> {
+    let mut owners: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, expr) in exprs.iter().enumerate() {
+        for var in expr.vars.iter().flatten() {
+            if let ExprVariable::Variable(key, _) | ExprVariable::Choice(key, _) = var {
+                owners.entry(key.as_str()).or_default().push(i);
+            }
+        }
+    }
+
+    // Whether an expression is open on a variable, i.e the variable is in its last group.
+    let open_on = |expr: &FuzzExpr, key: &str| expr.open_max && expr.vars.last().is_some_and(|group| group.iter().any(|var| matches!(var, ExprVariable::Variable(other, _) if other == key)));
+
+    let mut deps = vec![HashSet::new(); exprs.len()];
+    for (i, expr) in exprs.iter().enumerate() {
+        if expr.open_max {
+            for var in expr.vars.last().expect("An open expression has at least two groups") {
+                let ExprVariable::Variable(key, _) = var else {
+                    continue
+                };
+                deps[i].extend(owners[key.as_str()].iter().copied().filter(|&owner| !open_on(&exprs[owner], key)));
+            }
+        }
+
+        let mut generated = HashSet::new();
+        for var in expr.vars.iter().flatten() {
+            let lens = match var {
+                ExprVariable::Variable(key, _) | ExprVariable::Choice(key, _) => {
+                    generated.insert(key.as_str());
+                    continue
+                }
+                ExprVariable::Array(_, len, _) => vec![len],
+                ExprVariable::Matrix(_, rows, cols, _) => vec![rows, cols],
+            };
+            for len_key in lens.into_iter().filter_map(LenExpr::variable) {
+                let Some(len_owners) = owners.get(len_key) else {
+                    return Err(AppError::UndeclaredVariable(len_key.to_string()))
+                };
+                for &owner in len_owners {
+                    if owner != i {
+                        deps[i].insert(owner);
+                    } else if !generated.contains(len_key) {
+                        return Err(AppError::CyclicDependency(expr.to_string()))
+                    }
+                }
+            }
+        }
+    }
+
+    let later_lens = strings.iter().map(|string| &string.len).chain(trees.iter().map(|tree| &tree.nodes));
+    if let Some(len_key) = later_lens.filter_map(LenExpr::variable).find(|len_key| !owners.contains_key(len_key)) {
+        return Err(AppError::UndeclaredVariable(len_key.to_string()))
+    }
+
+    // Repeatedly take the first expression whose dependencies are all generated already.
+    let mut remaining: Vec<Option<FuzzExpr>> = exprs.into_iter().map(Some).collect();
+    let mut done = vec![false; remaining.len()];
+    let mut ordered = Vec::new();
+    while ordered.len() < remaining.len() {
+        let next = (0..remaining.len()).find(|&i| !done[i] && deps[i].iter().all(|&dep| done[dep]));
+        let Some(next) = next else {
+            let stuck = remaining.iter().flatten().next().expect("Unordered expressions remain");
+            return Err(AppError::CyclicDependency(stuck.to_string()))
+        };
+        done[next] = true;
+        ordered.push(remaining[next].take().expect("Each expression is taken once"));
+    }
+    Ok(ordered)
+}
+
+/// Check that every array, matrix, string and tree length is at least 1 however its length
+/// variable gets generated, e.g reject `0 <= N <= 10` along with `A[N]#`.
+///
+/// # Arguments
+/// - `exprs`: the expressions
+/// - `strings`: the string variables
+/// - `trees`: the trees
+/// - `allow_empty_arrays`: whether array lengths may be 0
+///
+/// # Returns
+/// An `AppResult` containing nothing, or an `AppError::InvalidLengthRange` for the first length
+/// that can be too small. Lengths referring to undeclared variables are left to `order_exprs`.
+fn check_lengths(exprs: &[FuzzExpr], strings: &[FuzzString], trees: &[FuzzTree], allow_empty_arrays: bool) -> AppResult<()> {
+    // A variable shared between expressions has to fit all of them, so its smallest value is the
+    // highest of their lower bounds.
+    let mut mins: HashMap<&str, Value> = HashMap::new();
+    for expr in exprs {
+        for (group, &min) in expr.vars.iter().zip(&expr.group_mins) {
+            for var in group {
+                if let ExprVariable::Variable(key, _) | ExprVariable::Choice(key, _) = var {
+                    mins.entry(key.as_str()).and_modify(|prev| *prev = (*prev).max(min)).or_insert(min);
+                }
+            }
+        }
+    }
+
+    let too_small = |len: &LenExpr, min: Value| len.resolve(|key| mins.get(key).copied()).filter(|len| *len < min);
+    let smallest = |len: &LenExpr| too_small(len, 1);
+    let array_min = if allow_empty_arrays { 0 } else { 1 };
+    for expr in exprs {
+        for var in expr.vars.iter().flatten() {
+            let lens = match var {
+                ExprVariable::Variable(..) | ExprVariable::Choice(..) => continue,
+                ExprVariable::Array(_, len, _) => vec![(len, array_min)],
+                ExprVariable::Matrix(_, rows, cols, _) => vec![(rows, 1), (cols, 1)],
+            };
+            if let Some(len) = lens.into_iter().find_map(|(len, min)| too_small(len, min)) {
+                return Err(AppError::InvalidLengthRange(len, expr.to_string()))
+            }
+        }
+    }
+    for string in strings {
+        if let Some(len) = smallest(&string.len) {
+            return Err(AppError::InvalidLengthRange(len, string.to_string()))
+        }
+    }
+    for tree in trees {
+        if let Some(len) = smallest(&tree.nodes) {
+            return Err(AppError::InvalidLengthRange(len, tree.to_string()))
+        }
+    }
+    Ok(())
+}
+
+/// Replace names declared with `define` by their values: a lone name becomes a `NumValue`, and so
+/// does an array length referring to one. Names in invalid positions are left for the expression
+/// parser to reject.
+///
+/// # Arguments
+/// - `tokens`: tokens of a single line
+/// - `defines`: the declared names and their values
+fn resolve_defines(tokens: &mut VecDeque<Token>, defines: &HashMap<String, Value>) {
+    for token in tokens.iter_mut() {
+        let Token::VariableGroup(vars) = token else {
+            continue
+        };
+
+        if let [ExprVariable::Variable(name, flags)] = vars.as_slice() {
+            if let (Some(val), true) = (defines.get(name), *flags == VarFlags::default()) {
+                *token = Token::NumValue(*val);
+                continue
+            }
+        }
+
+        for var in vars.iter_mut() {
+            let lens = match var {
+                ExprVariable::Array(_, len, _) => vec![len],
+                ExprVariable::Matrix(_, rows, cols, _) => vec![rows, cols],
+                ExprVariable::Variable(..) | ExprVariable::Choice(..) => continue
+            };
+            for len in lens {
+                if let Some(val) = len.resolve(|name| defines.get(name).copied()) {
+                    *len = LenExpr::Constant(val);
+                }
+            }
+        }
+    }
+}
+
+/// Complete an expression made of a lower bound and a single variable group (e.g `1 <= A`) with
+/// a default maximum, as if it was written as `1 <= A <= MAX`.
+///
+/// # Arguments
+/// - `tokens`: the expression's tokens
+/// - `default_max`: the default maximum, if any. Without it, the tokens are left as is.
+fn apply_default_max(tokens: &mut VecDeque<Token>, default_max: Option<Value>) {
+    let Some(max) = default_max else {
+        return
+    };
+    if let [Token::NumValue(_), Token::Comparison(_), Token::VariableGroup(_)] = tokens.make_contiguous() {
+        tokens.push_back(Token::Comparison(ComparisonType::LessThanOrEqualTo));
+        tokens.push_back(Token::NumValue(max));
+    }
+}
+
+/// Strip a comment from a line of the fuzz information. A comment starts with a `#` at the start
+/// of a word (i.e at the start of the line or right after whitespace) and runs to the end of the
+/// line, so the `#` in array variables like `A[N]#` is not a comment.
+///
+/// # Arguments
+/// - `line`: the line to strip
+///
+/// # Returns
+/// The line without its comment, with trailing whitespace trimmed.
+fn strip_comment(line: &str) -> &str {
+    let mut prev_is_space = true;
+    for (idx, ch) in line.char_indices() {
+        if ch == '#' && prev_is_space {
+            return line[..idx].trim_end()
+        }
+        prev_is_space = ch.is_whitespace();
+    }
+    line.trim_end()
+}
+
+/// Marks a name of the input order whose length is written right before it, e.g `A@len` for
+/// `A[N]#` writes `N` and then the items of `A`. Only arrays and strings have one.
+pub(crate) const LENGTH_MARKER: &str = "@len";
 
+/// Parse the variable names of an input order, with the `input order:` prefix already stripped.
+/// Names are separated by commas and/or whitespace (e.g `A B`, `A, B` or `A,B`), and a single
+/// trailing comma is allowed.
+///
+/// # Arguments
+/// - `order`: the names
+///
+/// # Returns
+/// An `Option` containing the names, or `None` when there are none or two commas have nothing
+/// between them.
+fn parse_input_order(order: &str) -> Option<Vec<String>> {
+    let order = order.trim();
+    let order = order.strip_suffix(',').unwrap_or(order);
+    if order.is_empty() {
+        return None
+    }
+
+    let mut vars = Vec::new();
+    for part in order.split(',') {
+        let len = vars.len();
+        vars.extend(part.split_whitespace().map(String::from));
+        if vars.len() == len {
+            return None
+        }
+    }
+    Some(vars)
 }
 
-/// The whole data used to start the fuzzing. Create one by running `Self::parse`.
+/// Parse the value of an `input separator:` or `output separator:` directive, with the prefix
+/// already stripped (see `unescape`).
+///
+/// # Arguments
+/// - `value`: the separator as written
+///
+/// # Returns
+/// An `Option` containing the separator, or `None` when it is empty or has an invalid escape.
+fn parse_separator(value: &str) -> Option<String> {
+    unescape(value.trim()).filter(|sep| !sep.is_empty())
+}
+
+/// A string variable, declared on its own line like `S{N}`. Its characters are picked from the
+/// alphabet given through the command line.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct FuzzString {
+    /// The string variable's name.
+    pub(crate) name: String,
+
+    /// The length of the string.
+    pub(crate) len: LenExpr,
+
+    /// Whether the string reads the same forwards and backwards, marked with `@palindrome`.
+    pub(crate) palindrome: bool,
+
+    /// The (1-indexed) line of the spec the declaration is on, reported in errors.
+    pub(crate) line: u64,
+
+    /// The string representation of the declaration. Used for debugging.
+    pub(crate) repr: String
+}
+
+impl Display for FuzzString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.repr)
+    }
+}
+
+/// A random labeled tree, declared on its own line like `tree G[N]#`. Its nodes are numbered from
+/// 1 to the amount of nodes, and it is emitted as its edges.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct FuzzTree {
+    /// The tree's name.
+    pub(crate) name: String,
+
+    /// The amount of nodes of the tree.
+    pub(crate) nodes: LenExpr,
+
+    /// The (1-indexed) line of the spec the declaration is on, reported in errors.
+    pub(crate) line: u64,
+
+    /// The string representation of the declaration. Used for debugging.
+    pub(crate) repr: String
+}
+
+impl Display for FuzzTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.repr)
+    }
+}
+
+/// A constraint that two variables differ, declared on its own line like `A != B`. Inputs that
+/// break it are generated again.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct FuzzExclusion {
+    /// Name of the left variable.
+    pub(crate) left: String,
+
+    /// Name of the right variable.
+    pub(crate) right: String,
+
+    /// The (1-indexed) line of the spec the constraint is on, reported in errors.
+    pub(crate) line: u64,
+
+    /// The string representation of the constraint. Used for debugging.
+    pub(crate) repr: String
+}
+
+/// What a declared name is, along with where its length comes from.
+#[derive(Debug, PartialEq)]
+pub enum VarKind {
+    Variable,
+    /// An array, along with its length (e.g `N-1`).
+    Array(String),
+    /// A matrix, along with its amount of rows and columns.
+    Matrix(String, String),
+    /// A string variable, along with its length.
+    String(String),
+    /// A tree, along with its amount of nodes.
+    Tree(String)
+}
+
+impl Display for VarKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Variable => write!(f, "variable"),
+            Self::Array(_) => write!(f, "array"),
+            Self::Matrix(..) => write!(f, "matrix"),
+            Self::String(_) => write!(f, "string"),
+            Self::Tree(_) => write!(f, "tree"),
+        }
+    }
+}
+
+/// A declared name along with what the parser inferred about it, see `FuzzData::list_vars`.
 #[derive(Debug, PartialEq)]
-pub(crate) struct FuzzData {
+pub struct VarListing {
+    pub name: String,
+    pub kind: VarKind,
+    /// The inclusive range of the variable, or of the array or matrix items. `None` for strings
+    /// and trees.
+    pub bounds: Option<(Value, Value)>
+}
+
+impl VarListing {
+    /// Where the length comes from, e.g `N` for `A[N]#` or `N x M` for `A[N][M]#`. `None` for
+    /// variables.
+    pub fn length(&self) -> Option<String> {
+        match &self.kind {
+            VarKind::Variable => None,
+            VarKind::Array(len) | VarKind::String(len) | VarKind::Tree(len) => Some(len.clone()),
+            VarKind::Matrix(rows, cols) => Some(format!("{} x {}", rows, cols)),
+        }
+    }
+}
+
+/// Writes what the parser makes of each expression line: the tokens it was split into and the
+/// resulting expression. Used to debug specs that don't parse as intended.
+pub struct ParseTracer<'a> {
+    out: &'a mut dyn Write
+}
+
+impl<'a> ParseTracer<'a> {
+    pub fn new(out: &'a mut dyn Write) -> Self {
+        Self { out }
+    }
+
+    fn tokens(&mut self, line_no: u64, tokens: &VecDeque<Token>) -> AppResult<()> {
+        writeln!(self.out, "line {}: tokens {:?}", line_no, tokens)?;
+        Ok(())
+    }
+
+    fn expr(&mut self, line_no: u64, expr: Option<&FuzzExpr>) -> AppResult<()> {
+        match expr {
+            Some(expr) => writeln!(self.out, "line {}: expression {:?}", line_no, expr)?,
+            None => writeln!(self.out, "line {}: the tokens don't form an expression", line_no)?,
+        }
+        Ok(())
+    }
+}
+
+/// Options of `FuzzData::parse_with`. Non-exhaustive: set fields on `ParseOptions::default()`.
+#[derive(Default)]
+#[non_exhaustive]
+pub struct ParseOptions<'a> {
+    /// Traces every expression line, if set.
+    pub tracer: Option<ParseTracer<'a>>,
+
+    /// Where warnings about the spec are written, if set (e.g a `<=` spelled `=<`). Each kind of
+    /// warning is written at most once per parse.
+    pub warnings: Option<&'a mut dyn Write>,
+
+    /// The maximum of expressions made of a lower bound and a single variable group (e.g
+    /// `1 <= A`), which are invalid without it.
+    pub default_max: Option<Value>,
+
+    /// Whether array lengths may be 0, for testing how solutions handle empty arrays. Lengths
+    /// below 0 are still rejected. The runner has to allow them too, see
+    /// `RunnerOptions::allow_empty_arrays`.
+    pub allow_empty_arrays: bool,
+
+    /// Whether values may lie outside the `i64` range, up to the range of `Value`. Bounds like
+    /// `10^19` are rejected without it.
+    pub bigint: bool,
+}
+
+/// The whole data used to start the fuzzing. Create one by running `Self::parse`, or from
+/// expressions built in code with `Self::from_exprs`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FuzzData {
     /// Vector of valid fuzzer expressions.
     pub(crate) exprs: Vec<FuzzExpr>,
+    /// String variables. They are generated after every expression, so their length may refer
+    /// to any variable.
+    pub(crate) strings: Vec<FuzzString>,
+    /// Trees, generated after every expression like string variables.
+    pub(crate) trees: Vec<FuzzTree>,
+    /// Constraints that two variables differ, checked after every expression got generated.
+    pub(crate) exclusions: Vec<FuzzExclusion>,
     /// The input order. After all variables have been set in hashmap(s), the strings below will be
-    /// used to lookup the variable values from the hashmap.
+    /// used to lookup the variable values from the hashmap. Names may end with `LENGTH_MARKER`.
     pub(crate) input_order: Vec<String>,
     pub(crate) input_separator: String,
     pub(crate) output_separator: String
 }
 
 impl FuzzData {
-    /// Parse lines of a file.
+    /// Parse lines of a file. Comments and trailing whitespace (including the `\r` of CRLF line
+    /// endings) are stripped from each line first (see `strip_comment`), so an expression's `repr`
+    /// and the lines in errors never contain them.
     ///
     /// # Arguments
-    /// - `input_separator`: the input separator
-    /// - `output_separator`: the output separator
+    /// - `input_separator`: the input separator, unless the file declares one with `input separator:`
+    /// - `output_separator`: the output separator, unless the file declares one with `output separator:`
     /// - `lines`: an item that can be iterated over as `String`s
     /// 
     /// # Returns
     /// An `AppResult` containing `Self` when parse succeeded. `Err` containing `AppError` otherwise.
-    pub(crate) fn parse<T: IntoIterator<Item = String>>(input_separator: String, output_separator: String, lines: T) -> AppResult<Self> {
+    pub fn parse<T: IntoIterator<Item = String>>(input_separator: String, output_separator: String, lines: T) -> AppResult<Self> {
+        Self::parse_with(input_separator, output_separator, lines, ParseOptions::default())
+    }
+
+    /// Like `parse`, with `ParseOptions`.
+    pub fn parse_with<T: IntoIterator<Item = String>>(input_separator: String, output_separator: String, lines: T, options: ParseOptions) -> AppResult<Self> {
+        let ParseOptions { mut tracer, mut warnings, default_max, allow_empty_arrays, bigint } = options;
+        let mut warned_lte_alias = false;
         let mut exprs = Vec::new();
+        let mut strings = Vec::new();
+        let mut trees = Vec::new();
+        let mut exclusions = Vec::new();
+        let mut defines = HashMap::new();
         let mut input_order = None;
+        let mut declared_input_sep = None;
+        let mut declared_output_sep = None;
         let mut i = 0;
         for line in lines {
             i += 1;
-            if line.starts_with("#") || line.is_empty() {
+            let line = strip_comment(&line).to_string();
+            if line.is_empty() {
                 continue
             }
 
-            if line.starts_with("input order:") {
-                if input_order.is_none() {
-                    let tmp_input_order: Vec<&str> = line.split(":").collect();
+            if let Some(order) = line.strip_prefix("input order:") {
+                if input_order.is_some() {
+                    return Err(AppError::MultipleInputOrder)
+                }
+                let Some(vars) = parse_input_order(order) else {
+                    return Err(AppError::InvalidSyntax(i, line))
+                };
+                input_order = Some(vars);
+                continue;
+            }
+
+            if let Some((name, value)) = line.split_once(':').filter(|(name, _)| *name == "input separator" || *name == "output separator") {
+                let declared_sep = if name == "input separator" { &mut declared_input_sep } else { &mut declared_output_sep };
+                if declared_sep.is_some() {
+                    return Err(AppError::DoubleDeclaration(name.to_string()))
+                }
+                let Some(sep) = parse_separator(value) else {
+                    return Err(AppError::InvalidSyntax(i, line))
+                };
+                *declared_sep = Some(sep);
+                continue;
+            }
 
-                if tmp_input_order.len() < 2 {
+            if let Some(define) = line.strip_prefix("define ") {
+                let Some((name, val)) = tokenize_define(define) else {
                     return Err(AppError::InvalidSyntax(i, line))
+                };
+                if defines.insert(name.clone(), val).is_some() {
+                    return Err(AppError::DoubleDeclaration(name))
                 }
+                continue;
+            }
 
-                let vars: Vec<String> = tmp_input_order[1].split_whitespace().map(|str| str.into()).collect();
-                    input_order = Some(vars);
-                } else {
-                    return Err(AppError::MultipleInputOrder)
+            if let Some(decl) = line.strip_prefix("tree ") {
+                let Some((name, mut nodes)) = tokenize_tree_decl(decl.trim()) else {
+                    return Err(AppError::InvalidSyntax(i, line))
+                };
+                if let Some(val) = nodes.resolve(|name| defines.get(name).copied()) {
+                    nodes = LenExpr::Constant(val);
+                }
+                trees.push(FuzzTree { name, nodes, line: i, repr: line.clone() });
+                continue;
+            }
+
+            if let Some((left, right)) = tokenize_exclusion(&line) {
+                exclusions.push(FuzzExclusion { left, right, line: i, repr: line.clone() });
+                continue;
+            }
+
+            // A line of string declarations, e.g `S{N} T{5}`.
+            if let Some(decls) = line.split_whitespace().map(|item| Some((item, tokenize_string_decl(item)?))).collect::<Option<Vec<_>>>() {
+                for (repr, (name, mut len, palindrome)) in decls {
+                    if let Some(val) = len.resolve(|name| defines.get(name).copied()) {
+                        len = LenExpr::Constant(val);
+                    }
+                    strings.push(FuzzString { name, len, palindrome, line: i, repr: repr.to_string() });
                 }
                 continue;
             }
 
-            // Anything other than the two above are treated as an expression.
-            if let Some(mut tokens) = tokenize_expr_line(&line) {
-                if let Some(expr) = parse_expr_from_line(&line, &mut tokens) {
+            // Anything other than the seven above are treated as an expression.
+            if let Some(mut tokens) = tokenize_choice_line(&line).or_else(|| tokenize_interval_line(&line)).or_else(|| tokenize_expr_line(&line)) {
+                if let Some(tracer) = &mut tracer {
+                    tracer.tokens(i, &tokens)?;
+                }
+                if let Some(out) = warnings.as_mut().filter(|_| !warned_lte_alias && uses_lte_alias(&line)) {
+                    writeln!(out, "Warning: line {}: `=<` is read as `<=`, which is the canonical form", i)?;
+                    warned_lte_alias = true;
+                }
+                resolve_defines(&mut tokens, &defines);
+                apply_default_max(&mut tokens, default_max);
+                let expr = parse_expr_from_line(&line, &mut tokens).map(|expr| FuzzExpr { line: i, ..expr });
+                if let Some(tracer) = &mut tracer {
+                    tracer.expr(i, expr.as_ref())?;
+                }
+                if let Some(expr) = expr {
+                    if !expr.open_max {
+                        check_range(&expr)?;
+                    }
+                    if !bigint {
+                        check_i64(&expr)?;
+                    }
                     exprs.push(expr);
                 } else {
                     return Err(AppError::InvalidSyntax(i, line))
@@ -215,16 +883,127 @@ impl FuzzData {
             }
         }
 
-        // When an expression contains an array, we have to evaluate them last.
-        exprs.sort_by_key(|x| if x.contains_array {1} else {0} );
+        let separators = (declared_input_sep.unwrap_or(input_separator), declared_output_sep.unwrap_or(output_separator));
+        Self::assemble(exprs, strings, trees, exclusions, input_order, separators, allow_empty_arrays)
+    }
+
+    /// Create the data from expressions built in code (see `FuzzExprBuilder`), checked the same
+    /// way as a parsed spec.
+    ///
+    /// # Arguments
+    /// - `exprs`: the expressions
+    /// - `input_order`: the names in the input, in order
+    /// - `input_separator`: the input separator
+    /// - `output_separator`: the output separator
+    ///
+    /// # Returns
+    /// An `AppResult` containing `Self`, or the `AppError` a spec holding these expressions would
+    /// give (e.g `AppError::UndeclaredVariable` for a name in the input order nothing declares).
+    pub fn from_exprs(exprs: Vec<FuzzExpr>, input_order: Vec<String>, input_separator: String, output_separator: String) -> AppResult<Self> {
+        Self::assemble(exprs, Vec::new(), Vec::new(), Vec::new(), Some(input_order), (input_separator, output_separator), false)
+    }
+
+    /// Check the declarations of a spec against each other, and put them in generation order.
+    /// Empty arrays are only accepted with `allow_empty_arrays`, see `ParseOptions`.
+    fn assemble(mut exprs: Vec<FuzzExpr>, strings: Vec<FuzzString>, trees: Vec<FuzzTree>, exclusions: Vec<FuzzExclusion>, input_order: Option<Vec<String>>, (input_separator, output_separator): (String, String), allow_empty_arrays: bool) -> AppResult<Self> {
+        if exprs.is_empty() && strings.is_empty() && trees.is_empty() {
+            return Err(AppError::NoExpressions)
+        }
+        resolve_open_maxes(&mut exprs)?;
+
+        // A plain variable may appear in multiple expressions, in which case they share it (see
+        // `RunnerOptions::independent_vars`), but only once per expression. Every other name has
+        // to be unique, including the name of a variable picked from a list.
+        let mut declared = HashSet::new();
+        let mut variables = HashSet::new();
+        let mut choices = HashSet::new();
+        for expr in &exprs {
+            let mut in_expr = HashSet::new();
+            for var in expr.vars.iter().flatten() {
+                let (ExprVariable::Variable(key, _) | ExprVariable::Array(key, ..) | ExprVariable::Matrix(key, ..) | ExprVariable::Choice(key, _)) = var;
+                let is_variable = matches!(var, ExprVariable::Variable(..));
+                let shareable = is_variable && variables.contains(key.as_str()) && !choices.contains(key.as_str());
+                if !in_expr.insert(key.as_str()) || (!declared.insert(key.as_str()) && !shareable) {
+                    return Err(AppError::DoubleDeclaration(key.clone()))
+                }
+                if is_variable {
+                    variables.insert(key.as_str());
+                } else if matches!(var, ExprVariable::Choice(..)) {
+                    variables.insert(key.as_str());
+                    choices.insert(key.as_str());
+                }
+            }
+        }
+        for name in strings.iter().map(|string| string.name.as_str()).chain(trees.iter().map(|tree| tree.name.as_str())) {
+            if !declared.insert(name) {
+                return Err(AppError::DoubleDeclaration(name.to_string()))
+            }
+        }
+
+        if let Some(name) = exclusions.iter().flat_map(|excl| [&excl.left, &excl.right]).find(|name| !variables.contains(name.as_str())) {
+            return Err(AppError::UndeclaredVariable(name.clone()))
+        }
+
+        let input_order = input_order.ok_or(AppError::NoInputOrder)?;
+        for name in &input_order {
+            let (name, with_length) = name.strip_suffix(LENGTH_MARKER).map_or((name.as_str(), false), |name| (name, true));
+            if !declared.contains(name) {
+                return Err(AppError::UndeclaredVariable(name.to_string()))
+            }
+            let is_array = exprs.iter().flat_map(|expr| expr.vars.iter().flatten()).any(|var| matches!(var, ExprVariable::Array(key, ..) if key == name));
+            if with_length && !is_array && !strings.iter().any(|string| string.name == name) {
+                return Err(AppError::NoLength(name.to_string()))
+            }
+        }
+
+        let exprs = order_exprs(exprs, &strings, &trees)?;
+        check_lengths(&exprs, &strings, &trees, allow_empty_arrays)?;
 
         Ok(Self {
-            input_order: input_order.ok_or(AppError::NoInputOrder)?,
+            input_order,
             exprs,
+            strings,
+            trees,
+            exclusions,
             input_separator,
             output_separator
         })
     }
+
+    /// List every declared name, in the order they are generated. A variable shared between
+    /// expressions is listed once, with the range fitting all of them.
+    ///
+    /// # Returns
+    /// A `Vec` containing a `VarListing` for each declared name.
+    pub fn list_vars(&self) -> Vec<VarListing> {
+        let mut listings: Vec<VarListing> = Vec::new();
+        for expr in &self.exprs {
+            for (depth, group) in expr.vars.iter().enumerate() {
+                let (min, max) = (expr.group_mins[depth], expr.group_maxes[depth]);
+                for var in group {
+                    let (name, kind) = match var {
+                        ExprVariable::Variable(key, _) | ExprVariable::Choice(key, _) => (key, VarKind::Variable),
+                        ExprVariable::Array(key, len, _) => (key, VarKind::Array(len.to_string())),
+                        ExprVariable::Matrix(key, rows, cols, _) => (key, VarKind::Matrix(rows.to_string(), cols.to_string())),
+                    };
+                    match listings.iter_mut().find(|listing| &listing.name == name) {
+                        Some(VarListing { bounds: Some((prev_min, prev_max)), .. }) => {
+                            *prev_min = (*prev_min).max(min);
+                            *prev_max = (*prev_max).min(max);
+                        }
+                        _ => listings.push(VarListing { name: name.clone(), kind, bounds: Some((min, max)) })
+                    }
+                }
+            }
+        }
+        for string in &self.strings {
+            listings.push(VarListing { name: string.name.clone(), kind: VarKind::String(string.len.to_string()), bounds: None });
+        }
+        for tree in &self.trees {
+            listings.push(VarListing { name: tree.name.clone(), kind: VarKind::Tree(tree.nodes.to_string()), bounds: None });
+        }
+        listings
+    }
 }
 
 #[cfg(test)]
@@ -241,12 +1020,15 @@ mod tests {
             Token::Comparison(ComparisonType::LessThanOrEqualTo), Token::NumValue(100000)]);
 
         let should_be = FuzzExpr {
-            contains_array: true,
             vars: vec![vec!["A[10]#".into()], vec!["C".into(), "D".into()]],
             comparisons: vec![ComparisonType::LessThan, ComparisonType::LessThanOrEqualTo, ComparisonType::LessThanOrEqualTo],
             const_min: 1,
             const_max: 100000,
             less_than_count: 1,
+            group_mins: vec![2, 2],
+            group_maxes: vec![100000, 100000],
+            open_max: false,
+            line: 0,
             repr: "1 < A[10]# <= C,D <= 100000".to_string()
         };
 
@@ -270,20 +1052,24 @@ mod tests {
 
     #[test]
     fn test_parse_valid_file() {
-        let mut file_string: Vec<String> = Vec::new();
-        file_string.push("# Comment".into());
-        file_string.push("".into());
-        file_string.push("".into());
-        file_string.push("1 < A[10]# <= C,D <= 100000".into());
-        file_string.push("input order: A C D".into());
+        let file_string: Vec<String> = vec![
+            "# Comment".into(),
+            "".into(),
+            "".into(),
+            "1 < A[10]# <= C,D <= 100000".into(),
+            "input order: A C D".into(),
+        ];
 
         let expr = FuzzExpr {
-            contains_array: true,
             vars: vec![vec!["A[10]#".into()], vec!["C".into(), "D".into()]],
             comparisons: vec![ComparisonType::LessThan, ComparisonType::LessThanOrEqualTo, ComparisonType::LessThanOrEqualTo],
             const_min: 1,
             const_max: 100000,
             less_than_count: 1,
+            group_mins: vec![2, 2],
+            group_maxes: vec![100000, 100000],
+            open_max: false,
+            line: 4,
             repr: "1 < A[10]# <= C,D <= 100000".to_string()
         };
 
@@ -291,76 +1077,589 @@ mod tests {
             output_separator: "\n".to_string(),
             input_separator: "\n".to_string(),
             exprs: vec![expr],
+            strings: vec![],
+            trees: vec![],
+            exclusions: vec![],
             input_order: vec!["A".into(), "C".into(), "D".into()]
         };
 
-        let result = FuzzData::parse("\n".into(), "\n".into(), file_string.into_iter()).unwrap();
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
 
         assert_eq!(result, should_be);
     }
 
+    #[test]
+    fn test_strip_comment() {
+        assert_eq!(strip_comment("# Comment"), "");
+        assert_eq!(strip_comment("1 <= N <= 100000   # array size"), "1 <= N <= 100000");
+        assert_eq!(strip_comment("1 <= A[N]# <= 100 #comment"), "1 <= A[N]# <= 100");
+        assert_eq!(strip_comment("1 <= A[N]# <= 100"), "1 <= A[N]# <= 100");
+    }
+
+    #[test]
+    fn test_parse_trailing_comments() {
+        let file_string: Vec<String> = vec![
+            "1 <= N <= 100000   # array size".into(),
+            "1 <= A[N]# <= 100 # the array".into(),
+            "input order: N A # N first".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+
+        assert_eq!(result.input_order, vec!["N".to_string(), "A".to_string()]);
+        assert_eq!(result.exprs[0].repr, "1 <= N <= 100000");
+        assert_eq!(result.exprs[1].repr, "1 <= A[N]# <= 100");
+    }
+
+    #[test]
+    fn test_parse_defines() {
+        let file_string: Vec<String> = vec![
+            "define MAXN 10^5".into(),
+            "define LOW -5".into(),
+            "1 <= N <= MAXN".into(),
+            "LOW <= A[MAXN]# <= 100".into(),
+            "input order: N A".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+
+        assert_eq!(result.exprs[0].const_max, 100000);
+        assert_eq!(result.exprs[1].const_min, -5);
+        assert_eq!(result.exprs[1].vars, vec![vec![ExprVariable::Array("A".into(), LenExpr::Constant(100000), VarFlags::default())]]);
+        assert_eq!(result.exprs[1].repr, "LOW <= A[MAXN]# <= 100");
+    }
+
+    #[test]
+    fn test_parse_strings() {
+        let file_string: Vec<String> = vec![
+            "define LEN 5".into(),
+            "1 <= N <= 100".into(),
+            "S{N} T{LEN}".into(),
+            "P{N}@palindrome".into(),
+            "input order: N S T P".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+
+        assert_eq!(result.strings, vec![
+            FuzzString { name: "S".into(), len: LenExpr::Variable("N".into()), palindrome: false, line: 3, repr: "S{N}".into() },
+            FuzzString { name: "T".into(), len: LenExpr::Constant(5), palindrome: false, line: 3, repr: "T{LEN}".into() },
+            FuzzString { name: "P".into(), len: LenExpr::Variable("N".into()), palindrome: true, line: 4, repr: "P{N}@palindrome".into() },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_input_order_commas() {
+        let file_string: Vec<String> = vec!["1 <= A,C,D <= 10".into(), "input order:  A,  C ,D, ".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+        assert_eq!(result.input_order, vec!["A", "C", "D"]);
+
+        assert_eq!(parse_input_order(" A B, C"), Some(vec!["A".into(), "B".into(), "C".into()]));
+        assert_eq!(parse_input_order(""), None);
+        assert_eq!(parse_input_order(" , "), None);
+        assert_eq!(parse_input_order("A,,C"), None);
+        assert_eq!(parse_input_order(", A"), None);
+
+        let file_string: Vec<String> = vec!["1 <= A,C <= 10".into(), "input order: A, ,C".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::InvalidSyntax(2, "input order: A, ,C".into()));
+
+        // An empty order used to get through, and the input builder then underflowed.
+        let file_string: Vec<String> = vec!["1 <= N <= 5".into(), "input order:".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::InvalidSyntax(2, "input order:".into()));
+    }
+
+    #[test]
+    fn test_parse_separators() {
+        let file_string: Vec<String> = vec!["input separator: \\t".into(), "1 <= N <= 10".into(), "input order: N".into()];
+        let result = FuzzData::parse("\n".into(), " ".into(), file_string).unwrap();
+        assert_eq!(result.input_separator, "\t");
+        assert_eq!(result.output_separator, " ");
+
+        let file_string: Vec<String> = vec!["output separator: \\s".into(), "input separator: \\n\\t".into(), "1 <= N <= 10".into(), "input order: N".into()];
+        let result = FuzzData::parse(" ".into(), "\n".into(), file_string).unwrap();
+        assert_eq!(result.input_separator, "\n\t");
+        assert_eq!(result.output_separator, " ");
+
+        let file_string: Vec<String> = vec!["input separator: \\q".into(), "1 <= N <= 10".into(), "input order: N".into()];
+        let result = FuzzData::parse(" ".into(), " ".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::InvalidSyntax(1, "input separator: \\q".into()));
+
+        let file_string: Vec<String> = vec!["input separator: ,".into(), "input separator: ;".into(), "1 <= N <= 10".into(), "input order: N".into()];
+        let result = FuzzData::parse(" ".into(), " ".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::DoubleDeclaration("input separator".into()));
+    }
+
+    #[test]
+    fn test_parse_length_range() {
+        let parse = |lines: &[&str]| FuzzData::parse("\n".into(), "\n".into(), lines.iter().map(|line| line.to_string()));
+
+        // Lengths from a plain variable.
+        let result = parse(&["0 <= N <= 10", "1 <= A[N]# <= 5", "input order: N A"]).unwrap_err();
+        assert_eq!(result, AppError::InvalidLengthRange(0, "1 <= A[N]# <= 5".into()));
+        assert!(parse(&["0 < N <= 10", "1 <= A[N]# <= 5", "input order: N A"]).is_ok());
+        assert!(parse(&["0 <= M < N <= 10", "1 <= A[N]# <= 5", "input order: N A"]).is_ok());
+
+        // Lengths from a variable with an offset.
+        let result = parse(&["1 <= N <= 10", "1 <= B[N][N-2]# <= 5", "input order: N B"]).unwrap_err();
+        assert_eq!(result, AppError::InvalidLengthRange(-1, "1 <= B[N][N-2]# <= 5".into()));
+        let result = parse(&["1 <= N <= 10", "S{N-1}", "input order: N S"]).unwrap_err();
+        assert_eq!(result, AppError::InvalidLengthRange(0, "S{N-1}".into()));
+        assert!(parse(&["-1 <= N <= 10", "tree G[N+2]#", "input order: N G"]).is_ok());
+
+        // A shared variable takes the highest lower bound.
+        assert!(parse(&["0 <= N <= 10", "1 <= N <= 5", "1 <= A[N]# <= 5", "input order: N A"]).is_ok());
+
+        // Empty arrays, but never negative lengths or empty matrices, when allowed.
+        let parse_empty = |lines: &[&str]| {
+            let options = ParseOptions { allow_empty_arrays: true, ..Default::default() };
+            FuzzData::parse_with("\n".into(), "\n".into(), lines.iter().map(|line| line.to_string()), options)
+        };
+        assert!(parse_empty(&["0 <= N <= 10", "1 <= A[N]# <= 5", "input order: N A"]).is_ok());
+        let result = parse_empty(&["0 <= N <= 10", "1 <= A[N-1]# <= 5", "input order: N A"]).unwrap_err();
+        assert_eq!(result, AppError::InvalidLengthRange(-1, "1 <= A[N-1]# <= 5".into()));
+        let result = parse_empty(&["0 <= N <= 10", "1 <= B[N][2]# <= 5", "input order: N B"]).unwrap_err();
+        assert_eq!(result, AppError::InvalidLengthRange(0, "1 <= B[N][2]# <= 5".into()));
+    }
+
+    #[test]
+    fn test_parse_exclusions() {
+        let file_string: Vec<String> = vec!["1 <= U,V <= 10".into(), "U != V".into(), "input order: U V".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+        assert_eq!(result.exclusions, vec![FuzzExclusion { left: "U".into(), right: "V".into(), line: 2, repr: "U != V".into() }]);
+
+        let file_string: Vec<String> = vec!["1 <= U <= 10".into(), "U != W".into(), "input order: U".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::UndeclaredVariable("W".into()));
+
+        let file_string: Vec<String> = vec!["1 <= U <= 10".into(), "U != U".into(), "input order: U".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::InvalidExpression(2, "U != U".into()));
+    }
+
+    #[test]
+    fn test_expr_builder() {
+        use crate::parser::tokenizer::LenOp;
+        use ComparisonType::{LessThan, LessThanOrEqualTo};
+
+        let parsed = |line: &str| parse_expr_from_line(line, &mut tokenize_expr_line(line).unwrap()).unwrap();
+        let distinct = VarFlags { distinct: true, ..Default::default() };
+        let built = FuzzExprBuilder::new(0)
+            .group(LessThan, vec![ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), distinct)])
+            .group(LessThanOrEqualTo, vec!["B".into(), "C".into()])
+            .build(LessThan, 100)
+            .unwrap();
+        assert_eq!(built, parsed("0 < A[N]#! <= B,C < 100"));
+
+        let sorted = VarFlags { sorted: true, sum_cap: Some(1000), ..Default::default() };
+        let built = FuzzExprBuilder::new(-5)
+            .group(LessThanOrEqualTo, vec![ExprVariable::Matrix("M".into(), LenExpr::Constant(3), LenExpr::Expr("N".into(), LenOp::Sub, 1), VarFlags::default())])
+            .group(LessThanOrEqualTo, vec![ExprVariable::Array("S".into(), LenExpr::Constant(4), sorted)])
+            .build(LessThanOrEqualTo, 50)
+            .unwrap();
+        assert_eq!(built, parsed("-5 <= M[3][N-1]# <= S[4]#+sum(1000) <= 50"));
+
+        let result = FuzzExprBuilder::new(0).group(LessThan, vec!["A".into()]).group(LessThan, vec!["B".into()]).build(LessThan, 2);
+        assert!(matches!(result, Err(AppError::RangeTooSmall { needed: 4, .. })));
+        let result = FuzzExprBuilder::new(10).group(LessThanOrEqualTo, vec!["A".into()]).build(LessThanOrEqualTo, 5);
+        assert_eq!(result.unwrap_err(), AppError::InvalidSyntax(0, "10 <= A <= 5".into()));
+        let result = FuzzExprBuilder::new(0).build(LessThanOrEqualTo, 5);
+        assert_eq!(result.unwrap_err(), AppError::InvalidSyntax(0, "0 <= 5".into()));
+        let log_distinct = VarFlags { distinct: true, log: true, ..Default::default() };
+        let result = FuzzExprBuilder::new(1).group(LessThanOrEqualTo, vec![ExprVariable::Variable("A".into(), log_distinct)]).build(LessThanOrEqualTo, 5);
+        assert!(matches!(result, Err(AppError::InvalidSyntax(0, _))));
+    }
+
+    #[test]
+    fn test_parse_trees() {
+        let file_string: Vec<String> = vec![
+            "define NODES 5".into(),
+            "1 <= N <= 100".into(),
+            "tree G[N]#".into(),
+            "tree H[NODES]#".into(),
+            "input order: N G H".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+
+        assert_eq!(result.trees, vec![
+            FuzzTree { name: "G".into(), nodes: LenExpr::Variable("N".into()), line: 3, repr: "tree G[N]#".into() },
+            FuzzTree { name: "H".into(), nodes: LenExpr::Constant(5), line: 4, repr: "tree H[NODES]#".into() },
+        ]);
+
+        let file_string: Vec<String> = vec!["tree G[M]#".into(), "input order: G".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::UndeclaredVariable("M".into()));
+
+        let file_string: Vec<String> = vec!["tree G[N]#!".into(), "input order: G".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::InvalidSyntax(1, "tree G[N]#!".into()));
+    }
+
+    #[test]
+    fn test_parse_undeclared_input_order() {
+        let file_string: Vec<String> = vec![
+            "1 <= N <= 100".into(),
+            "1 <= A[N]# <= 100".into(),
+            "input order: N A B".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+
+        assert_eq!(result, AppError::UndeclaredVariable("B".into()));
+    }
+
+    #[test]
+    fn test_parse_double_declaration() {
+        let file_string: Vec<String> = vec![
+            "1 <= A <= 100".into(),
+            "1 <= B <= A[5]# <= 10".into(),
+            "input order: A B".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+
+        assert_eq!(result, AppError::DoubleDeclaration("A".into()));
+
+        let file_string: Vec<String> = vec!["1 <= A <= B <= A <= 10".into(), "input order: A B".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::DoubleDeclaration("A".into()));
+
+        let file_string: Vec<String> = vec!["1 <= A <= 10".into(), "A{3}".into(), "input order: A".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::DoubleDeclaration("A".into()));
+    }
+
+    #[test]
+    fn test_parse_shared_variable() {
+        let file_string: Vec<String> = vec![
+            "1 <= B[N]# <= 100".into(),
+            "1 <= N <= 100".into(),
+            "1 <= M <= N <= 10".into(),
+            "input order: N M B".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+        let reprs: Vec<&str> = result.exprs.iter().map(|expr| expr.repr.as_str()).collect();
+
+        // The array waits for every expression generating its length.
+        assert_eq!(reprs, vec!["1 <= N <= 100", "1 <= M <= N <= 10", "1 <= B[N]# <= 100"]);
+    }
+
+    #[test]
+    fn test_parse_intervals() {
+        let parse_exprs = |lines: &[&str]| FuzzData::parse("\n".into(), "\n".into(), lines.iter().map(|line| line.to_string())).map(|data| data.exprs);
+        let chains = parse_exprs(&["define MAXV 100", "1 <= A <= MAXV", "0 < B < MAXV", "1 <= C < MAXV", "0 < D <= MAXV", "input order: A B C D"]).unwrap();
+        let intervals = parse_exprs(&["define MAXV 100", "A in [1, MAXV]", "B in (0, MAXV)", "C in [1, MAXV)", "D in (0, MAXV]", "input order: A B C D"]).unwrap();
+        for (chain, interval) in chains.iter().zip(&intervals) {
+            assert_eq!((chain.const_min, chain.const_max, chain.less_than_count), (interval.const_min, interval.const_max, interval.less_than_count));
+            assert_eq!((&chain.vars, &chain.comparisons, &chain.group_maxes), (&interval.vars, &interval.comparisons, &interval.group_maxes));
+        }
+        assert_eq!(intervals[1].repr, "B in (0, MAXV)");
+
+        let result = parse_exprs(&["A in [100, 1]", "input order: A"]).unwrap_err();
+        assert_eq!(result, AppError::InvalidSyntax(1, "A in [100, 1]".into()));
+        let result = parse_exprs(&["A in (1, 2)", "input order: A"]).unwrap_err();
+        assert_eq!(result, AppError::RangeTooSmall { line: 1, expr: "A in (1, 2)".into(), min: 1, max: 2, needed: 3 });
+    }
+
+    #[test]
+    fn test_parse_choices() {
+        let parse = |lines: &[&str]| FuzzData::parse("\n".into(), "\n".into(), lines.iter().map(|line| line.to_string()));
+        // Declared after the array it is the length of, so it gets ordered first.
+        let data = parse(&["1 <= A[K]# <= 10", "K in {1, 2,4,8}", "1 <= B <= 10", "B != K", "input order: K A B"]).unwrap();
+        let choice = &data.exprs[0];
+        assert_eq!(choice.vars, vec![vec![ExprVariable::Choice("K".into(), vec![1, 2, 4, 8])]]);
+        assert_eq!((choice.const_min, choice.const_max, &choice.group_mins, &choice.group_maxes), (1, 8, &vec![1], &vec![8]));
+        assert_eq!(choice.repr, "K in {1, 2,4,8}");
+
+        // A listed value shorter than 1 is no array length.
+        let result = parse(&["K in {0, 2}", "1 <= A[K]# <= 10", "input order: K A"]).unwrap_err();
+        assert_eq!(result, AppError::InvalidLengthRange(0, "1 <= A[K]# <= 10".into()));
+        // Any other expression generating it could pick an unlisted value.
+        assert_eq!(parse(&["K in {1, 2}", "1 <= K <= 2", "input order: K"]).unwrap_err(), AppError::DoubleDeclaration("K".into()));
+        assert_eq!(parse(&["1 <= K <= 2", "K in {1, 2}", "input order: K"]).unwrap_err(), AppError::DoubleDeclaration("K".into()));
+        assert_eq!(parse(&["K in {1, 2}", "K in {3}", "input order: K"]).unwrap_err(), AppError::DoubleDeclaration("K".into()));
+        assert_eq!(parse(&["K in {1, X}", "input order: K"]).unwrap_err(), AppError::InvalidExpression(1, "K in {1, X}".into()));
+    }
+
+    #[test]
+    fn test_parse_open_chain() {
+        let file_string: Vec<String> = vec![
+            "1 <= A < B".into(),
+            "0 <= C <= A".into(),
+            "2 <= B <= 50".into(),
+            "1 <= B <= 100".into(),
+            "input order: A B C".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+        let reprs: Vec<&str> = result.exprs.iter().map(|expr| expr.repr.as_str()).collect();
+        assert_eq!(reprs, vec!["2 <= B <= 50", "1 <= B <= 100", "1 <= A < B", "0 <= C <= A"]);
+
+        // `B` has to fit both of its expressions, and `A` stays below it.
+        let chain = &result.exprs[2];
+        assert!(chain.open_max);
+        assert_eq!((chain.const_max, chain.less_than_count), (50, 1));
+        assert_eq!(chain.group_maxes, vec![49, 50]);
+        assert_eq!(result.exprs[3].const_max, 49);
+
+        // Only plain variables can close a chain.
+        let file_string: Vec<String> = vec!["1 <= N <= 10".into(), "1 <= A <= B[N]#".into(), "input order: N A B".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::InvalidSyntax(2, "1 <= A <= B[N]#".into()));
+    }
+
+    #[test]
+    fn test_parse_open_chain_unknown_bound() {
+        let file_string: Vec<String> = vec!["1 <= A <= B".into(), "1 <= C <= B".into(), "input order: A B C".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::UnknownBound("B".into(), "1 <= A <= B".into()));
+
+        let file_string: Vec<String> = vec!["1 <= A <= B".into(), "1 <= B <= A".into(), "input order: A B".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::UnknownBound("B".into(), "1 <= A <= B".into()));
+
+        // `B` is declared nowhere else.
+        let file_string: Vec<String> = vec!["1 <= A <= B".into(), "input order: A".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::UnknownBound("B".into(), "1 <= A <= B".into()));
+
+        // No room below `B` for `A`.
+        let file_string: Vec<String> = vec!["1 <= A < B".into(), "1 <= B <= 1".into(), "input order: A B".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::RangeTooSmall { line: 1, expr: "1 <= A < B".into(), min: 1, max: 1, needed: 2 });
+    }
+
+    #[test]
+    fn test_parse_length_declared_later() {
+        let file_string: Vec<String> = vec![
+            "1 <= A[N]# <= 100".into(),
+            "1 <= B[M][N]# <= 100".into(),
+            "1 <= N <= M <= 10".into(),
+            "input order: N M A B".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+        let reprs: Vec<&str> = result.exprs.iter().map(|expr| expr.repr.as_str()).collect();
+
+        assert_eq!(reprs, vec!["1 <= N <= M <= 10", "1 <= A[N]# <= 100", "1 <= B[M][N]# <= 100"]);
+    }
+
+    #[test]
+    fn test_parse_length_cycle() {
+        let file_string: Vec<String> = vec![
+            "1 <= N <= A[M]# <= 100".into(),
+            "1 <= M <= B[N]# <= 100".into(),
+            "input order: N M A B".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::CyclicDependency("1 <= N <= A[M]# <= 100".into()));
+
+        let file_string: Vec<String> = vec![
+            "0 <= A[N]# <= N <= 2000".into(),
+            "input order: N A".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::CyclicDependency("0 <= A[N]# <= N <= 2000".into()));
+    }
+
+    #[test]
+    fn test_parse_undeclared_length() {
+        let file_string: Vec<String> = vec![
+            "1 <= A[N]# <= 100".into(),
+            "input order: A".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::UndeclaredVariable("N".into()));
+    }
+
+    #[test]
+    fn test_parse_redefine() {
+        let file_string: Vec<String> = vec![
+            "define MAXN 10^5".into(),
+            "define MAXN 10".into(),
+            "input order: N".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+
+        assert_eq!(result, AppError::DoubleDeclaration("MAXN".into()));
+    }
+
+    #[test]
+    fn test_parse_undefined_name() {
+        let file_string: Vec<String> = vec![
+            "define MAXN 10^5".into(),
+            "1 <= N <= MAXM".into(),
+            "input order: N".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+
+        assert_eq!(result, AppError::UnknownBound("MAXM".into(), "1 <= N <= MAXM".into()));
+    }
+
     #[test]
     fn test_parse_invalid_max_bigger_than_min() {
-        let mut file_string: Vec<String> = Vec::new();
-        file_string.push("# Comment".into());
-        file_string.push("".into());
-        file_string.push("1000 < A[10]# <= C,D <= 1".into());
-        file_string.push("input order: A C D".into());
+        let file_string: Vec<String> = vec![
+            "# Comment".into(),
+            "".into(),
+            "1000 < A[10]# <= C,D <= 1".into(),
+            "input order: A C D".into(),
+        ];
 
-        let result = FuzzData::parse("\n".into(), "\n".into(), file_string.into_iter()).unwrap_err();
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
 
         assert_eq!(result, AppError::InvalidSyntax(3, "1000 < A[10]# <= C,D <= 1".into()));
     }
 
     #[test]
     fn test_parse_invalid_file_syntax() {
-        let mut file_string: Vec<String> = Vec::new();
-        file_string.push("# Comment".into());
-        file_string.push("".into());
-        file_string.push("()".into()); // Cannot get tokenized!
-        file_string.push("1 < A[10]# <= C,D <= 100000".into());
-        file_string.push("input order: A C D".into());
+        let file_string: Vec<String> = vec![
+            "# Comment".into(),
+            "".into(),
+            "()".into(), // Cannot get tokenized!
+            "1 < A[10]# <= C,D <= 100000".into(),
+            "input order: A C D".into(),
+        ];
 
-        let result = FuzzData::parse("\n".into(), "\n".into(), file_string.into_iter()).unwrap_err();
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
 
         assert_eq!(result, AppError::InvalidExpression(3, "()".into()));
     }
 
     #[test]
     fn test_parse_invalid_range() {
-        let mut file_string: Vec<String> = Vec::new();
-        file_string.push("# Comment".into());
-        file_string.push("".into());
-        file_string.push("0 < A < B < 2".into());
-        file_string.push("input order: A C D".into());
+        let file_string: Vec<String> = vec![
+            "# Comment".into(),
+            "".into(),
+            "0 < A < B < 2".into(),
+            "input order: A C D".into(),
+        ];
 
-        let result = FuzzData::parse("\n".into(), "\n".into(), file_string.into_iter()).unwrap_err();
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
 
-        assert_eq!(result, AppError::InvalidSyntax(3, "0 < A < B < 2".into()));
+        assert_eq!(result, AppError::RangeTooSmall { line: 3, expr: "0 < A < B < 2".into(), min: 0, max: 2, needed: 4 });
+        assert_eq!(result.to_string(), "Range from 0 to 2 is too small at line 3: `0 < A < B < 2` needs 4 distinct values, but only 3 are available");
     }
 
     #[test]
     fn test_parse_invalid_file_expr() {
-        let mut file_string: Vec<String> = Vec::new();
-        file_string.push("# Comment".into());
-        file_string.push("".into());
-        file_string.push("< A[10]# <= C,D <= 100000 <".into()); // Can be tokenized but cannot be parsed
-        file_string.push("input order: A C D".into());
+        let file_string: Vec<String> = vec![
+            "# Comment".into(),
+            "".into(),
+            "< A[10]# <= C,D <= 100000 <".into(), // Can be tokenized but cannot be parsed
+            "input order: A C D".into(),
+        ];
 
-        let result = FuzzData::parse("\n".into(), "\n".into(), file_string.into_iter()).unwrap_err();
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
 
         assert_eq!(result, AppError::InvalidSyntax(3, "< A[10]# <= C,D <= 100000 <".into()));
     }
 
+    #[test]
+    fn test_list_vars() {
+        let file_string: Vec<String> = vec![
+            "define MAXN 10".into(),
+            "2 <= N < MAXN".into(),
+            "1 <= N,M <= 5".into(),
+            "0 < A[N-1]# < B[N][M]# <= 100".into(),
+            "S{M}".into(),
+            "tree G[N]#".into(),
+            "input order: N M A B S G".into(),
+        ];
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap();
+        assert_eq!(result.list_vars(), vec![
+            VarListing { name: "N".into(), kind: VarKind::Variable, bounds: Some((2, 5)) },
+            VarListing { name: "M".into(), kind: VarKind::Variable, bounds: Some((1, 5)) },
+            VarListing { name: "A".into(), kind: VarKind::Array("N-1".into()), bounds: Some((1, 99)) },
+            VarListing { name: "B".into(), kind: VarKind::Matrix("N".into(), "M".into()), bounds: Some((2, 100)) },
+            VarListing { name: "S".into(), kind: VarKind::String("M".into()), bounds: None },
+            VarListing { name: "G".into(), kind: VarKind::Tree("N".into()), bounds: None },
+        ]);
+        assert_eq!(result.list_vars()[3].length(), Some("N x M".into()));
+    }
+
+    #[test]
+    fn test_parse_no_expressions() {
+        let file_string: Vec<String> = vec!["# Nothing but an input order".into(), "input order: N".into()];
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::NoExpressions);
+
+        let file_string: Vec<String> = vec!["S{5}".into(), "input order: S".into()];
+        assert!(FuzzData::parse("\n".into(), "\n".into(), file_string).is_ok());
+    }
+
+    #[test]
+    fn test_parse_default_max() {
+        let file_string: Vec<String> = vec!["1 <= N".into(), "0 < A[N]#,B <= 50".into(), "input order: N A B".into()];
+        let options = ParseOptions { default_max: Some(1000000), ..Default::default() };
+        let result = FuzzData::parse_with("\n".into(), "\n".into(), file_string.clone(), options).unwrap();
+        assert_eq!(result.exprs[0].const_max, 1000000);
+        assert_eq!(result.exprs[0].repr, "1 <= N");
+        assert_eq!(result.exprs[1].const_max, 50);
+
+        let result = FuzzData::parse("\n".into(), "\n".into(), file_string).unwrap_err();
+        assert_eq!(result, AppError::InvalidSyntax(1, "1 <= N".into()));
+    }
+
+    #[test]
+    fn test_parse_crlf() {
+        let lines = ["define MAXN 100", "# Comment", "", "1 <= N <= MAXN", "S{N}", "input separator: \\s", "input order: N S"];
+        let result = FuzzData::parse("\n".into(), "\n".into(), lines.map(|line| format!("{}\r", line))).unwrap();
+        assert_eq!(result, FuzzData::parse("\n".into(), "\n".into(), lines.map(String::from)).unwrap());
+        assert_eq!(result.exprs[0].const_max, 100);
+        assert_eq!(result.input_order, vec!["N", "S"]);
+    }
+
+    #[test]
+    fn test_parse_traced() {
+        let file_string: Vec<String> = vec!["1 <= N <= 10".into(), "1 <= A[N]# <=".into(), "input order: N A".into()];
+        let mut out = Vec::new();
+        let options = ParseOptions { tracer: Some(ParseTracer::new(&mut out)), ..Default::default() };
+        let result = FuzzData::parse_with("\n".into(), "\n".into(), file_string, options).unwrap_err();
+        assert_eq!(result, AppError::InvalidSyntax(2, "1 <= A[N]# <=".into()));
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("line 1: tokens [NumValue(1), Comparison(LessThanOrEqualTo), VariableGroup([Variable(\"N\", "));
+        assert!(out.contains("line 1: expression FuzzExpr { const_min: 1, const_max: 10, "));
+        assert!(out.contains("line 2: the tokens don't form an expression"));
+    }
+
+    #[test]
+    fn test_parse_lte_alias_warning() {
+        let file_string: Vec<String> = vec!["1 =< N =< 10".into(), "1 <= A[N]# =< 100".into(), "input order: N A".into()];
+        let mut out = Vec::new();
+        let options = ParseOptions { warnings: Some(&mut out), ..Default::default() };
+        let result = FuzzData::parse_with("\n".into(), "\n".into(), file_string.clone(), options).unwrap();
+        assert!(result.exprs.iter().flat_map(|expr| &expr.comparisons).all(|comparison| *comparison == ComparisonType::LessThanOrEqualTo));
+        assert_eq!(String::from_utf8(out).unwrap(), "Warning: line 1: `=<` is read as `<=`, which is the canonical form\n");
+
+        // Once per parse, not once per process.
+        let mut out = Vec::new();
+        let options = ParseOptions { warnings: Some(&mut out), ..Default::default() };
+        FuzzData::parse_with("\n".into(), "\n".into(), file_string, options).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+    }
+
     #[test]
     fn test_display_repr() {
         let expression = FuzzExpr {
-            contains_array: true,
             vars: vec![vec!["A[10]#".into()], vec!["C".into(), "D".into()]],
             comparisons: vec![ComparisonType::LessThan, ComparisonType::LessThanOrEqualTo, ComparisonType::LessThanOrEqualTo],
             const_min: 1,
             const_max: 100000,
             less_than_count: 1,
+            group_mins: vec![2, 2],
+            group_maxes: vec![100000, 100000],
+            open_max: false,
+            line: 0,
             repr: "1 < A[10]# <= C,D <= 100000".to_string()
         };
 
@@ -2,41 +2,160 @@
 //! tokenization, they only return `Option<T>`s and the caller can return an `AppError` when it
 //! encounters an error with the entire context information known.
 
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fmt::Display};
 
 // Who knows maybe someday they'll change, right?
 const LESS_THAN: &str = "<";
 const LESS_THAN_OR_EQUAL_TO: &str = "<=";
+/// A common typo of `LESS_THAN_OR_EQUAL_TO`, accepted with a warning.
+const LESS_THAN_OR_EQUAL_TO_ALIAS: &str = "=<";
+const POWER: char = '^';
+const NEGATIVE: char = '-';
+const DISTINCT_MARKER: &str = "!";
+const SORTED_MARKER: &str = "+";
+const STRICTLY_SORTED_MARKER: &str = "++";
+const STRING_LEN_START: char = '{';
+const STRING_LEN_END: char = '}';
+const LOG_MARKER: &str = "@log";
+const PALINDROME_MARKER: &str = "@palindrome";
+const SUM_CAP_NAME: &str = "sum";
+const WRAP_NAME: &str = "wrap";
+const MARKER_ARG_START: char = '(';
+const MARKER_ARG_END: &str = ")";
+const NOT_EQUAL: &str = "!=";
+const GROUP_SEPARATOR: char = ',';
+const INTERVAL_KEYWORD: &str = " in ";
+const INCLUSIVE_START: char = '[';
+const EXCLUSIVE_START: char = '(';
+const INCLUSIVE_END: char = ']';
+const EXCLUSIVE_END: char = ')';
+const CHOICES_START: char = '{';
+const CHOICES_END: char = '}';
 
-pub(crate) type VariableGroup = Vec<ExprVariable>;
+pub type VariableGroup = Vec<ExprVariable>;
 
-#[derive(Debug)]
+/// The type of every value: bounds, lengths and generated numbers. Wide enough for values past
+/// `i64`, but those are only accepted with `ParseOptions::bigint`.
+pub type Value = i128;
+
+#[derive(Debug, Clone, Copy)]
 #[derive(PartialEq)]
 /// Comparison type.
-pub(crate) enum ComparisonType {
+pub enum ComparisonType {
     LessThan,
     LessThanOrEqualTo
 }
 
+impl Display for ComparisonType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LessThan => write!(f, "{}", LESS_THAN),
+            Self::LessThanOrEqualTo => write!(f, "{}", LESS_THAN_OR_EQUAL_TO),
+        }
+    }
+}
+
+/// Arithmetic operation applied to a variable in an array's length, e.g the `-` in `A[N-1]#`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum LenOp {
+    Add,
+    Sub
+}
+
 /// Enum specifically representing the type of expression used for an array variable's length. For
-/// example, `N` is treated as a `Variable` and `100` is treated as a `Constant`.
-#[derive(PartialEq, Debug)]
-pub(crate) enum LenExpr {
+/// example, `N` is treated as a `Variable`, `100` is treated as a `Constant` and `N-1` is treated
+/// as an `Expr`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum LenExpr {
     Variable(String),
-    Constant(i64)
+    Constant(Value),
+    /// A variable with a constant offset, e.g `N-1` or `N+2`.
+    Expr(String, LenOp, Value)
+}
+
+impl LenExpr {
+    /// The variable the length depends on, if any.
+    pub(crate) fn variable(&self) -> Option<&str> {
+        match self {
+            Self::Variable(key) | Self::Expr(key, _, _) => Some(key),
+            Self::Constant(_) => None
+        }
+    }
+
+    /// Compute the length.
+    ///
+    /// # Arguments
+    /// - `get_var`: looks up a variable's value
+    ///
+    /// # Returns
+    /// An `Option` containing the length, or `None` when the variable has no value or the
+    /// arithmetic overflows.
+    pub(crate) fn resolve(&self, get_var: impl Fn(&str) -> Option<Value>) -> Option<Value> {
+        match self {
+            Self::Variable(key) => get_var(key),
+            Self::Constant(val) => Some(*val),
+            Self::Expr(key, LenOp::Add, offset) => get_var(key)?.checked_add(*offset),
+            Self::Expr(key, LenOp::Sub, offset) => get_var(key)?.checked_sub(*offset),
+        }
+    }
 }
 
-#[derive(PartialEq, Debug)]
+impl Display for LenExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Variable(key) => write!(f, "{}", key),
+            Self::Constant(val) => write!(f, "{}", val),
+            Self::Expr(key, LenOp::Add, offset) => write!(f, "{}+{}", key, offset),
+            Self::Expr(key, LenOp::Sub, offset) => write!(f, "{}-{}", key, offset),
+        }
+    }
+}
+
+/// Generation constraints attached to a variable through trailing markers, e.g `A[N]#!`.
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub struct VarFlags {
+    /// Marked with a trailing `!`. For arrays, every element is pairwise distinct. For variables,
+    /// the value is distinct from every other distinct variable of the same variable group (so
+    /// `A!,B!,C!` are pairwise distinct).
+    pub distinct: bool,
+
+    /// Marked with a trailing `+`. Only valid for arrays: the elements are emitted in
+    /// non-decreasing order. Combined with `distinct` (written as `++` or `+!`), the elements are
+    /// strictly increasing.
+    pub sorted: bool,
+
+    /// Marked with a trailing `@log`. Values are picked log-uniformly instead of uniformly, so
+    /// small values (relative to the range's minimum) come up far more often in huge ranges. Not
+    /// valid together with `distinct`.
+    pub log: bool,
+
+    /// Written as a trailing `sum(CAP)`, e.g `A[N]#+sum(10^6)`. Only valid for arrays that aren't
+    /// distinct: the sum of the elements never exceeds `CAP`.
+    pub sum_cap: Option<Value>,
+
+    /// Written as a trailing `wrap(K)`, e.g `A[N]#wrap(3)`. Only valid for arrays: the elements
+    /// are emitted `K` per line instead of all on one.
+    pub wrap: Option<usize>
+}
+
+#[derive(PartialEq, Debug, Clone)]
 /// Representation of a variable used in expressions.
-pub(crate) enum ExprVariable {
-    /// An array variable. Contains a `String` which represents its string representation and a
-    /// `LenExpr` representing the length of the array.
-    Array(String, LenExpr),
-    /// A variable holding single value.
-    Variable(String)
+pub enum ExprVariable {
+    /// An array variable. Contains a `String` which represents its string representation, a
+    /// `LenExpr` representing the length of the array and its `VarFlags`.
+    Array(String, LenExpr, VarFlags),
+    /// A variable holding single value, along with its `VarFlags`.
+    Variable(String, VarFlags),
+    /// A two-dimensional array variable, e.g `A[N][M]#`. Contains a `String` which represents its
+    /// string representation, `LenExpr`s representing the amount of rows and columns and its
+    /// `VarFlags`, which apply to the elements in row-major order.
+    Matrix(String, LenExpr, LenExpr, VarFlags),
+    /// A variable holding a single value picked from a list, e.g `K in {1,2,4,8}`. Contains a
+    /// `String` which represents its string representation and the values, sorted and distinct.
+    Choice(String, Vec<Value>)
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 /// Token for parsing.
 pub(crate) enum Token {
     /// A comparison token, equivalent to either `<` or `<=`.
@@ -45,8 +164,48 @@ pub(crate) enum Token {
     /// A group of variable names.
     VariableGroup(VariableGroup),
 
-    /// A constant 64-bit integer.
-    NumValue(i64)
+    /// A constant integer (`Value`).
+    NumValue(Value)
+}
+
+/// Writes the variable the way it is declared in a spec, e.g `A[N]#+sum(100)`.
+impl Display for ExprVariable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flags = match self {
+            Self::Variable(key, flags) => {
+                write!(f, "{}", key)?;
+                flags
+            }
+            Self::Array(key, len, flags) => {
+                write!(f, "{}[{}]#", key, len)?;
+                flags
+            }
+            Self::Matrix(key, rows, cols, flags) => {
+                write!(f, "{}[{}][{}]#", key, rows, cols)?;
+                flags
+            }
+            Self::Choice(key, values) => {
+                let values: Vec<String> = values.iter().map(Value::to_string).collect();
+                return write!(f, "{}{}{}{}{}", key, INTERVAL_KEYWORD, CHOICES_START, values.join(&GROUP_SEPARATOR.to_string()), CHOICES_END)
+            }
+        };
+        match (flags.sorted, flags.distinct) {
+            (true, true) => write!(f, "{}", STRICTLY_SORTED_MARKER)?,
+            (true, false) => write!(f, "{}", SORTED_MARKER)?,
+            (false, true) => write!(f, "{}", DISTINCT_MARKER)?,
+            (false, false) => ()
+        }
+        if flags.log {
+            write!(f, "{}", LOG_MARKER)?;
+        }
+        if let Some(cap) = flags.sum_cap {
+            write!(f, "{}{}{}{}", SUM_CAP_NAME, MARKER_ARG_START, cap, MARKER_ARG_END)?;
+        }
+        if let Some(wrap) = flags.wrap {
+            write!(f, "{}{}{}{}", WRAP_NAME, MARKER_ARG_START, wrap, MARKER_ARG_END)?;
+        }
+        Ok(())
+    }
 }
 
 // Do not use for the app! Use the non-panicking function `string_to_variable` instead. This is a
@@ -60,6 +219,40 @@ impl From<&str> for ExprVariable {
     }
 }
 
+/// Try to parse the variable name used in an array's length.
+///
+/// # Arguments
+/// - `string`: input string
+///
+/// # Returns
+/// An `Option` containing the variable name if it is a valid name.
+fn string_to_len_variable(string: &str) -> Option<String> {
+    if string.bytes().next()?.is_ascii_alphabetic() && string.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'_') {
+        Some(string.into())
+    } else {
+        None
+    }
+}
+
+/// Try to parse the length of an array (or a matrix dimension), e.g the `N-1` in `A[N-1]#`.
+///
+/// # Arguments
+/// - `len`: input string
+///
+/// # Returns
+/// An `Option` containing the `LenExpr` if the length is valid.
+fn string_to_len_expr(len: &str) -> Option<LenExpr> {
+    if let Ok(x) = len.parse::<Value>() {
+        Some(LenExpr::Constant(x))
+    } else if let Some((key, offset)) = len.split_once('+') {
+        Some(LenExpr::Expr(string_to_len_variable(key)?, LenOp::Add, offset.parse().ok()?))
+    } else if let Some((key, offset)) = len.split_once('-') {
+        Some(LenExpr::Expr(string_to_len_variable(key)?, LenOp::Sub, offset.parse().ok()?))
+    } else {
+        Some(LenExpr::Variable(string_to_len_variable(len)?))
+    }
+}
+
 /// Try to parse a string into an expression variable.
 ///
 /// # Arguments
@@ -67,28 +260,112 @@ impl From<&str> for ExprVariable {
 ///
 /// # Returns
 /// An `Option` containing an `ExprVariable` if value is valid as a variable.
-fn string_to_variable(string: &str) -> Option<ExprVariable> {
+pub(crate) fn string_to_variable(string: &str) -> Option<ExprVariable> {
+    let mut flags = VarFlags::default();
+    let mut string = string;
+    // Markers taking an argument, e.g `sum(10^6)wrap(3)`, in any order.
+    while let Some(stripped) = string.strip_suffix(MARKER_ARG_END) {
+        let (stripped, arg) = stripped.rsplit_once(MARKER_ARG_START)?;
+        if let Some(stripped) = stripped.strip_suffix(SUM_CAP_NAME).filter(|_| flags.sum_cap.is_none()) {
+            flags.sum_cap = Some(string_to_num(arg)?);
+            string = stripped;
+        } else if let Some(stripped) = stripped.strip_suffix(WRAP_NAME).filter(|_| flags.wrap.is_none()) {
+            flags.wrap = Some(arg.parse().ok().filter(|wrap| *wrap > 0)?);
+            string = stripped;
+        } else {
+            return None
+        }
+    }
+
+    loop {
+        if let Some(stripped) = string.strip_suffix(LOG_MARKER) {
+            if flags.log {
+                return None
+            }
+            flags.log = true;
+            string = stripped;
+        } else if let Some(stripped) = string.strip_suffix(STRICTLY_SORTED_MARKER) {
+            if flags.sorted || flags.distinct {
+                return None
+            }
+            flags.sorted = true;
+            flags.distinct = true;
+            string = stripped;
+        } else if let Some(stripped) = string.strip_suffix(SORTED_MARKER) {
+            if flags.sorted {
+                return None
+            }
+            flags.sorted = true;
+            string = stripped;
+        } else if let Some(stripped) = string.strip_suffix(DISTINCT_MARKER) {
+            if flags.distinct {
+                return None
+            }
+            flags.distinct = true;
+            string = stripped;
+        } else {
+            break
+        }
+    }
+
+    if flags.log && flags.distinct {
+        return None
+    }
+
     if string.ends_with("]#") {
         let new_string = string.strip_suffix("]#")?.to_string();
         let split: Vec<&str> = new_string.split("[").collect();
+        if split.len() == 3 && flags.sum_cap.is_none() && flags.wrap.is_none() {
+            let rows = string_to_len_expr(split[1].strip_suffix("]")?)?;
+            let cols = string_to_len_expr(split[2])?;
+            return Some(ExprVariable::Matrix(split[0].into(), rows, cols, flags))
+        }
         if split.len() != 2 {
             return None
         }
-        let len = split[1];
-        let len_expr;
-        if let Ok(x) = len.parse::<i64>() {
-            len_expr = LenExpr::Constant(x);
-        } else {
-            len_expr = LenExpr::Variable(split[1].into())
+        if flags.sum_cap.is_some() && flags.distinct {
+            return None
         }
-        return Some(ExprVariable::Array(split[0].into(), len_expr))
-    } else if !(string.contains("[") || string.contains("]") || string.contains(" ")) {
-        return Some(ExprVariable::Variable(string.into()))
+        return Some(ExprVariable::Array(split[0].into(), string_to_len_expr(split[1])?, flags))
+    } else if !(flags.sorted || flags.sum_cap.is_some() || flags.wrap.is_some() || string.is_empty() || string.contains("[") || string.contains("]") || string.contains(" ") || string.contains(DISTINCT_MARKER) || string.contains(SORTED_MARKER)) {
+        return Some(ExprVariable::Variable(string.into(), flags))
     }
     None
 
 }
 
+/// Try to parse a string into a number. Besides plain integers, power-of notation (e.g `10^9`) is
+/// supported, and both may be negative (e.g `-10^9`, which is `-(10^9)`).
+///
+/// # Arguments
+/// - `string`: input string
+///
+/// # Returns
+/// An `Option` containing the number if the string is a valid number that fits in a `Value`.
+fn string_to_num(string: &str) -> Option<Value> {
+    let (negative, unsigned) = match string.strip_prefix(NEGATIVE) {
+        Some(unsigned) => (true, unsigned),
+        None => (false, string)
+    };
+
+    // Signs are handled above, so the parts themselves must be plain digits.
+    if !unsigned.bytes().all(|byte| byte.is_ascii_digit() || byte == POWER as u8) {
+        return None
+    }
+
+    let value = if let Some((base, exp)) = unsigned.split_once(POWER) {
+        base.parse::<Value>().ok()?.checked_pow(exp.parse::<u32>().ok()?)?
+    } else {
+        unsigned.parse::<Value>().ok()?
+    };
+
+    if negative {
+        value.checked_neg()
+    } else {
+        Some(value)
+    }
+}
+
 /// Tokenize a single value.
 ///
 /// # Arguments
@@ -99,18 +376,14 @@ fn string_to_variable(string: &str) -> Option<ExprVariable> {
 pub(crate) fn tokenize(item: &str) -> Option<Token> {
     if item == LESS_THAN {
         return Some(Token::Comparison(ComparisonType::LessThan))
-    } else if item == LESS_THAN_OR_EQUAL_TO {
+    } else if item == LESS_THAN_OR_EQUAL_TO || item == LESS_THAN_OR_EQUAL_TO_ALIAS {
         return Some(Token::Comparison(ComparisonType::LessThanOrEqualTo))
     }
 
     let mut item_iter = item.bytes();
-    let first = item_iter.nth(0)?;
-    if first.is_ascii_digit() {
-        if let Ok(result) = item.parse::<i64>() {
-            return Some(Token::NumValue(result))
-        } else {
-            return None
-        }
+    let first = item_iter.next()?;
+    if first.is_ascii_digit() || first == NEGATIVE as u8 {
+        return Some(Token::NumValue(string_to_num(item)?))
     }
 
     if first.is_ascii_alphabetic() {
@@ -127,6 +400,150 @@ pub(crate) fn tokenize(item: &str) -> Option<Token> {
     None
 }
 
+/// Tokenize the name and value of a `define NAME value` directive, with the `define` keyword
+/// already stripped. The value may use any notation a constant in an expression may use.
+///
+/// # Arguments
+/// - `line`: the directive's name and value, e.g `"MAXN 10^5"`
+///
+/// # Returns
+/// An `Option` containing the name and its value when the directive is valid.
+pub(crate) fn tokenize_define(line: &str) -> Option<(String, Value)> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() != 2 || !parts[0].bytes().next()?.is_ascii_alphabetic() {
+        return None
+    }
+
+    match string_to_variable(parts[0])? {
+        ExprVariable::Variable(name, flags) if flags == VarFlags::default() => Some((name, string_to_num(parts[1])?)),
+        _ => None
+    }
+}
+
+/// Tokenize a string variable declaration, e.g `S{N}` for a string of length `N`. The length
+/// follows the same syntax as an array's length. A trailing `@palindrome` (e.g `S{N}@palindrome`)
+/// makes the string a palindrome.
+///
+/// # Arguments
+/// - `item`: the declaration
+///
+/// # Returns
+/// An `Option` containing the string's name, length and whether it is a palindrome when the
+/// declaration is valid.
+pub(crate) fn tokenize_string_decl(item: &str) -> Option<(String, LenExpr, bool)> {
+    let (item, palindrome) = match item.strip_suffix(PALINDROME_MARKER) {
+        Some(stripped) => (stripped, true),
+        None => (item, false)
+    };
+    let (name, len) = item.strip_suffix(STRING_LEN_END)?.split_once(STRING_LEN_START)?;
+    Some((string_to_len_variable(name)?, string_to_len_expr(len)?, palindrome))
+}
+
+/// Tokenize a tree declaration, e.g `G[N]#` for a tree on `N` nodes, with the `tree` keyword
+/// already stripped. The amount of nodes follows the same syntax as an array's length.
+///
+/// # Arguments
+/// - `item`: the declaration
+///
+/// # Returns
+/// An `Option` containing the tree's name and amount of nodes when the declaration is valid.
+pub(crate) fn tokenize_tree_decl(item: &str) -> Option<(String, LenExpr)> {
+    match string_to_variable(item)? {
+        ExprVariable::Array(name, nodes, flags) if flags == VarFlags::default() => Some((name, nodes)),
+        _ => None
+    }
+}
+
+/// Tokenize an exclusion constraint between two variables, e.g `A != B`.
+///
+/// # Arguments
+/// - `line`: the constraint's line
+///
+/// # Returns
+/// An `Option` containing both variables' names when the constraint is valid.
+pub(crate) fn tokenize_exclusion(line: &str) -> Option<(String, String)> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() != 3 || parts[1] != NOT_EQUAL || parts[0] == parts[2] {
+        return None
+    }
+    Some((string_to_len_variable(parts[0])?, string_to_len_variable(parts[2])?))
+}
+
+/// Resolve the escape sequences of a separator: `\n`, `\t`, `\r`, `\s` (a space, since values
+/// are trimmed) and `\\`.
+///
+/// # Arguments
+/// - `value`: the separator as written
+///
+/// # Returns
+/// An `Option` containing the separator, or `None` on an unknown or unfinished escape sequence.
+pub fn unescape(value: &str) -> Option<String> {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue
+        }
+        result.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            's' => ' ',
+            '\\' => '\\',
+            _ => return None
+        });
+    }
+    Some(result)
+}
+
+/// Split a line of comparison expression into the items `tokenize` takes. Comparison operators
+/// (and the `=<` alias) are items of their own even without surrounding whitespace (e.g
+/// `0<=A<=100`), and whitespace around the commas of a variable group is dropped (e.g `A, B`).
+///
+/// # Arguments
+/// - `line`: line of expression
+///
+/// # Returns
+/// The items of the line, in order.
+fn lex_expr_line(line: &str) -> Vec<String> {
+    let mut spaced = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if LESS_THAN.starts_with(ch) {
+            spaced.push(' ');
+            spaced.push(ch);
+            // Keep `<=` whole.
+            if let Some(next) = chars.next_if(|next| LESS_THAN_OR_EQUAL_TO.ends_with(*next)) {
+                spaced.push(next);
+            }
+            spaced.push(' ');
+        } else if LESS_THAN_OR_EQUAL_TO_ALIAS.starts_with(ch) && chars.peek().is_some_and(|next| LESS_THAN_OR_EQUAL_TO_ALIAS.ends_with(*next)) {
+            chars.next();
+            spaced.push_str(&format!(" {} ", LESS_THAN_OR_EQUAL_TO_ALIAS));
+        } else {
+            spaced.push(ch);
+        }
+    }
+
+    let mut items: Vec<String> = Vec::new();
+    for item in spaced.split_whitespace() {
+        match items.last_mut() {
+            Some(last) if last.ends_with(GROUP_SEPARATOR) || item.starts_with(GROUP_SEPARATOR) => last.push_str(item),
+            _ => items.push(item.to_string())
+        }
+    }
+    items
+}
+
+/// Whether a line of comparison expression spells `<=` as its `=<` alias, which callers warn about.
+///
+/// # Arguments
+/// - `line`: line of expression
+pub(crate) fn uses_lte_alias(line: &str) -> bool {
+    lex_expr_line(line).iter().any(|item| item == LESS_THAN_OR_EQUAL_TO_ALIAS)
+}
+
 /// Tokenize a line of comparison expression, e.g `"3 < A < 100"`. Caller should return an
 /// `AppError::InvalidExpression` when this returns `None`.
 ///
@@ -137,8 +554,8 @@ pub(crate) fn tokenize(item: &str) -> Option<Token> {
 /// An `Option` containing vector of `Token`s when parsing is successful.
 pub(crate) fn tokenize_expr_line(line: &str) -> Option<VecDeque<Token>> {
     let mut tokens = VecDeque::new();
-    let tokens_val = line.split_whitespace();
-    for val in tokens_val {
+    let tokens_val = lex_expr_line(line);
+    for val in &tokens_val {
         if let Some(token) = tokenize(val) {
             tokens.push_back(token);
         } else {
@@ -148,22 +565,194 @@ pub(crate) fn tokenize_expr_line(line: &str) -> Option<VecDeque<Token>> {
     Some(tokens)
 }
 
+/// Tokenize a line written as an interval, e.g `A in [1, 100]`, into the tokens of the equivalent
+/// comparison chain (here `1 <= A <= 100`). A square bracket includes its endpoint and a
+/// parenthesis excludes it, so `A in (0, 100)` is `0 < A < 100` and `A in [1, 100)` is
+/// `1 <= A < 100`.
+///
+/// # Arguments
+/// - `line`: line of expression
+///
+/// # Returns
+/// An `Option` containing vector of `Token`s when the line is an interval.
+pub(crate) fn tokenize_interval_line(line: &str) -> Option<VecDeque<Token>> {
+    let (group, interval) = line.split_once(INTERVAL_KEYWORD)?;
+    let interval = interval.trim();
+    let min_comparison = match interval.chars().next()? {
+        INCLUSIVE_START => ComparisonType::LessThanOrEqualTo,
+        EXCLUSIVE_START => ComparisonType::LessThan,
+        _ => return None
+    };
+    let max_comparison = match interval.chars().last()? {
+        INCLUSIVE_END => ComparisonType::LessThanOrEqualTo,
+        EXCLUSIVE_END => ComparisonType::LessThan,
+        _ => return None
+    };
+    let (min, max) = interval[1..interval.len() - 1].split_once(GROUP_SEPARATOR)?;
+
+    // Endpoints may be `define` names, which are tokenized as variable groups until resolved.
+    let endpoint = |item: &str| tokenize(item.trim()).filter(|token| !matches!(token, Token::Comparison(_)));
+    let group = group.split_whitespace().collect::<String>();
+    let Some(Token::VariableGroup(vars)) = tokenize(&group) else {
+        return None
+    };
+    Some(VecDeque::from([endpoint(min)?, Token::Comparison(min_comparison), Token::VariableGroup(vars), Token::Comparison(max_comparison), endpoint(max)?]))
+}
+
+/// Tokenize a line picking a variable's value from a list, e.g `K in {1,2,4,8}`, into the tokens
+/// of the equivalent comparison chain, from the smallest listed value to the largest, holding a
+/// single `ExprVariable::Choice`.
+///
+/// # Arguments
+/// - `line`: line of expression
+///
+/// # Returns
+/// An `Option` containing vector of `Token`s when the line is a list of choices for a single plain
+/// variable, with at least one value.
+pub(crate) fn tokenize_choice_line(line: &str) -> Option<VecDeque<Token>> {
+    let (name, choices) = line.split_once(INTERVAL_KEYWORD)?;
+    let choices = choices.trim().strip_prefix(CHOICES_START)?.strip_suffix(CHOICES_END)?;
+    let mut values = choices.split(GROUP_SEPARATOR).map(|value| string_to_num(value.trim())).collect::<Option<Vec<Value>>>()?;
+    values.sort_unstable();
+    values.dedup();
+
+    let Some(Token::VariableGroup(vars)) = tokenize(name.trim()) else {
+        return None
+    };
+    let [ExprVariable::Variable(name, flags)] = vars.as_slice() else {
+        return None
+    };
+    if *flags != VarFlags::default() {
+        return None
+    }
+    let (min, max) = (*values.first()?, *values.last()?);
+    let choice = ExprVariable::Choice(name.clone(), values);
+    Some(VecDeque::from([Token::NumValue(min), Token::Comparison(ComparisonType::LessThanOrEqualTo), Token::VariableGroup(vec![choice]), Token::Comparison(ComparisonType::LessThanOrEqualTo), Token::NumValue(max)]))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_string_to_variable() {
-        assert_eq!(string_to_variable("variable"), Some(ExprVariable::Variable("variable".into())));
-        assert_eq!(string_to_variable("some_variable_123"), Some(ExprVariable::Variable("some_variable_123".into())));
-        assert_eq!(string_to_variable("array[100]#"), Some(ExprVariable::Array("array".into(), LenExpr::Constant(100))));
-        assert_eq!(string_to_variable("array[N]#"), Some(ExprVariable::Array("array".into(), LenExpr::Variable("N".into()))));
+        assert_eq!(string_to_variable("variable"), Some(ExprVariable::Variable("variable".into(), VarFlags::default())));
+        assert_eq!(string_to_variable("some_variable_123"), Some(ExprVariable::Variable("some_variable_123".into(), VarFlags::default())));
+        assert_eq!(string_to_variable("array[100]#"), Some(ExprVariable::Array("array".into(), LenExpr::Constant(100), VarFlags::default())));
+        assert_eq!(string_to_variable("array[N]#"), Some(ExprVariable::Array("array".into(), LenExpr::Variable("N".into()), VarFlags::default())));
         assert_eq!(string_to_variable("this is invalid"), None);
         assert_eq!(string_to_variable("this[is not valid]"), None);
         assert_eq!(string_to_variable("this_is_not_valid[100]"), None);
         assert_eq!(string_to_variable("this_is_not[]valid"), None);
     }
 
+    #[test]
+    fn test_string_to_offset_array() {
+        assert_eq!(string_to_variable("A[N-1]#"), Some(ExprVariable::Array("A".into(), LenExpr::Expr("N".into(), LenOp::Sub, 1), VarFlags::default())));
+        assert_eq!(string_to_variable("A[N+2]#"), Some(ExprVariable::Array("A".into(), LenExpr::Expr("N".into(), LenOp::Add, 2), VarFlags::default())));
+        assert_eq!(string_to_variable("A[N+M]#"), None);
+        assert_eq!(string_to_variable("A[1+N]#"), None);
+        assert_eq!(string_to_variable("A[N-]#"), None);
+    }
+
+    #[test]
+    fn test_tokenize_string_decl() {
+        assert_eq!(tokenize_string_decl("S{N}"), Some(("S".into(), LenExpr::Variable("N".into()), false)));
+        assert_eq!(tokenize_string_decl("T{10}"), Some(("T".into(), LenExpr::Constant(10), false)));
+        assert_eq!(tokenize_string_decl("S{N-1}"), Some(("S".into(), LenExpr::Expr("N".into(), LenOp::Sub, 1), false)));
+        assert_eq!(tokenize_string_decl("P{N}@palindrome"), Some(("P".into(), LenExpr::Variable("N".into()), true)));
+        assert_eq!(tokenize_string_decl("P{N}@palindrome@palindrome"), None);
+        assert_eq!(tokenize_string_decl("P@palindrome{N}"), None);
+        assert_eq!(tokenize_string_decl("S{N"), None);
+        assert_eq!(tokenize_string_decl("{N}"), None);
+        assert_eq!(tokenize_string_decl("S[N]#"), None);
+    }
+
+    #[test]
+    fn test_tokenize_tree_decl() {
+        assert_eq!(tokenize_tree_decl("G[N]#"), Some(("G".into(), LenExpr::Variable("N".into()))));
+        assert_eq!(tokenize_tree_decl("G[10]#"), Some(("G".into(), LenExpr::Constant(10))));
+        assert_eq!(tokenize_tree_decl("G[N]#!"), None);
+        assert_eq!(tokenize_tree_decl("G[N][M]#"), None);
+        assert_eq!(tokenize_tree_decl("G"), None);
+    }
+
+    #[test]
+    fn test_string_to_matrix() {
+        assert_eq!(string_to_variable("A[N][M]#"), Some(ExprVariable::Matrix("A".into(), LenExpr::Variable("N".into()), LenExpr::Variable("M".into()), VarFlags::default())));
+        assert_eq!(string_to_variable("A[3][N-1]#!"), Some(ExprVariable::Matrix("A".into(), LenExpr::Constant(3), LenExpr::Expr("N".into(), LenOp::Sub, 1), VarFlags { distinct: true, ..Default::default() })));
+        assert_eq!(string_to_variable("A[N]M]#"), None);
+        assert_eq!(string_to_variable("A[N][M][K]#"), None);
+    }
+
+    #[test]
+    fn test_len_expr_resolve() {
+        let get_var = |key: &str| if key == "N" { Some(10) } else { None };
+        assert_eq!(LenExpr::Expr("N".into(), LenOp::Sub, 1).resolve(get_var), Some(9));
+        assert_eq!(LenExpr::Expr("N".into(), LenOp::Add, 2).resolve(get_var), Some(12));
+        assert_eq!(LenExpr::Variable("N".into()).resolve(get_var), Some(10));
+        assert_eq!(LenExpr::Constant(5).resolve(get_var), Some(5));
+        assert_eq!(LenExpr::Variable("M".into()).resolve(get_var), None);
+    }
+
+    #[test]
+    fn test_string_to_distinct_variable() {
+        let distinct = VarFlags { distinct: true, ..Default::default() };
+        assert_eq!(string_to_variable("A!"), Some(ExprVariable::Variable("A".into(), distinct)));
+        assert_eq!(string_to_variable("A[10]#!"), Some(ExprVariable::Array("A".into(), LenExpr::Constant(10), distinct)));
+        assert_eq!(string_to_variable("A!!"), None);
+        assert_eq!(string_to_variable("!"), None);
+    }
+
+    #[test]
+    fn test_string_to_sorted_variable() {
+        let sorted = VarFlags { sorted: true, ..Default::default() };
+        let strictly_sorted = VarFlags { sorted: true, distinct: true, ..Default::default() };
+        assert_eq!(string_to_variable("A[N]#+"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), sorted)));
+        assert_eq!(string_to_variable("A[N]#++"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), strictly_sorted)));
+        assert_eq!(string_to_variable("A[N]#+!"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), strictly_sorted)));
+        assert_eq!(string_to_variable("A[N]#+++"), None);
+        assert_eq!(string_to_variable("A+"), None);
+    }
+
+    #[test]
+    fn test_string_to_log_variable() {
+        let log = VarFlags { log: true, ..Default::default() };
+        let sorted_log = VarFlags { log: true, sorted: true, ..Default::default() };
+        assert_eq!(string_to_variable("N@log"), Some(ExprVariable::Variable("N".into(), log)));
+        assert_eq!(string_to_variable("A[N]#@log"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), log)));
+        assert_eq!(string_to_variable("A[N]#+@log"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), sorted_log)));
+        assert_eq!(string_to_variable("A[N]#@log+"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), sorted_log)));
+        assert_eq!(string_to_variable("N!@log"), None);
+        assert_eq!(string_to_variable("N@log@log"), None);
+    }
+
+    #[test]
+    fn test_string_to_sum_capped_variable() {
+        let capped = VarFlags { sum_cap: Some(1000000), ..Default::default() };
+        let sorted_capped = VarFlags { sorted: true, sum_cap: Some(50), ..Default::default() };
+        assert_eq!(string_to_variable("A[N]#sum(10^6)"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), capped)));
+        assert_eq!(string_to_variable("A[N]#+sum(50)"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), sorted_capped)));
+        assert_eq!(string_to_variable("A[N]#!sum(50)"), None);
+        assert_eq!(string_to_variable("A[N][M]#sum(50)"), None);
+        assert_eq!(string_to_variable("Asum(50)"), None);
+        assert_eq!(string_to_variable("A[N]#sum(x)"), None);
+    }
+
+    #[test]
+    fn test_string_to_wrapped_variable() {
+        let wrapped = VarFlags { wrap: Some(3), ..Default::default() };
+        let capped_wrapped = VarFlags { sorted: true, sum_cap: Some(50), wrap: Some(3), ..Default::default() };
+        assert_eq!(string_to_variable("A[N]#wrap(3)"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), wrapped)));
+        assert_eq!(string_to_variable("A[N]#+sum(50)wrap(3)"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), capped_wrapped)));
+        assert_eq!(string_to_variable("A[N]#+wrap(3)sum(50)"), Some(ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), capped_wrapped)));
+        assert_eq!(string_to_variable("A[N]#wrap(3)wrap(3)"), None);
+        assert_eq!(string_to_variable("A[N]#wrap(0)"), None);
+        assert_eq!(string_to_variable("A[N][M]#wrap(3)"), None);
+        assert_eq!(string_to_variable("Awrap(3)"), None);
+        assert_eq!(string_to_variable("A[N]#size(3)"), None);
+    }
+
     #[test]
     fn test_tokenize() {
         assert_eq!(tokenize(" "), None);
@@ -175,6 +764,32 @@ mod tests {
         assert_eq!(tokenize("variable"), Some(Token::VariableGroup(vec!["variable".into()])));
     }
 
+    #[test]
+    fn test_tokenize_power() {
+        assert_eq!(tokenize("2^10"), Some(Token::NumValue(1024)));
+        assert_eq!(tokenize("10^18"), Some(Token::NumValue(1_000_000_000_000_000_000)));
+        assert_eq!(tokenize("10^19"), Some(Token::NumValue(10_000_000_000_000_000_000)));
+        assert_eq!(tokenize("10^39"), None);
+        assert_eq!(tokenize("-10^9"), Some(Token::NumValue(-1_000_000_000)));
+        assert_eq!(tokenize("-5"), Some(Token::NumValue(-5)));
+        assert_eq!(tokenize("10^"), None);
+        assert_eq!(tokenize("^9"), None);
+        assert_eq!(tokenize("10^-9"), None);
+        assert_eq!(tokenize("--5"), None);
+        assert_eq!(tokenize("-"), None);
+    }
+
+    #[test]
+    fn test_tokenize_define() {
+        assert_eq!(tokenize_define("MAXN 10^5"), Some(("MAXN".into(), 100000)));
+        assert_eq!(tokenize_define("  LOW   -3 "), Some(("LOW".into(), -3)));
+        assert_eq!(tokenize_define("MAXN"), None);
+        assert_eq!(tokenize_define("MAXN 1 2"), None);
+        assert_eq!(tokenize_define("A[10]# 5"), None);
+        assert_eq!(tokenize_define("1N 5"), None);
+        assert_eq!(tokenize_define("N N"), None);
+    }
+
     #[test]
     fn test_tokenize_line() {
         let line = "1 < A <= C,D <= 100000";
@@ -186,6 +801,64 @@ mod tests {
             Token::NumValue(100000)])));
     }
 
+    #[test]
+    fn test_tokenize_line_tight() {
+        assert_eq!(lex_expr_line("0<=A<=100"), vec!["0", "<=", "A", "<=", "100"]);
+        assert_eq!(tokenize_expr_line("0<=A<=100"), tokenize_expr_line("0 <= A <= 100"));
+        assert_eq!(tokenize_expr_line("1<=A,B<10^9"), tokenize_expr_line("1 <= A,B < 10^9"));
+        assert_eq!(tokenize_expr_line("1 <= A , B,C ,D <= 10"), tokenize_expr_line("1 <= A,B,C,D <= 10"));
+        assert_eq!(tokenize_expr_line("1< A<=10"), tokenize_expr_line("1 < A <= 10"));
+        assert_eq!(tokenize_expr_line("-5<A[N]#,B<=C@log<-1"), tokenize_expr_line("-5 < A[N]#,B <= C@log < -1"));
+        assert!(tokenize_expr_line("1 <== A <= 10").is_none());
+    }
+
+    #[test]
+    fn test_tokenize_alias() {
+        assert_eq!(tokenize("=<"), Some(Token::Comparison(ComparisonType::LessThanOrEqualTo)));
+        assert_eq!(lex_expr_line("0=<A=<100"), vec!["0", "=<", "A", "=<", "100"]);
+        assert_eq!(tokenize_expr_line("1 =< A,B < 10"), tokenize_expr_line("1 <= A,B < 10"));
+        assert_eq!(tokenize_expr_line("1=<A<=10"), tokenize_expr_line("1 <= A <= 10"));
+        assert!(uses_lte_alias("1=<A<=10"));
+        assert!(!uses_lte_alias("1 <= A <= 10"));
+        // There is no greater-than comparison to alias.
+        assert!(tokenize_expr_line("10 => A => 1").is_none());
+    }
+
+    #[test]
+    fn test_tokenize_interval_line() {
+        assert_eq!(tokenize_interval_line("A in [1, 100]"), tokenize_expr_line("1 <= A <= 100"));
+        assert_eq!(tokenize_interval_line("A in (0, 100)"), tokenize_expr_line("0 < A < 100"));
+        assert_eq!(tokenize_interval_line("A in [1, 100)"), tokenize_expr_line("1 <= A < 100"));
+        assert_eq!(tokenize_interval_line("A in (0,100]"), tokenize_expr_line("0 < A <= 100"));
+        assert_eq!(tokenize_interval_line("A, B[N]#  in  [-10^9, MAXV]"), tokenize_expr_line("-10^9 <= A,B[N]# <= MAXV"));
+        assert_eq!(tokenize_interval_line("A in [1, 100"), None);
+        assert_eq!(tokenize_interval_line("A in {1, 100}"), None);
+        assert_eq!(tokenize_interval_line("A in [1 100]"), None);
+        assert_eq!(tokenize_interval_line("A in [<, 100]"), None);
+        assert_eq!(tokenize_interval_line("1 in [1, 100]"), None);
+        assert_eq!(tokenize_interval_line("1 <= A <= 100"), None);
+    }
+
+    #[test]
+    fn test_tokenize_choice_line() {
+        let choice = |values: Vec<Value>| Token::VariableGroup(vec![ExprVariable::Choice("K".into(), values)]);
+        let tokens = tokenize_choice_line("K in {8, 2,4,1,4}").unwrap();
+        assert_eq!(tokens, VecDeque::from([
+            Token::NumValue(1), Token::Comparison(ComparisonType::LessThanOrEqualTo),
+            choice(vec![1, 2, 4, 8]),
+            Token::Comparison(ComparisonType::LessThanOrEqualTo), Token::NumValue(8)]));
+        assert_eq!(tokenize_choice_line("K  in {-10^9}").unwrap()[2], choice(vec![-1000000000]));
+        assert_eq!(ExprVariable::Choice("K".into(), vec![1, 2, 4, 8]).to_string(), "K in {1,2,4,8}");
+
+        assert_eq!(tokenize_choice_line("K in {}"), None);
+        assert_eq!(tokenize_choice_line("K in {1,,2}"), None);
+        assert_eq!(tokenize_choice_line("K in {1, X}"), None);
+        assert_eq!(tokenize_choice_line("K in [1, 2]"), None);
+        assert_eq!(tokenize_choice_line("K! in {1, 2}"), None);
+        assert_eq!(tokenize_choice_line("A[N]# in {1, 2}"), None);
+        assert_eq!(tokenize_choice_line("K,L in {1, 2}"), None);
+    }
+
     #[test]
     fn test_tokenize_line_invalid() {
         let line_invalid = "3.4 < 123 != 2_XYZ";
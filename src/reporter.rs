@@ -0,0 +1,305 @@
+//! Per-test terminal output. By default, only failures, crashes, errors and slow tests get their
+//! own lines, plus a heartbeat line with the running counts every `HEARTBEAT_INTERVAL` tests, so
+//! that very large runs aren't slowed down by printing. With `--verbose`, every test gets its own
+//! line. With `--progress`, a single line is rewritten in place (using a carriage return) with
+//! the running counts instead of the heartbeat. With `--quiet`, nothing is printed until the
+//! final summary. With `--format tap`, every test gets a Test Anything Protocol line whatever the
+//! other options, and the plan is printed once every test ran.
+
+use std::io::{stderr, stdout, Write};
+
+use beanfuzz::AppResultData;
+
+use crate::cli::{CLIArgs, OutputFormat};
+
+/// Moves the cursor to the start of the line and clears the line.
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
+/// Amount of tests between two heartbeat lines by default.
+const HEARTBEAT_INTERVAL: u64 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ReportMode {
+    /// A heartbeat line every `HEARTBEAT_INTERVAL` tests, plus a line for every failure, crash
+    /// and error.
+    Heartbeat,
+    /// A line for every test.
+    Lines,
+    /// A single progress line, plus a line for every failure, crash and error.
+    Progress,
+    /// No per-test output.
+    Quiet,
+    /// A TAP line for every test.
+    Tap,
+}
+
+pub(crate) struct Reporter {
+    mode: ReportMode,
+    /// Where the per-test lines go, stdout outside of tests.
+    out: Box<dyn Write>,
+    /// Total amount of tests to run, unknown when running until a time budget elapses.
+    total: Option<u64>,
+    /// Whether the progress line is currently shown, so it has to be cleared before printing.
+    progress_shown: bool,
+    /// Whether tests skipped due to an error are failures, see `--error-is-failure`.
+    error_is_failure: bool,
+    /// Amount of tests reported so far, for the TAP plan.
+    reported: u64,
+}
+
+/// Format the progress line.
+///
+/// # Arguments
+/// - `total`: total amount of tests to run, if known
+/// - `result`: the counts so far
+fn progress_line(total: Option<u64>, result: &AppResultData) -> String {
+    let done = result.successful_tests + result.failed_tests + result.crashed_tests + result.error_tests;
+    let done = match total {
+        Some(total) => format!("{}/{}", done, total),
+        None => done.to_string()
+    };
+    format!("[{}] ok: {}, failed: {}, crashed: {}, error: {}", done, result.successful_tests, result.failed_tests, result.crashed_tests, result.error_tests)
+}
+
+/// Format the TAP line of a passing test.
+fn tap_ok(test_no: u64) -> String {
+    format!("ok {}", test_no)
+}
+
+/// Format the TAP line of a test that didn't pass, followed by its diagnostic as an indented YAML
+/// block.
+///
+/// # Arguments
+/// - `test_no`: the (1-indexed) test number
+/// - `description`: what went wrong, e.g `output mismatch`
+/// - `message`: the details, as reported without TAP
+fn tap_not_ok(test_no: u64, description: &str, message: &str) -> String {
+    // A JSON string is a valid YAML string, and escapes whatever the message holds.
+    format!("not ok {} - {}\n  ---\n  message: {}\n  ...", test_no, description, serde_json::Value::from(message))
+}
+
+/// Format the TAP line of a test skipped due to an error.
+///
+/// # Arguments
+/// - `test_no`: the (1-indexed) test number
+/// - `message`: the error, as reported without TAP
+fn tap_skip(test_no: u64, message: &str) -> String {
+    // A newline would end the directive early.
+    format!("ok {} # SKIP {}", test_no, message.replace('\n', " "))
+}
+
+impl Reporter {
+    pub(crate) fn new(args: &CLIArgs, total: Option<u64>) -> Self {
+        let mode = if args.format == OutputFormat::Tap {
+            ReportMode::Tap
+        } else if args.quiet {
+            ReportMode::Quiet
+        } else if args.progress {
+            ReportMode::Progress
+        } else if args.verbose {
+            ReportMode::Lines
+        } else {
+            ReportMode::Heartbeat
+        };
+
+        Self::with_output(mode, total, args.error_is_failure, Box::new(stdout()))
+    }
+
+    fn with_output(mode: ReportMode, total: Option<u64>, error_is_failure: bool, out: Box<dyn Write>) -> Self {
+        Self { mode, out, total, progress_shown: false, error_is_failure, reported: 0 }
+    }
+
+    fn line(&mut self, line: &str) {
+        // Like `println!`, but a closed stdout isn't worth panicking over.
+        let _ = writeln!(self.out, "{}", line);
+    }
+
+    /// Report a passing test, which only gets its own line when verbose.
+    pub(crate) fn success(&mut self, test_no: u64) {
+        self.reported += 1;
+        match self.mode {
+            ReportMode::Lines => self.line(&format!("Test #{} succeeded", test_no)),
+            ReportMode::Tap => self.line(&tap_ok(test_no)),
+            ReportMode::Heartbeat | ReportMode::Progress | ReportMode::Quiet => ()
+        }
+    }
+
+    /// Report a failure or crash, which gets its own line unless quiet.
+    ///
+    /// # Arguments
+    /// - `test_no`: the (1-indexed) test number
+    /// - `description`: what went wrong in a few words, e.g `output mismatch`, for TAP
+    /// - `message`: the line to report
+    pub(crate) fn problem(&mut self, test_no: u64, description: &str, message: &str) {
+        self.reported += 1;
+        if self.mode == ReportMode::Tap {
+            self.line(&tap_not_ok(test_no, description, message));
+        } else {
+            self.print_problem(message);
+        }
+    }
+
+    /// Report a test skipped due to an error, which gets its own line unless quiet. With TAP, it
+    /// is a skipped test unless errors are failures.
+    pub(crate) fn error(&mut self, test_no: u64, message: &str) {
+        self.reported += 1;
+        match self.mode {
+            ReportMode::Tap if self.error_is_failure => self.line(&tap_not_ok(test_no, "error", message)),
+            ReportMode::Tap => self.line(&tap_skip(test_no, message)),
+            _ => self.print_problem(message)
+        }
+    }
+
+    /// Report a slow test (see `--tle-ratio`), which gets its own line unless quiet. With TAP, it
+    /// is a comment, as the test still gets its own result line.
+    pub(crate) fn slow(&mut self, message: &str) {
+        if self.mode == ReportMode::Tap {
+            self.line(&format!("# {}", message));
+        } else {
+            self.print_problem(message);
+        }
+    }
+
+    fn print_problem(&mut self, message: &str) {
+        if self.mode == ReportMode::Quiet {
+            return
+        }
+        if self.progress_shown {
+            eprint!("{}", CLEAR_LINE);
+            self.progress_shown = false;
+        }
+        self.line(message);
+    }
+
+    /// Redraw the progress line (on stderr) after a test finished, or print the heartbeat line if
+    /// it is due.
+    pub(crate) fn update(&mut self, result: &AppResultData) {
+        if self.mode == ReportMode::Heartbeat {
+            let done = result.successful_tests + result.failed_tests + result.crashed_tests + result.error_tests;
+            if done.is_multiple_of(HEARTBEAT_INTERVAL) {
+                self.line(&progress_line(self.total, result));
+            }
+            return
+        }
+        if self.mode != ReportMode::Progress {
+            return
+        }
+        eprint!("{}{}", CLEAR_LINE, progress_line(self.total, result));
+        // The progress line has no newline, so it wouldn't show up otherwise.
+        let _ = stderr().flush();
+        self.progress_shown = true;
+    }
+
+    /// End the progress line, so the summary starts on its own line. With TAP, print the plan.
+    pub(crate) fn finish(&mut self) {
+        if self.progress_shown {
+            eprintln!();
+            self.progress_shown = false;
+        }
+        if self.mode == ReportMode::Tap {
+            let plan = format!("1..{}", self.reported);
+            self.line(&plan);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Collects what a reporter prints, while the test still holds onto it.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Report `count` passing tests, returning what got printed.
+    fn report_successes(mode: ReportMode, count: u64) -> String {
+        let output = SharedOutput::default();
+        let mut reporter = Reporter::with_output(mode, Some(count), false, Box::new(output.clone()));
+        let mut result = AppResultData::new(None, 0, OutputFormat::Text);
+        for test_no in 1..=count {
+            result.successful_tests += 1;
+            reporter.success(test_no);
+            reporter.update(&result);
+        }
+        reporter.finish();
+        let printed = output.0.borrow().clone();
+        String::from_utf8(printed).unwrap()
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let printed = report_successes(ReportMode::Heartbeat, 25_000);
+        let lines: Vec<_> = printed.lines().collect();
+        assert_eq!(lines.len(), 25, "{}", printed);
+        assert_eq!(lines[0], "[1000/25000] ok: 1000, failed: 0, crashed: 0, error: 0");
+        assert_eq!(lines[24], "[25000/25000] ok: 25000, failed: 0, crashed: 0, error: 0");
+        assert!(!printed.contains("succeeded"), "{}", printed);
+
+        let printed = report_successes(ReportMode::Lines, 3);
+        assert_eq!(printed, "Test #1 succeeded\nTest #2 succeeded\nTest #3 succeeded\n");
+    }
+
+    #[test]
+    fn test_progress_line() {
+        let mut result = AppResultData::new(None, 0, OutputFormat::Text);
+        result.successful_tests = 5;
+        result.failed_tests = 2;
+        result.error_tests = 1;
+        assert_eq!(progress_line(Some(20), &result), "[8/20] ok: 5, failed: 2, crashed: 0, error: 1");
+        assert_eq!(progress_line(None, &result), "[8] ok: 5, failed: 2, crashed: 0, error: 1");
+    }
+
+    #[test]
+    fn test_tap_stream() {
+        let output = SharedOutput::default();
+        let mut reporter = Reporter::with_output(ReportMode::Tap, Some(5), false, Box::new(output.clone()));
+        reporter.success(1);
+        reporter.problem(2, "output mismatch", "Test #2 failed! See log file for details.");
+        reporter.error(3, "An error occurred with test #3: Input too large,\nskipping..");
+        reporter.slow("Test #4 is slow: executable A ran 3.0x longer than the other");
+        reporter.problem(4, "crash", "Test #4 crashed: executable A exited with \"signal: 9\"");
+        reporter.success(5);
+        reporter.finish();
+        let stream = String::from_utf8(output.0.borrow().clone()).unwrap();
+
+        // Test lines start at column 0, diagnostics are indented under them.
+        let (mut passed, mut skipped, mut failed, mut plan) = (Vec::new(), Vec::new(), Vec::new(), None);
+        for line in stream.lines().filter(|line| !line.starts_with(' ') && !line.starts_with('#')) {
+            if let Some(rest) = line.strip_prefix("not ok ") {
+                failed.push(rest.split(" - ").next().unwrap().parse::<u64>().unwrap());
+            } else if let Some(rest) = line.strip_prefix("ok ") {
+                match rest.split_once(" # SKIP ") {
+                    Some((test_no, _)) => skipped.push(test_no.parse::<u64>().unwrap()),
+                    None => passed.push(rest.parse::<u64>().unwrap())
+                }
+            } else {
+                plan = Some(line.strip_prefix("1..").unwrap().parse::<u64>().unwrap());
+            }
+        }
+        assert_eq!((passed, skipped, failed, plan), (vec![1, 5], vec![3], vec![2, 4], Some(5)), "{}", stream);
+        assert!(stream.ends_with("\n1..5\n"), "{}", stream);
+        assert!(stream.contains("ok 3 # SKIP An error occurred with test #3: Input too large, skipping..\n"), "{}", stream);
+        assert!(stream.contains("not ok 2 - output mismatch\n  ---\n  message: \"Test #2 failed! See log file for details.\"\n  ...\n"), "{}", stream);
+        assert!(stream.contains("# Test #4 is slow: executable A ran 3.0x longer than the other\nnot ok 4 - crash\n  ---\n  message: \"Test #4 crashed: executable A exited with \\\"signal: 9\\\"\"\n  ...\n"), "{}", stream);
+
+        // Errors are failures too when asked to.
+        let output = SharedOutput::default();
+        let mut reporter = Reporter::with_output(ReportMode::Tap, Some(1), true, Box::new(output.clone()));
+        reporter.error(1, "An error occurred with test #1");
+        reporter.finish();
+        let stream = String::from_utf8(output.0.borrow().clone()).unwrap();
+        assert_eq!(stream, "not ok 1 - error\n  ---\n  message: \"An error occurred with test #1\"\n  ...\n1..1\n");
+    }
+}
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use beanfuzz::{AppError, ComparisonType, ExprVariable, FuzzData, FuzzExprBuilder, Generator, LenExpr, Runner, RunnerOptions, RunnerResult, Value, VarFlags};
+
+fn parse(lines: &[&str]) -> FuzzData {
+    FuzzData::parse(" ".into(), " ".into(), lines.iter().map(|line| line.to_string())).unwrap()
+}
+
+#[test]
+fn test_generate_through_public_api() {
+    let data = parse(&["1 <= N <= 10", "-5 <= A[N]# <= 5", "S{N}", "input order: N A S"]);
+    let mut options = RunnerOptions::default();
+    options.seed = 7;
+    let mut generator = Generator::new(data, &options);
+
+    for _ in 0..100 {
+        let input = generator.generate().unwrap().to_string();
+        let state = generator.get_state();
+        let n = *state.get_var("N").unwrap();
+        let arr = state.get_arr("A").unwrap();
+        let string = state.get_str("S").unwrap();
+
+        assert!((1..=10).contains(&n));
+        assert_eq!(arr.len() as Value, n);
+        assert!(arr.iter().all(|val| (-5..=5).contains(val)));
+        assert_eq!(string.len() as Value, n);
+
+        let arr: Vec<String> = arr.iter().map(Value::to_string).collect();
+        assert_eq!(input, format!("{} {} {}", n, arr.join(" "), string));
+        assert_eq!(generator.get_last_input(), input);
+    }
+}
+
+#[test]
+fn test_seeded_generators_agree() {
+    let lines = ["1 <= N <= 10^9", "input order: N"];
+    let mut options = RunnerOptions::default();
+    options.seed = 1234;
+    let mut generator_1 = Generator::new(parse(&lines), &options);
+    let mut generator_2 = Generator::new(parse(&lines), &options);
+    for _ in 0..10 {
+        assert_eq!(generator_1.generate().unwrap(), generator_2.generate().unwrap());
+    }
+}
+
+#[test]
+fn test_parse_error() {
+    let lines = ["1 <= N <= 10", "input order: N M"].map(String::from);
+    assert_eq!(FuzzData::parse(" ".into(), " ".into(), lines).unwrap_err(), AppError::UndeclaredVariable("M".into()));
+}
+
+#[test]
+fn test_run_through_public_api() {
+    let data = parse(&["1 <= N <= 10", "input order: N"]);
+    let mut runner = Runner::new(data, PathBuf::from("/bin/cat"), PathBuf::from("/bin/cat"), RunnerOptions::default());
+    assert!(matches!(runner.run_once(), Ok(RunnerResult::Ok)));
+}
+
+#[test]
+fn test_build_without_a_spec() {
+    let n = FuzzExprBuilder::new(1)
+        .group(ComparisonType::LessThanOrEqualTo, vec![ExprVariable::Variable("N".into(), VarFlags::default())])
+        .build(ComparisonType::LessThanOrEqualTo, 10)
+        .unwrap();
+    let n_clone = n.clone();
+    let arr = FuzzExprBuilder::new(-5)
+        .group(ComparisonType::LessThanOrEqualTo, vec![ExprVariable::Array("A".into(), LenExpr::Variable("N".into()), VarFlags::default())])
+        .build(ComparisonType::LessThanOrEqualTo, 5)
+        .unwrap();
+    // Declared in any order, like in a spec.
+    let built = FuzzData::from_exprs(vec![arr, n], vec!["N".into(), "A".into()], " ".into(), " ".into()).unwrap();
+    let parsed = parse(&["1 <= N <= 10", "-5 <= A[N]# <= 5", "input order: N A"]);
+
+    let mut options = RunnerOptions::default();
+    options.seed = 3;
+    let mut generator_1 = Generator::new(built, &options);
+    let mut generator_2 = Generator::new(parsed, &options);
+    for _ in 0..10 {
+        assert_eq!(generator_1.generate().unwrap(), generator_2.generate().unwrap());
+    }
+
+    let result = FuzzData::from_exprs(vec![n_clone], vec!["M".into()], " ".into(), " ".into());
+    assert_eq!(result.unwrap_err(), AppError::UndeclaredVariable("M".into()));
+}